@@ -25,6 +25,10 @@ pub enum Person {
     CanHost,
     IsChild,
     HouseholdId,
+    SignedUpAt,
+    UpdatedAt,
+    DietaryNotes,
+    Source,
 }
 
 #[derive(DeriveIden)]
@@ -37,6 +41,9 @@ pub enum Household {
     IsSignedUp,
     CanHost,
     AvatarUrl,
+    SignedUpAt,
+    UpdatedAt,
+    Source,
 }
 
 #[derive(DeriveIden)]
@@ -48,6 +55,7 @@ pub enum PotluckSeries {
     StartDate,
     EndDate,
     Description,
+    GroupSize,
 }
 
 #[derive(DeriveIden)]
@@ -59,6 +67,9 @@ pub enum Potluck {
     PotluckSeriesId,
     HostPersonId,
     HostHouseholdId,
+    Round,
+    CancelledAt,
+    HostCancelledAt,
 }
 
 #[derive(DeriveIden)]
@@ -69,6 +80,9 @@ pub enum Attendance {
     OrganizationId,
     AttendeePersonId,
     AttendeeHouseholdId,
+    CancelledAt,
+    MovedFromPotluckId,
+    Dish,
 }
 
 #[derive(DeriveIden)]
@@ -80,6 +94,16 @@ pub enum User {
     AccessToken,
     RefreshToken,
     TokenExpiresAt,
+    TokenKeyId,
+    SessionSecret,
+    Role,
+    AccountId,
+}
+
+#[derive(DeriveIden)]
+pub enum Account {
+    Table,
+    Id,
 }
 
 #[derive(DeriveIden)]
@@ -93,3 +117,105 @@ pub enum PairingHistory {
     EntityBPersonId,
     EntityBHouseholdId,
 }
+
+#[derive(DeriveIden)]
+pub enum Notification {
+    Table,
+    Id,
+    OrganizationId,
+    PersonId,
+    PotluckId,
+    Message,
+    ReadAt,
+}
+
+#[derive(DeriveIden)]
+pub enum PotluckRound {
+    Table,
+    Id,
+    OrganizationId,
+    PotluckSeriesId,
+    Round,
+    ScheduledOn,
+}
+
+#[derive(DeriveIden)]
+pub enum Unavailability {
+    Table,
+    Id,
+    OrganizationId,
+    PotluckSeriesId,
+    PersonId,
+    HouseholdId,
+    StartsOn,
+    EndsOn,
+    Note,
+}
+
+#[derive(DeriveIden)]
+pub enum Waitlist {
+    Table,
+    Id,
+    OrganizationId,
+    PotluckSeriesId,
+    Round,
+    PersonId,
+    HouseholdId,
+}
+
+#[derive(DeriveIden)]
+pub enum SeriesTemplate {
+    Table,
+    Id,
+    OrganizationId,
+    Name,
+    Description,
+    GroupSize,
+    LengthDays,
+    RoundOffsets,
+}
+
+#[derive(DeriveIden)]
+pub enum ApiToken {
+    Table,
+    Id,
+    OrganizationId,
+    UserId,
+    Name,
+    TokenHash,
+    Scopes,
+    ExpiresAt,
+    LastUsedAt,
+    RevokedAt,
+}
+
+#[derive(DeriveIden)]
+pub enum MergeRecord {
+    Table,
+    Id,
+    OrganizationId,
+    UserId,
+    Kind,
+    KeptId,
+    KeptName,
+    DuplicateId,
+    DuplicateName,
+    Duplicate,
+    Counts,
+}
+
+#[derive(DeriveIden)]
+pub enum AuditLog {
+    Table,
+    Id,
+    OrganizationId,
+    UserId,
+    Action,
+    Entity,
+    EntityId,
+    EntityName,
+    Field,
+    OldValue,
+    NewValue,
+    CreatedAt,
+}
@@ -4,6 +4,19 @@ mod iden;
 mod m20220101_000001_create_table;
 mod m20250405_153121_user_table;
 mod m20250715_040011_add_pairing_history_table;
+mod m20261018_090000_add_cancellations;
+mod m20261018_100000_add_rounds_and_availability;
+mod m20261018_110000_add_waitlist;
+mod m20261018_120000_add_series_templates;
+mod m20261018_130000_add_api_tokens;
+mod m20261018_140000_add_user_roles;
+mod m20261018_150000_add_dietary_notes;
+mod m20261018_160000_add_dish_assignments;
+mod m20261018_170000_add_record_sources;
+mod m20261018_180000_add_merge_records;
+mod m20261018_190000_add_audit_log;
+mod m20261018_200000_encrypt_user_tokens;
+mod m20261018_210000_add_accounts;
 
 pub struct Migrator;
 
@@ -14,6 +27,19 @@ impl MigratorTrait for Migrator {
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20250405_153121_user_table::Migration),
             Box::new(m20250715_040011_add_pairing_history_table::Migration),
+            Box::new(m20261018_090000_add_cancellations::Migration),
+            Box::new(m20261018_100000_add_rounds_and_availability::Migration),
+            Box::new(m20261018_110000_add_waitlist::Migration),
+            Box::new(m20261018_120000_add_series_templates::Migration),
+            Box::new(m20261018_130000_add_api_tokens::Migration),
+            Box::new(m20261018_140000_add_user_roles::Migration),
+            Box::new(m20261018_150000_add_dietary_notes::Migration),
+            Box::new(m20261018_160000_add_dish_assignments::Migration),
+            Box::new(m20261018_170000_add_record_sources::Migration),
+            Box::new(m20261018_180000_add_merge_records::Migration),
+            Box::new(m20261018_190000_add_audit_log::Migration),
+            Box::new(m20261018_200000_encrypt_user_tokens::Migration),
+            Box::new(m20261018_210000_add_accounts::Migration),
         ]
     }
 }
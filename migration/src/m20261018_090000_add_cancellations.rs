@@ -0,0 +1,152 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PotluckSeries::Table)
+                    .add_column(integer(PotluckSeries::GroupSize).default(8))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Potluck::Table)
+                    .add_column(integer(Potluck::Round).default(1))
+                    .add_column(timestamp_null(Potluck::CancelledAt))
+                    .add_column(timestamp_null(Potluck::HostCancelledAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Attendance::Table)
+                    .add_column(timestamp_null(Attendance::CancelledAt))
+                    .add_column(integer_null(Attendance::MovedFromPotluckId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_attendance_moved_from_potluck")
+                            .from_tbl(Attendance::Table)
+                            .from_col(Attendance::MovedFromPotluckId)
+                            .to_tbl(Potluck::Table)
+                            .to_col(Potluck::Id)
+                            .on_delete(ForeignKeyAction::SetNull),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_potluck_series_round")
+                    .table(Potluck::Table)
+                    .col(Potluck::PotluckSeriesId)
+                    .col(Potluck::Round)
+                    .to_owned(),
+            )
+            .await?;
+
+        let table = table_auto(Notification::Table)
+            .col(pk_auto(Notification::Id))
+            .col(integer(Notification::OrganizationId))
+            .col(integer(Notification::PersonId))
+            .col(integer_null(Notification::PotluckId))
+            .col(string(Notification::Message))
+            .col(timestamp_null(Notification::ReadAt))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_notification_organization")
+                    .from(Notification::Table, Notification::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_notification_person")
+                    .from(Notification::Table, Notification::PersonId)
+                    .to(Person::Table, Person::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_notification_potluck")
+                    .from(Notification::Table, Notification::PotluckId)
+                    .to(Potluck::Table, Potluck::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_person")
+                    .table(Notification::Table)
+                    .col(Notification::PersonId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Notification::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_potluck_series_round")
+                    .table(Potluck::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Attendance::Table)
+                    .drop_foreign_key(Alias::new("fk_attendance_moved_from_potluck"))
+                    .drop_column(Attendance::MovedFromPotluckId)
+                    .drop_column(Attendance::CancelledAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Potluck::Table)
+                    .drop_column(Potluck::HostCancelledAt)
+                    .drop_column(Potluck::CancelledAt)
+                    .drop_column(Potluck::Round)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PotluckSeries::Table)
+                    .drop_column(PotluckSeries::GroupSize)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = table_auto(PotluckRound::Table)
+            .col(pk_auto(PotluckRound::Id))
+            .col(integer(PotluckRound::OrganizationId))
+            .col(integer(PotluckRound::PotluckSeriesId))
+            .col(integer(PotluckRound::Round))
+            .col(date(PotluckRound::ScheduledOn))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_potluck_round_organization")
+                    .from(PotluckRound::Table, PotluckRound::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_potluck_round_series")
+                    .from(PotluckRound::Table, PotluckRound::PotluckSeriesId)
+                    .to(PotluckSeries::Table, PotluckSeries::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_potluck_round_series_round")
+                    .table(PotluckRound::Table)
+                    .col(PotluckRound::PotluckSeriesId)
+                    .col(PotluckRound::Round)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        let table = table_auto(Unavailability::Table)
+            .col(pk_auto(Unavailability::Id))
+            .col(integer(Unavailability::OrganizationId))
+            .col(integer(Unavailability::PotluckSeriesId))
+            .col(integer_null(Unavailability::PersonId))
+            .col(integer_null(Unavailability::HouseholdId))
+            .col(date(Unavailability::StartsOn))
+            .col(date(Unavailability::EndsOn))
+            .col(string_null(Unavailability::Note))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_unavailability_organization")
+                    .from(Unavailability::Table, Unavailability::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_unavailability_series")
+                    .from(Unavailability::Table, Unavailability::PotluckSeriesId)
+                    .to(PotluckSeries::Table, PotluckSeries::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_unavailability_person")
+                    .from(Unavailability::Table, Unavailability::PersonId)
+                    .to(Person::Table, Person::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_unavailability_household")
+                    .from(Unavailability::Table, Unavailability::HouseholdId)
+                    .to(Household::Table, Household::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .check(
+                Expr::col(Unavailability::PersonId)
+                    .is_not_null()
+                    .and(Expr::col(Unavailability::HouseholdId).is_null())
+                    .or(Expr::col(Unavailability::PersonId)
+                        .is_null()
+                        .and(Expr::col(Unavailability::HouseholdId).is_not_null())),
+            )
+            .check(Expr::col(Unavailability::EndsOn).gte(Expr::col(Unavailability::StartsOn)))
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_unavailability_series")
+                    .table(Unavailability::Table)
+                    .col(Unavailability::PotluckSeriesId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Unavailability::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(PotluckRound::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
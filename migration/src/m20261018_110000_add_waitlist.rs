@@ -0,0 +1,135 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Person::Table)
+                    .add_column(timestamp_null(Person::SignedUpAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Household::Table)
+                    .add_column(timestamp_null(Household::SignedUpAt))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Best guess for existing sign-ups is the last time the row changed.
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Person::Table)
+                    .value(Person::SignedUpAt, Expr::col(Person::UpdatedAt))
+                    .and_where(Expr::col(Person::IsSignedUp).eq(true))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(Household::Table)
+                    .value(Household::SignedUpAt, Expr::col(Household::UpdatedAt))
+                    .and_where(Expr::col(Household::IsSignedUp).eq(true))
+                    .to_owned(),
+            )
+            .await?;
+
+        let table = table_auto(Waitlist::Table)
+            .col(pk_auto(Waitlist::Id))
+            .col(integer(Waitlist::OrganizationId))
+            .col(integer(Waitlist::PotluckSeriesId))
+            .col(integer(Waitlist::Round))
+            .col(integer_null(Waitlist::PersonId))
+            .col(integer_null(Waitlist::HouseholdId))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_waitlist_organization")
+                    .from(Waitlist::Table, Waitlist::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_waitlist_series")
+                    .from(Waitlist::Table, Waitlist::PotluckSeriesId)
+                    .to(PotluckSeries::Table, PotluckSeries::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_waitlist_person")
+                    .from(Waitlist::Table, Waitlist::PersonId)
+                    .to(Person::Table, Person::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_waitlist_household")
+                    .from(Waitlist::Table, Waitlist::HouseholdId)
+                    .to(Household::Table, Household::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .check(
+                Expr::col(Waitlist::PersonId)
+                    .is_not_null()
+                    .and(Expr::col(Waitlist::HouseholdId).is_null())
+                    .or(Expr::col(Waitlist::PersonId)
+                        .is_null()
+                        .and(Expr::col(Waitlist::HouseholdId).is_not_null())),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_waitlist_series_round")
+                    .table(Waitlist::Table)
+                    .col(Waitlist::PotluckSeriesId)
+                    .col(Waitlist::Round)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Waitlist::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Household::Table)
+                    .drop_column(Household::SignedUpAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Person::Table)
+                    .drop_column(Person::SignedUpAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
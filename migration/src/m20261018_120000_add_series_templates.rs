@@ -0,0 +1,51 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = table_auto(SeriesTemplate::Table)
+            .col(pk_auto(SeriesTemplate::Id))
+            .col(integer(SeriesTemplate::OrganizationId))
+            .col(string(SeriesTemplate::Name))
+            .col(string_null(SeriesTemplate::Description))
+            .col(integer(SeriesTemplate::GroupSize))
+            .col(integer(SeriesTemplate::LengthDays))
+            .col(json(SeriesTemplate::RoundOffsets))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_series_template_organization")
+                    .from(SeriesTemplate::Table, SeriesTemplate::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_series_template_organization_name")
+                    .table(SeriesTemplate::Table)
+                    .col(SeriesTemplate::OrganizationId)
+                    .col(SeriesTemplate::Name)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(SeriesTemplate::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
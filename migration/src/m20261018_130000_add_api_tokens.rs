@@ -0,0 +1,58 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let table = table_auto(ApiToken::Table)
+            .col(pk_auto(ApiToken::Id))
+            .col(integer(ApiToken::OrganizationId))
+            .col(integer(ApiToken::UserId))
+            .col(string(ApiToken::Name))
+            .col(string_uniq(ApiToken::TokenHash))
+            .col(json(ApiToken::Scopes))
+            .col(timestamp_null(ApiToken::ExpiresAt))
+            .col(timestamp_null(ApiToken::LastUsedAt))
+            .col(timestamp_null(ApiToken::RevokedAt))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_api_token_organization")
+                    .from(ApiToken::Table, ApiToken::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_api_token_user")
+                    .from(ApiToken::Table, ApiToken::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_token_user")
+                    .table(ApiToken::Table)
+                    .col(ApiToken::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ApiToken::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // People and households no longer have to come from Planning Center,
+        // so the PCO id becomes optional and `source` says where a row came
+        // from. Existing rows were all synced.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Person::Table)
+                    .modify_column(ColumnDef::new(Person::PcoId).string().null())
+                    .add_column(string_len(Person::Source, 16).default("pco"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Household::Table)
+                    .modify_column(ColumnDef::new(Household::PcoId).string().null())
+                    .add_column(string_len(Household::Source, 16).default("pco"))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `pco_id` can only be NOT NULL again once every row has one, so
+        // local rows get a placeholder id first rather than being deleted
+        // along with their attendance and history. Planning Center never
+        // issues ids like `local-12`, so they can't collide with real ones;
+        // a resync reports those rows as missing from Planning Center.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE person SET pco_id = 'local-' || id WHERE pco_id IS NULL",
+        )
+        .await?;
+        db.execute_unprepared(
+            "UPDATE household SET pco_id = 'local-' || id WHERE pco_id IS NULL",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Person::Table)
+                    .modify_column(ColumnDef::new(Person::PcoId).string().not_null())
+                    .drop_column(Person::Source)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Household::Table)
+                    .modify_column(ColumnDef::new(Household::PcoId).string().not_null())
+                    .drop_column(Household::Source)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per merge of duplicate people or households. The removed
+        // record is kept as JSON so a bad merge can be put right by hand.
+        let table = table_auto(MergeRecord::Table)
+            .col(pk_auto(MergeRecord::Id))
+            .col(integer(MergeRecord::OrganizationId))
+            .col(integer_null(MergeRecord::UserId))
+            .col(string_len(MergeRecord::Kind, 16))
+            .col(integer(MergeRecord::KeptId))
+            .col(string(MergeRecord::KeptName))
+            .col(integer(MergeRecord::DuplicateId))
+            .col(string(MergeRecord::DuplicateName))
+            .col(json(MergeRecord::Duplicate))
+            .col(json(MergeRecord::Counts))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_merge_record_organization")
+                    .from(MergeRecord::Table, MergeRecord::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_merge_record_user")
+                    .from(MergeRecord::Table, MergeRecord::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_merge_record_organization")
+                    .table(MergeRecord::Table)
+                    .col(MergeRecord::OrganizationId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MergeRecord::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
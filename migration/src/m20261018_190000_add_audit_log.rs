@@ -0,0 +1,74 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // One row per changed field, or per record created, deleted or
+        // merged. The entity's name is copied in so the history still reads
+        // after the record itself is gone.
+        let table = table_auto(AuditLog::Table)
+            .col(pk_auto(AuditLog::Id))
+            .col(integer(AuditLog::OrganizationId))
+            .col(integer_null(AuditLog::UserId))
+            .col(string_len(AuditLog::Action, 16))
+            .col(string_len(AuditLog::Entity, 32))
+            .col(integer(AuditLog::EntityId))
+            .col(string(AuditLog::EntityName))
+            .col(string_null(AuditLog::Field))
+            .col(text_null(AuditLog::OldValue))
+            .col(text_null(AuditLog::NewValue))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_audit_log_organization")
+                    .from(AuditLog::Table, AuditLog::OrganizationId)
+                    .to(Organization::Table, Organization::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_audit_log_user")
+                    .from(AuditLog::Table, AuditLog::UserId)
+                    .to(User::Table, User::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            )
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_organization_created")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::OrganizationId)
+                    .col(AuditLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_audit_log_entity")
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Entity)
+                    .col(AuditLog::EntityId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
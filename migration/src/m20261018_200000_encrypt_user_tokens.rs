@@ -0,0 +1,54 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Tokens are now encrypted, and `token_key_id` says with which key.
+        // Rows saved before this have no key id and hold plaintext until the
+        // app next starts and encrypts them; the keys aren't available here.
+        // Sessions were keyed on the access token, so each user gets a
+        // random secret for that instead, from Postgres' strong random
+        // source (`gen_random_uuid`, unlike `random()`).
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(integer_null(User::TokenKeyId))
+                    .add_column(string(User::SessionSecret).default(Expr::cust(
+                        "replace(gen_random_uuid()::text || gen_random_uuid()::text, '-', '')",
+                    )))
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Encrypted tokens are useless without the app's keys, so they are
+        // dropped rather than left behind; users sign in again.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE \"user\" SET access_token = '', refresh_token = NULL, \
+             token_expires_at = now() WHERE token_key_id IS NOT NULL",
+        )
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_column(User::TokenKeyId)
+                    .drop_column(User::SessionSecret)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+use crate::iden::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A user row is one Planning Center login in one organization. An
+        // account groups the logins of someone who organizes for several.
+        let table = table_auto(Account::Table)
+            .col(pk_auto(Account::Id))
+            .to_owned();
+        manager.create_table(table).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .add_column(integer_null(User::AccountId))
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every existing login starts out as an account of its own.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "INSERT INTO account (id, created_at, updated_at) \
+             SELECT id, created_at, updated_at FROM \"user\"",
+        )
+        .await?;
+        db.execute_unprepared(
+            "SELECT setval(pg_get_serial_sequence('account', 'id'), \
+             COALESCE((SELECT MAX(id) FROM account), 0) + 1, false)",
+        )
+        .await?;
+        db.execute_unprepared("UPDATE \"user\" SET account_id = id")
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .modify_column(integer(User::AccountId))
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_user_account")
+                            .from_tbl(User::Table)
+                            .from_col(User::AccountId)
+                            .to_tbl(Account::Table)
+                            .to_col(Account::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_account")
+                    .table(User::Table)
+                    .col(User::AccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(User::Table)
+                    .drop_foreign_key(Alias::new("fk_user_account"))
+                    .drop_column(User::AccountId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Account::Table).to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
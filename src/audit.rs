@@ -0,0 +1,216 @@
+//! A history of who changed what, so organizers can see how a roster or a
+//! potluck came to look the way it does.
+//!
+//! Handlers describe what they did as [`Change`]s and pass them to
+//! [`record`], in the same transaction as the change where there is one.
+
+use chrono::Utc;
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Set};
+use serde::Serialize;
+use serde_json::Value;
+
+pub use crate::entities::sea_orm_active_enums::{AuditAction, AuditEntity};
+use crate::{
+    entities::{attendance, audit_log, household, person, potluck, potluck_series},
+    planning::participant::ParticipantId,
+};
+
+/// Left out of [`Change::diff`], since they move on every save.
+const IGNORED_FIELDS: [&str; 2] = ["created_at", "updated_at"];
+
+/// One entry for the audit log.
+#[derive(Debug, Clone)]
+pub struct Change {
+    action: AuditAction,
+    entity: AuditEntity,
+    entity_id: i32,
+    entity_name: String,
+    field: Option<String>,
+    old_value: Option<String>,
+    new_value: Option<String>,
+}
+
+impl Change {
+    fn new(action: AuditAction, entity: AuditEntity, entity_id: i32, name: &str) -> Self {
+        Change {
+            action,
+            entity,
+            entity_id,
+            entity_name: name.to_string(),
+            field: None,
+            old_value: None,
+            new_value: None,
+        }
+    }
+
+    pub fn created(entity: AuditEntity, entity_id: i32, name: &str) -> Self {
+        Self::new(AuditAction::Create, entity, entity_id, name)
+    }
+
+    pub fn deleted(entity: AuditEntity, entity_id: i32, name: &str) -> Self {
+        Self::new(AuditAction::Delete, entity, entity_id, name)
+    }
+
+    /// `duplicate` was merged into the record and deleted.
+    pub fn merged(entity: AuditEntity, entity_id: i32, name: &str, duplicate: &str) -> Self {
+        Change {
+            old_value: Some(duplicate.to_string()),
+            ..Self::new(AuditAction::Merge, entity, entity_id, name)
+        }
+    }
+
+    pub fn updated(
+        entity: AuditEntity,
+        entity_id: i32,
+        name: &str,
+        field: &str,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) -> Self {
+        Change {
+            field: Some(field.to_string()),
+            old_value,
+            new_value,
+            ..Self::new(AuditAction::Update, entity, entity_id, name)
+        }
+    }
+
+    /// One change per field that differs between two versions of a record.
+    pub fn diff<T: Serialize>(
+        entity: AuditEntity,
+        entity_id: i32,
+        name: &str,
+        before: &T,
+        after: &T,
+    ) -> Vec<Self> {
+        let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+            (serde_json::to_value(before), serde_json::to_value(after))
+        else {
+            return Vec::new();
+        };
+        before
+            .iter()
+            .filter(|(field, _)| !IGNORED_FIELDS.contains(&field.as_str()))
+            .filter_map(|(field, old)| {
+                let new = after.get(field).unwrap_or(&Value::Null);
+                (old != new).then(|| {
+                    Self::updated(entity, entity_id, name, field, display(old), display(new))
+                })
+            })
+            .collect()
+    }
+}
+
+/// The name of a person or household in the organization, or `None` if
+/// it isn't one of theirs.
+pub async fn find_participant_name<C>(
+    db: &C,
+    organization_id: i32,
+    participant: ParticipantId,
+) -> Result<Option<String>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    Ok(match participant {
+        ParticipantId::Person(id) => person::Entity::find_by_id(id)
+            .filter(person::Column::OrganizationId.eq(organization_id))
+            .one(db)
+            .await?
+            .map(|p| p.name),
+        ParticipantId::Household(id) => household::Entity::find_by_id(id)
+            .filter(household::Column::OrganizationId.eq(organization_id))
+            .one(db)
+            .await?
+            .map(|h| h.name),
+    })
+}
+
+/// The name to log for a person or household, such as an attendee.
+pub async fn participant_name<C>(
+    db: &C,
+    organization_id: i32,
+    participant: ParticipantId,
+) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let name = find_participant_name(db, organization_id, participant).await?;
+    Ok(name.unwrap_or_else(|| participant.to_string()))
+}
+
+/// The name to log for an attendance: who it is for.
+pub async fn attendance_name<C>(db: &C, attendance: &attendance::Model) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    match ParticipantId::from_attendance(attendance) {
+        Some(attendee) => participant_name(db, attendance.organization_id, attendee).await,
+        None => Ok(format!("Attendance #{}", attendance.id)),
+    }
+}
+
+/// The name to log for a potluck: its series and round, and who hosts it.
+pub async fn potluck_name<C>(db: &C, potluck: &potluck::Model) -> Result<String, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let series = potluck_series::Entity::find_by_id(potluck.potluck_series_id)
+        .one(db)
+        .await?
+        .map_or_else(|| "Series".to_string(), |s| s.name);
+    let name = format!("{series} round {}", potluck.round);
+    match ParticipantId::host_of(potluck) {
+        Some(host) => {
+            let host = participant_name(db, potluck.organization_id, host).await?;
+            Ok(format!("{name} at {host}"))
+        }
+        None => Ok(name),
+    }
+}
+
+fn display(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Saves `changes` as done by `user_id`, or by nobody in particular when
+/// the change came from a sync or a script. Updates that left a value as
+/// it was are skipped.
+pub async fn record<C>(
+    db: &C,
+    organization_id: i32,
+    user_id: Option<i32>,
+    changes: impl IntoIterator<Item = Change>,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = Utc::now().naive_utc();
+    let rows: Vec<audit_log::ActiveModel> = changes
+        .into_iter()
+        .filter(|c| c.action != AuditAction::Update || c.old_value != c.new_value)
+        .map(|c| audit_log::ActiveModel {
+            organization_id: Set(organization_id),
+            user_id: Set(user_id),
+            action: Set(c.action),
+            entity: Set(c.entity),
+            entity_id: Set(c.entity_id),
+            entity_name: Set(c.entity_name),
+            field: Set(c.field),
+            old_value: Set(c.old_value),
+            new_value: Set(c.new_value),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        })
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+
+    audit_log::Entity::insert_many(rows).exec(db).await?;
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! Encryption for the Planning Center tokens we keep for each user, so a
+//! database dump doesn't hand out access to anyone's Planning Center.
+//!
+//! Keys come from `TOKEN_KEYS` as comma-separated `id:base64` pairs of
+//! 32-byte AES keys. The first key encrypts; the others are only used to
+//! decrypt tokens saved before a rotation. Each user row records which key
+//! its tokens were encrypted with.
+//!
+//! A new key can be made with `openssl rand -base64 32`.
+
+use aes_gcm::{
+    Aes256Gcm, KeyInit,
+    aead::{Aead, AeadCore, OsRng},
+};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::{collections::HashMap, fmt};
+
+/// Length of the nonce stored in front of each ciphertext.
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenKeyError {
+    #[error("TOKEN_KEYS has no keys")]
+    Empty,
+
+    #[error("Key `{0}` is not `id:base64`")]
+    Malformed(String),
+
+    #[error("Key {0} is not 32 bytes of base64")]
+    InvalidKey(i32),
+
+    #[error("Key {0} is listed twice")]
+    Duplicate(i32),
+
+    #[error("No key with id {0}")]
+    UnknownKey(i32),
+
+    #[error("Token could not be decrypted")]
+    Decrypt,
+}
+
+/// A token as stored: which key encrypted it, and the base64 nonce and
+/// ciphertext.
+pub struct SealedToken {
+    pub key_id: i32,
+    pub ciphertext: String,
+}
+
+#[derive(Clone)]
+pub struct TokenKeys {
+    current: i32,
+    ciphers: HashMap<i32, Aes256Gcm>,
+}
+
+/// Shows only the key ids, never the keys.
+impl fmt::Debug for TokenKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ids: Vec<&i32> = self.ciphers.keys().collect();
+        ids.sort();
+        f.debug_struct("TokenKeys")
+            .field("current", &self.current)
+            .field("ids", &ids)
+            .finish()
+    }
+}
+
+impl TokenKeys {
+    /// Parses `TOKEN_KEYS`, whose first key is the one new tokens use.
+    pub fn parse(value: &str) -> Result<Self, TokenKeyError> {
+        let mut current = None;
+        let mut ciphers = HashMap::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, key) = entry
+                .split_once(':')
+                .ok_or_else(|| TokenKeyError::Malformed(entry.to_string()))?;
+            let id: i32 = id
+                .trim()
+                .parse()
+                .map_err(|_| TokenKeyError::Malformed(entry.to_string()))?;
+            let cipher = STANDARD
+                .decode(key.trim())
+                .ok()
+                .and_then(|bytes| Aes256Gcm::new_from_slice(&bytes).ok())
+                .ok_or(TokenKeyError::InvalidKey(id))?;
+            if ciphers.insert(id, cipher).is_some() {
+                return Err(TokenKeyError::Duplicate(id));
+            }
+            current.get_or_insert(id);
+        }
+        let current = current.ok_or(TokenKeyError::Empty)?;
+        Ok(Self { current, ciphers })
+    }
+
+    /// The id of the key new tokens are encrypted with.
+    pub fn current_id(&self) -> i32 {
+        self.current
+    }
+
+    pub fn encrypt(&self, token: &str) -> SealedToken {
+        let cipher = &self.ciphers[&self.current];
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, token.as_bytes())
+            .expect("AES-GCM encryption should not fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        SealedToken {
+            key_id: self.current,
+            ciphertext: STANDARD.encode(sealed),
+        }
+    }
+
+    pub fn decrypt(&self, key_id: i32, ciphertext: &str) -> Result<String, TokenKeyError> {
+        let cipher = self
+            .ciphers
+            .get(&key_id)
+            .ok_or(TokenKeyError::UnknownKey(key_id))?;
+        let sealed = STANDARD
+            .decode(ciphertext)
+            .map_err(|_| TokenKeyError::Decrypt)?;
+        if sealed.len() < NONCE_LEN {
+            return Err(TokenKeyError::Decrypt);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = cipher
+            .decrypt(nonce.into(), ciphertext)
+            .map_err(|_| TokenKeyError::Decrypt)?;
+        String::from_utf8(plaintext).map_err(|_| TokenKeyError::Decrypt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+
+    use super::{TokenKeyError, TokenKeys};
+
+    fn key(byte: u8) -> String {
+        STANDARD.encode([byte; 32])
+    }
+
+    #[test]
+    fn the_first_key_is_current() {
+        let keys = TokenKeys::parse(&format!(" 2:{} , 1:{},", key(2), key(1))).unwrap();
+        assert_eq!(keys.current_id(), 2);
+        assert_eq!(format!("{keys:?}"), "TokenKeys { current: 2, ids: [1, 2] }");
+    }
+
+    #[test]
+    fn bad_key_lists_are_rejected() {
+        let short = STANDARD.encode([0u8; 16]);
+        for (value, expected) in [
+            ("", TokenKeyError::Empty),
+            (" , ", TokenKeyError::Empty),
+            ("nokey", TokenKeyError::Malformed("nokey".to_string())),
+            ("x:abc", TokenKeyError::Malformed("x:abc".to_string())),
+            (&format!("1:{short}"), TokenKeyError::InvalidKey(1)),
+            ("1:not base64!", TokenKeyError::InvalidKey(1)),
+            (
+                &format!("1:{},1:{}", key(1), key(2)),
+                TokenKeyError::Duplicate(1),
+            ),
+        ] {
+            let error = TokenKeys::parse(value).unwrap_err();
+            assert_eq!(error.to_string(), expected.to_string(), "{value:?}");
+        }
+    }
+
+    #[test]
+    fn tokens_round_trip() {
+        let keys = TokenKeys::parse(&format!("1:{}", key(1))).unwrap();
+        let sealed = keys.encrypt("access-token");
+        assert_eq!(sealed.key_id, 1);
+        assert!(!sealed.ciphertext.contains("access-token"));
+        assert_ne!(sealed.ciphertext, keys.encrypt("access-token").ciphertext);
+        assert_eq!(
+            keys.decrypt(sealed.key_id, &sealed.ciphertext).unwrap(),
+            "access-token"
+        );
+    }
+
+    #[test]
+    fn retired_keys_still_decrypt_after_rotation() {
+        let old = TokenKeys::parse(&format!("1:{}", key(1))).unwrap();
+        let sealed = old.encrypt("refresh-token");
+
+        let rotated = TokenKeys::parse(&format!("2:{},1:{}", key(2), key(1))).unwrap();
+        assert_eq!(
+            rotated.decrypt(sealed.key_id, &sealed.ciphertext).unwrap(),
+            "refresh-token"
+        );
+        assert_eq!(rotated.encrypt("refresh-token").key_id, 2);
+
+        let dropped = TokenKeys::parse(&format!("2:{}", key(2))).unwrap();
+        assert!(matches!(
+            dropped.decrypt(sealed.key_id, &sealed.ciphertext),
+            Err(TokenKeyError::UnknownKey(1))
+        ));
+    }
+
+    #[test]
+    fn tampered_tokens_are_rejected() {
+        let keys = TokenKeys::parse(&format!("1:{},2:{}", key(1), key(2))).unwrap();
+        let sealed = keys.encrypt("access-token");
+
+        let mut bytes = STANDARD.decode(&sealed.ciphertext).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        let flipped = STANDARD.encode(bytes);
+        for (key_id, ciphertext) in [
+            (1, flipped.as_str()),
+            (2, sealed.ciphertext.as_str()),
+            (1, "not base64!"),
+            (1, "c2hvcnQ="),
+        ] {
+            assert!(
+                matches!(
+                    keys.decrypt(key_id, ciphertext),
+                    Err(TokenKeyError::Decrypt)
+                ),
+                "{key_id} {ciphertext}"
+            );
+        }
+    }
+}
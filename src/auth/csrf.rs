@@ -0,0 +1,300 @@
+//! Cross-site request forgery protection for everything a browser session
+//! can change.
+//!
+//! Each session gets a random token. `base.html` sends it with every HTMX
+//! request as the `X-CSRF-Token` header, and plain forms carry it in a
+//! hidden `csrf_token` field. Requests other than `GET`, `HEAD` and
+//! `OPTIONS` are rejected unless they include the session's token, or are
+//! API requests that authenticate with a bearer token instead.
+
+use axum::{
+    Form,
+    body::{Body, to_bytes},
+    extract::{FromRequest, Multipart, Request},
+    http::{
+        Method, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE},
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use axum_login::tower_sessions::{Session, session};
+use rand::RngCore;
+use serde::Deserialize;
+
+use crate::error::{AppError, is_api};
+
+const SESSION_KEY: &str = "csrf.token";
+
+/// Header HTMX requests send the token in.
+pub const HEADER: &str = "x-csrf-token";
+
+/// Form field plain forms send the token in.
+pub const FIELD: &str = "csrf_token";
+
+/// Largest form body read while looking for the token, the same as axum's
+/// default body limit.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+tokio::task_local! {
+    static TOKEN: String;
+}
+
+#[derive(Deserialize)]
+struct TokenField {
+    csrf_token: Option<String>,
+}
+
+/// The token for the session of the request being handled, for templates.
+/// Empty outside a request.
+pub fn current_token() -> String {
+    TOKEN.try_with(Clone::clone).unwrap_or_default()
+}
+
+/// Middleware that checks the token on requests that change something and
+/// makes it available to templates for the rest of the request.
+pub async fn protect(session: Session, request: Request, next: Next) -> Response {
+    let token = match session_token(&session).await {
+        Ok(token) => token,
+        Err(e) => return AppError::Other(e.into()).into_response(),
+    };
+
+    let request = if needs_token(&request) {
+        let (request, sent) = match sent_token(request).await {
+            Ok(found) => found,
+            Err(response) => return response,
+        };
+        if !sent.is_some_and(|sent| tokens_match(&sent, &token)) {
+            return AppError::CsrfMismatch.into_response();
+        }
+        request
+    } else {
+        request
+    };
+
+    TOKEN.scope(token, next.run(request)).await
+}
+
+async fn session_token(session: &Session) -> Result<String, session::Error> {
+    if let Some(token) = session.get::<String>(SESSION_KEY).await? {
+        return Ok(token);
+    }
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    session.insert(SESSION_KEY, &token).await?;
+    Ok(token)
+}
+
+/// Whether the request has to carry the session's token.
+///
+/// API requests with a bearer token are let through, because `ApiUser`
+/// then authenticates them by that token alone and never by the session
+/// cookie. That only holds while the app sends no CORS headers: a page on
+/// another site can't add an `Authorization` header to a cross-origin
+/// request without a preflight we would have to allow. Elsewhere a bearer
+/// header proves nothing, so the token is still needed.
+fn needs_token(request: &Request) -> bool {
+    let safe = matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let bearer = is_api(request)
+        && request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("Bearer "));
+    !safe && !bearer
+}
+
+/// The token the request sent, from the header or else from a form body.
+/// Reading the body consumes it, so the request is rebuilt around a copy.
+async fn sent_token(request: Request) -> Result<(Request, Option<String>), Response> {
+    if let Some(token) = request
+        .headers()
+        .get(HEADER)
+        .and_then(|value| value.to_str().ok())
+    {
+        let token = token.to_string();
+        return Ok((request, Some(token)));
+    }
+
+    let Some(content_type) = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+    else {
+        return Ok((request, None));
+    };
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, BODY_LIMIT)
+        .await
+        .map_err(|_| StatusCode::PAYLOAD_TOO_LARGE.into_response())?;
+
+    let probe = || {
+        Request::builder()
+            .method(Method::POST)
+            .header(CONTENT_TYPE, &content_type)
+            .body(Body::from(bytes.clone()))
+            .expect("Request should build")
+    };
+    let token = if content_type.starts_with("application/x-www-form-urlencoded") {
+        Form::<TokenField>::from_request(probe(), &())
+            .await
+            .ok()
+            .and_then(|Form(field)| field.csrf_token)
+    } else if content_type.starts_with("multipart/form-data") {
+        multipart_token(probe()).await
+    } else {
+        None
+    };
+
+    Ok((Request::from_parts(parts, Body::from(bytes)), token))
+}
+
+async fn multipart_token(request: Request) -> Option<String> {
+    let mut multipart = Multipart::from_request(request, &()).await.ok()?;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() == Some(FIELD) {
+            return field.text().await.ok();
+        }
+    }
+    None
+}
+
+/// Compares in constant time, so response timing doesn't reveal how much
+/// of a guess was right.
+fn tokens_match(sent: &str, expected: &str) -> bool {
+    sent.len() == expected.len()
+        && sent
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        extract::Request,
+        http::{
+            Method,
+            header::{AUTHORIZATION, CONTENT_TYPE},
+        },
+    };
+
+    use super::{FIELD, HEADER, needs_token, sent_token, tokens_match};
+
+    fn request(method: Method, path: &str) -> Request {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn with_bearer(method: Method, path: &str) -> Request {
+        Request::builder()
+            .method(method)
+            .uri(path)
+            .header(AUTHORIZATION, "Bearer plk_secret")
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn safe_methods_need_no_token() {
+        for method in [Method::GET, Method::HEAD, Method::OPTIONS] {
+            assert!(
+                !needs_token(&request(method.clone(), "/people")),
+                "{method}"
+            );
+        }
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            assert!(needs_token(&request(method.clone(), "/people")), "{method}");
+        }
+    }
+
+    #[test]
+    fn bearer_tokens_stand_in_only_for_the_api() {
+        assert!(!needs_token(&with_bearer(Method::POST, "/api/v1/series")));
+        assert!(needs_token(&request(Method::POST, "/api/v1/series")));
+        assert!(needs_token(&with_bearer(Method::POST, "/people")));
+        assert!(needs_token(&with_bearer(Method::POST, "/apiary")));
+    }
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(tokens_match("abc123", "abc123"));
+        assert!(!tokens_match("abc124", "abc123"));
+        assert!(!tokens_match("abc12", "abc123"));
+        assert!(!tokens_match("", "abc123"));
+    }
+
+    #[tokio::test]
+    async fn the_header_is_read_first() {
+        let request = Request::builder()
+            .method(Method::POST)
+            .header(HEADER, "from-header")
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(format!("{FIELD}=from-form")))
+            .unwrap();
+        let (_, sent) = sent_token(request).await.unwrap();
+        assert_eq!(sent.as_deref(), Some("from-header"));
+    }
+
+    #[tokio::test]
+    async fn form_fields_are_read_and_the_body_kept() {
+        let body = format!("name=Ann&{FIELD}=from-form");
+        let request = Request::builder()
+            .method(Method::POST)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(body.clone()))
+            .unwrap();
+        let (request, sent) = sent_token(request).await.unwrap();
+        assert_eq!(sent.as_deref(), Some("from-form"));
+        let kept = axum::body::to_bytes(request.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(kept, body.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn multipart_fields_are_read() {
+        let body = format!(
+            "--b\r\nContent-Disposition: form-data; name=\"{FIELD}\"\r\n\r\nfrom-multipart\r\n--b--\r\n"
+        );
+        let request = Request::builder()
+            .method(Method::POST)
+            .header(CONTENT_TYPE, "multipart/form-data; boundary=b")
+            .body(Body::from(body))
+            .unwrap();
+        let (_, sent) = sent_token(request).await.unwrap();
+        assert_eq!(sent.as_deref(), Some("from-multipart"));
+    }
+
+    #[tokio::test]
+    async fn requests_without_a_token_send_none() {
+        let (_, sent) = sent_token(request(Method::POST, "/people")).await.unwrap();
+        assert_eq!(sent, None);
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from("name=Ann"))
+            .unwrap();
+        let (_, sent) = sent_token(request).await.unwrap();
+        assert_eq!(sent, None);
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(format!(r#"{{"{FIELD}":"x"}}"#)))
+            .unwrap();
+        let (_, sent) = sent_token(request).await.unwrap();
+        assert_eq!(sent, None);
+    }
+}
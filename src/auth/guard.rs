@@ -0,0 +1,129 @@
+//! Keeps signed-out visitors out of the pages behind [`login_required`].
+//!
+//! The guard sends them to the login page with a `next` parameter so they
+//! come back to where they were. HTMX requests get an `HX-Redirect` header
+//! instead, since a redirect would only swap the login page into part of
+//! the current one.
+//!
+//! Signed-in users are further limited by their role: members may look but
+//! not change anything or read the audit history, while organizers and
+//! admins run potlucks.
+
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{Method, request::Parts},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+};
+
+use super::{router::next_param, user::AuthSession};
+use crate::{
+    entities::{sea_orm_active_enums::UserRole, user},
+    error::AppError,
+};
+
+/// The signed-in user, loaded once per request by [`login_required`].
+#[derive(Clone, Debug)]
+pub struct CurrentUser(pub user::Model);
+
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<CurrentUser>()
+            .cloned()
+            .ok_or(AppError::Unauthorized)
+    }
+}
+
+/// Middleware for routes that need a signed-in user.
+pub async fn login_required(
+    auth_session: AuthSession,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(user) = auth_session.user else {
+        let htmx = is_htmx(&request);
+        return login_redirect(htmx, return_to(&request).as_deref());
+    };
+    request.extensions_mut().insert(CurrentUser(user));
+    next.run(request).await
+}
+
+pub fn is_htmx(request: &Request) -> bool {
+    request.headers().contains_key("hx-request")
+}
+
+/// Where to return after signing in. HTMX requests come from the page in
+/// `HX-Current-URL`; other requests only return to pages that can be
+/// fetched again.
+pub fn return_to(request: &Request) -> Option<String> {
+    if is_htmx(request) {
+        let current = request.headers().get("hx-current-url")?.to_str().ok()?;
+        let (_, rest) = current.split_once("://")?;
+        return rest.find('/').map(|path| rest[path..].to_string());
+    }
+    (request.method() == Method::GET)
+        .then(|| request.uri().path_and_query())
+        .flatten()
+        .map(|path| path.to_string())
+}
+
+/// Sends the browser to the login page, asking it to come back to `next`.
+pub fn login_redirect(htmx: bool, next: Option<&str>) -> Response {
+    let login = format!("/login{}", next_param(next));
+    if htmx {
+        [("HX-Redirect", login)].into_response()
+    } else {
+        Redirect::to(&login).into_response()
+    }
+}
+
+/// Whether `role` may do everything `needed` may.
+pub fn has_role(role: UserRole, needed: UserRole) -> bool {
+    fn rank(role: UserRole) -> u8 {
+        match role {
+            UserRole::Member => 0,
+            UserRole::Organizer => 1,
+            UserRole::Admin => 2,
+        }
+    }
+    rank(role) >= rank(needed)
+}
+
+fn require_role(request: &Request, needed: UserRole) -> Result<(), AppError> {
+    let CurrentUser(user) = request
+        .extensions()
+        .get::<CurrentUser>()
+        .ok_or(AppError::Unauthorized)?;
+    if has_role(user.role, needed) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden)
+    }
+}
+
+/// Middleware that lets members view pages but only organizers change
+/// anything. Goes inside [`login_required`].
+pub async fn organizer_required_for_changes(request: Request, next: Next) -> Response {
+    if !matches!(*request.method(), Method::GET | Method::HEAD)
+        && let Err(e) = require_role(&request, UserRole::Organizer)
+    {
+        return e.into_response();
+    }
+    next.run(request).await
+}
+
+/// Middleware for pages only organizers and admins may see. Goes inside
+/// [`login_required`].
+pub async fn organizer_required(request: Request, next: Next) -> Response {
+    if let Err(e) = require_role(&request, UserRole::Organizer) {
+        return e.into_response();
+    }
+    next.run(request).await
+}
@@ -1,2 +1,6 @@
+pub mod crypto;
+pub mod csrf;
+pub mod guard;
 pub mod router;
+pub mod token;
 pub mod user;
@@ -1,20 +1,22 @@
 use axum::{
-    Form, Router,
+    Router,
     extract::Query,
-    http::StatusCode,
     response::{Html, IntoResponse, Redirect},
     routing::{get, post},
 };
 use axum_login::tower_sessions::Session;
 use oauth2::CsrfToken;
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use serde::Deserialize;
 
-use crate::router::AppState;
+use crate::{error::AppError, router::AppState};
 
 use super::user::{AuthSession, Credentials};
 
 pub const NEXT_URL_KEY: &str = "auth.next-url";
 pub const CSRF_STATE_KEY: &str = "oauth.csrf-state";
+/// Set while a signed-in user links another organization to their account.
+pub const LINK_ACCOUNT_KEY: &str = "auth.link-account";
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuthzResp {
@@ -29,6 +31,46 @@ pub struct NextUrl {
     next: Option<String>,
 }
 
+/// `next` if it is a path on this site, so a login link can't be used to
+/// send someone to another site afterwards. Scheme-relative URLs like
+/// `//evil.example` and backslashes, which browsers treat as slashes, are
+/// rejected.
+fn safe_next(next: &str) -> Option<&str> {
+    let local = next.starts_with('/')
+        && !next.starts_with("//")
+        && !next.chars().any(|c| c == '\\' || c.is_control());
+    local.then_some(next)
+}
+
+/// The query string passing `next` on to the login form, if it is safe.
+pub(super) fn next_param(next: Option<&str>) -> String {
+    next.and_then(safe_next).map_or_else(String::new, |url| {
+        format!("?next={}", utf8_percent_encode(url, NON_ALPHANUMERIC))
+    })
+}
+
+/// Sends the browser to Planning Center to sign in, coming back to `next`
+/// afterwards.
+pub(crate) async fn authorize(
+    auth_session: &AuthSession,
+    session: &Session,
+    next: Option<&str>,
+) -> Redirect {
+    let (auth_url, csrf_state) = auth_session.backend.authorize_url();
+
+    session
+        .insert(CSRF_STATE_KEY, csrf_state.secret())
+        .await
+        .expect("Serialization should not fail.");
+
+    session
+        .insert(NEXT_URL_KEY, next.and_then(safe_next))
+        .await
+        .expect("Serialization should not fail.");
+
+    Redirect::to(auth_url.as_str())
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/login", post(self::post::login))
@@ -43,21 +85,11 @@ mod post {
     pub async fn login(
         auth_session: AuthSession,
         session: Session,
-        Form(NextUrl { next }): Form<NextUrl>,
+        Query(NextUrl { next }): Query<NextUrl>,
     ) -> impl IntoResponse {
-        let (auth_url, csrf_state) = auth_session.backend.authorize_url();
-
-        session
-            .insert(CSRF_STATE_KEY, csrf_state.secret())
-            .await
-            .expect("Serialization should not fail.");
-
-        session
-            .insert(NEXT_URL_KEY, next)
-            .await
-            .expect("Serialization should not fail.");
-
-        Redirect::to(auth_url.as_str()).into_response()
+        // Signing in afresh abandons any organization link in progress.
+        let _ = session.remove::<i32>(LINK_ACCOUNT_KEY).await;
+        authorize(&auth_session, &session, next.as_deref()).await
     }
 }
 
@@ -71,28 +103,24 @@ mod get {
     pub async fn login(
         Query(NextUrl { next }): Query<NextUrl>,
         State(state): State<AppState>,
-    ) -> Result<Html<String>, StatusCode> {
-        let next_param = next.map_or_else(String::new, |url| format!("?next={}", url));
+    ) -> Result<Html<String>, AppError> {
+        let next_param = next_param(next.as_deref());
 
-        let tmpl = state
-            .templates
-            .get_template("login.html")
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let tmpl = state.templates.get_template("login.html")?;
 
-        let html = tmpl
-            .render(minijinja::context! {
-                next_param => next_param,
-            })
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let html = tmpl.render(minijinja::context! {
+            next_param => next_param,
+        })?;
 
         Ok(Html(html))
     }
 
-    pub async fn logout(mut auth_session: AuthSession) -> impl IntoResponse {
-        match auth_session.logout().await {
-            Ok(_) => Redirect::to("/login").into_response(),
-            Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
-        }
+    pub async fn logout(mut auth_session: AuthSession) -> Result<Redirect, AppError> {
+        auth_session
+            .logout()
+            .await
+            .map_err(|e| anyhow::anyhow!("Signing out failed: {e}"))?;
+        Ok(Redirect::to("/login"))
     }
 
     pub async fn callback(
@@ -102,35 +130,91 @@ mod get {
             code,
             state: new_state,
         }): Query<AuthzResp>,
-    ) -> impl IntoResponse {
+    ) -> Result<Redirect, AppError> {
         let Ok(Some(old_state)) = session.get(CSRF_STATE_KEY).await else {
-            return StatusCode::BAD_REQUEST.into_response();
+            return Err(AppError::BadRequest(
+                "This sign-in has expired. Sign in again.".to_string(),
+            ));
         };
 
+        // Only link to the account of whoever is still signed in.
+        let link_account = session
+            .remove::<i32>(LINK_ACCOUNT_KEY)
+            .await
+            .ok()
+            .flatten()
+            .filter(|id| auth_session.user.as_ref().map(|u| u.account_id) == Some(*id));
+
         let creds = Credentials {
             code,
             old_state,
             new_state,
+            link_account,
         };
 
-        let user = match auth_session.authenticate(creds).await {
-            Ok(Some(user)) => user,
-            Ok(None) => {
-                return (StatusCode::UNAUTHORIZED, "You are not allowed here.").into_response();
-            }
-            Err(_) => {
-                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-            }
-        };
+        let user = auth_session
+            .authenticate(creds)
+            .await
+            .map_err(|e| anyhow::anyhow!("Signing in failed: {e}"))?
+            .ok_or(AppError::Forbidden)?;
 
-        if auth_session.login(&user).await.is_err() {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
+        auth_session
+            .login(&user)
+            .await
+            .map_err(|e| anyhow::anyhow!("Starting a session failed: {e}"))?;
 
-        if let Ok(Some(next)) = session.remove::<String>(NEXT_URL_KEY).await {
-            Redirect::to(&next).into_response()
+        if let Ok(Some(next)) = session.remove::<String>(NEXT_URL_KEY).await
+            && let Some(next) = safe_next(&next)
+        {
+            Ok(Redirect::to(next))
         } else {
-            Redirect::to("/dashboard").into_response()
+            Ok(Redirect::to("/dashboard"))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{next_param, safe_next};
+
+    #[test]
+    fn local_paths_are_allowed() {
+        for next in [
+            "/",
+            "/dashboard",
+            "/series/3?round=2",
+            "/people#household-4",
+        ] {
+            assert_eq!(safe_next(next), Some(next), "{next}");
+        }
+    }
+
+    #[test]
+    fn other_sites_are_rejected() {
+        for next in [
+            "",
+            "dashboard",
+            "https://evil.example",
+            "http:/evil.example",
+            "javascript:alert(1)",
+            "//evil.example",
+            "///evil.example",
+            "/\\evil.example",
+            "\\\\evil.example",
+            "/\t/evil.example",
+            "/dashboard\r\nLocation: https://evil.example",
+        ] {
+            assert_eq!(safe_next(next), None, "{next:?}");
+        }
+    }
+
+    #[test]
+    fn next_param_is_encoded() {
+        assert_eq!(
+            next_param(Some("/series/3?round=2&x=a b")),
+            "?next=%2Fseries%2F3%3Fround%3D2%26x%3Da%20b"
+        );
+        assert_eq!(next_param(Some("//evil.example")), "");
+        assert_eq!(next_param(None), "");
+    }
+}
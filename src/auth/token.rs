@@ -0,0 +1,264 @@
+//! Personal API tokens, so scripts can use the JSON API without a browser
+//! login. Only a SHA-256 hash of each token is stored; the token itself is
+//! shown once, when it is created.
+
+use chrono::{NaiveDateTime, Utc};
+use rand::RngCore;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Set, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::entities::{api_token, user};
+
+/// Prefix on every token, so they are easy to spot in config files and logs.
+const TOKEN_PREFIX: &str = "plk_";
+
+/// What a token may do. Reading covers `GET` requests; anything that
+/// changes data needs `Write`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Read,
+    Write,
+}
+
+/// The user a valid token belongs to, and what the token allows.
+pub struct TokenGrant {
+    pub user: user::Model,
+    pub scopes: Vec<Scope>,
+}
+
+impl TokenGrant {
+    pub fn allows(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+fn hash_token(token: &str) -> String {
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let secret: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("{TOKEN_PREFIX}{secret}")
+}
+
+pub fn scopes(token: &api_token::Model) -> Vec<Scope> {
+    serde_json::from_value(token.scopes.clone()).unwrap_or_default()
+}
+
+/// Mints a token for the user. Returns the stored row and the token itself,
+/// which can't be recovered later.
+pub async fn create_token<C>(
+    db: &C,
+    user: &user::Model,
+    name: &str,
+    scopes: &[Scope],
+    expires_at: Option<NaiveDateTime>,
+) -> Result<(api_token::Model, String), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let token = generate_token();
+    let model = api_token::ActiveModel {
+        organization_id: Set(user.organization_id),
+        user_id: Set(user.id),
+        name: Set(name.to_string()),
+        token_hash: Set(hash_token(&token)),
+        scopes: Set(serde_json::json!(scopes)),
+        expires_at: Set(expires_at),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok((model, token))
+}
+
+/// Tokens the user has created, newest first, including revoked and expired
+/// ones so they can see what happened to them.
+pub async fn list_tokens<C>(db: &C, user: &user::Model) -> Result<Vec<api_token::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    api_token::Entity::find()
+        .filter(api_token::Column::UserId.eq(user.id))
+        .order_by_desc(api_token::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Revokes one of the user's tokens. Returns false when the user has no such
+/// token.
+pub async fn revoke_token<C>(db: &C, user: &user::Model, id: i32) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let now = Utc::now().naive_utc();
+    let result = api_token::Entity::update_many()
+        .col_expr(api_token::Column::RevokedAt, Expr::value(now))
+        .col_expr(api_token::Column::UpdatedAt, Expr::value(now))
+        .filter(api_token::Column::Id.eq(id))
+        .filter(api_token::Column::UserId.eq(user.id))
+        .filter(api_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Whether a stored token still stands for its user: it hasn't been revoked
+/// or run out, and the user hasn't switched organizations since minting it.
+fn is_usable(stored: &api_token::Model, user: &user::Model, now: NaiveDateTime) -> bool {
+    stored.revoked_at.is_none()
+        && stored.expires_at.is_none_or(|expires_at| expires_at > now)
+        && user.organization_id == stored.organization_id
+}
+
+/// Looks up the user behind a bearer token, recording that the token was
+/// used. Unknown, revoked and expired tokens give `None`.
+pub async fn authenticate<C>(db: &C, token: &str) -> Result<Option<TokenGrant>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+
+    let now = Utc::now().naive_utc();
+    let Some((stored, Some(user))) = api_token::Entity::find()
+        .filter(api_token::Column::TokenHash.eq(hash_token(token)))
+        .find_also_related(user::Entity)
+        .one(db)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if !is_usable(&stored, &user, now) {
+        return Ok(None);
+    }
+
+    api_token::Entity::update_many()
+        .col_expr(api_token::Column::LastUsedAt, Expr::value(now))
+        .filter(api_token::Column::Id.eq(stored.id))
+        .exec(db)
+        .await?;
+
+    Ok(Some(TokenGrant {
+        scopes: scopes(&stored),
+        user,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDateTime, TimeDelta, Utc};
+
+    use super::{Scope, TOKEN_PREFIX, TokenGrant, generate_token, hash_token, is_usable, scopes};
+    use crate::entities::{api_token, sea_orm_active_enums::UserRole, user};
+
+    fn stored(now: NaiveDateTime) -> api_token::Model {
+        api_token::Model {
+            created_at: now,
+            updated_at: now,
+            id: 7,
+            organization_id: 2,
+            user_id: 3,
+            name: "Script".to_string(),
+            token_hash: hash_token("plk_secret"),
+            scopes: serde_json::json!(["read"]),
+            expires_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        }
+    }
+
+    fn owner(now: NaiveDateTime) -> user::Model {
+        user::Model {
+            created_at: now,
+            updated_at: now,
+            id: 3,
+            person_id: 5,
+            organization_id: 2,
+            access_token: String::new(),
+            refresh_token: None,
+            token_expires_at: now,
+            token_key_id: None,
+            session_secret: String::new(),
+            role: UserRole::Organizer,
+            account_id: 1,
+        }
+    }
+
+    #[test]
+    fn tokens_are_hashed_as_hex_sha256() {
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn generated_tokens_are_prefixed_and_distinct() {
+        let token = generate_token();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generate_token());
+    }
+
+    #[test]
+    fn current_tokens_are_usable() {
+        let now = Utc::now().naive_utc();
+        let token = stored(now);
+        assert!(is_usable(&token, &owner(now), now));
+
+        let expires_later = api_token::Model {
+            expires_at: Some(now + TimeDelta::days(1)),
+            ..token
+        };
+        assert!(is_usable(&expires_later, &owner(now), now));
+    }
+
+    #[test]
+    fn revoked_and_expired_tokens_are_not() {
+        let now = Utc::now().naive_utc();
+        let revoked = api_token::Model {
+            revoked_at: Some(now - TimeDelta::minutes(1)),
+            ..stored(now)
+        };
+        assert!(!is_usable(&revoked, &owner(now), now));
+
+        let expired = api_token::Model {
+            expires_at: Some(now),
+            ..stored(now)
+        };
+        assert!(!is_usable(&expired, &owner(now), now));
+    }
+
+    #[test]
+    fn tokens_minted_in_another_organization_are_not() {
+        let now = Utc::now().naive_utc();
+        let moved = user::Model {
+            organization_id: 9,
+            ..owner(now)
+        };
+        assert!(!is_usable(&stored(now), &moved, now));
+    }
+
+    #[test]
+    fn grants_allow_only_their_scopes() {
+        let now = Utc::now().naive_utc();
+        let grant = TokenGrant {
+            scopes: scopes(&stored(now)),
+            user: owner(now),
+        };
+        assert!(grant.allows(Scope::Read));
+        assert!(!grant.allows(Scope::Write));
+    }
+}
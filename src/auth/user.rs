@@ -1,4 +1,5 @@
-use crate::OauthClient;
+use crate::auth::crypto::{TokenKeyError, TokenKeys};
+use crate::router::OauthClient;
 use crate::entities::user::{
     ActiveModel as UserActiveModel, Entity as UserEntity, Model as UserModel,
 };
@@ -6,16 +7,30 @@ use async_session::async_trait;
 use axum_login::{AuthUser, AuthnBackend, UserId};
 use chrono::TimeDelta;
 use chrono::Utc;
-use oauth2::{AuthorizationCode, TokenResponse};
-use oauth2::{CsrfToken, HttpClientError, Scope, basic::BasicRequestTokenError};
+use oauth2::{AuthorizationCode, RefreshToken, RequestTokenError, TokenResponse};
+use oauth2::{
+    CsrfToken, HttpClientError, Scope,
+    basic::{BasicErrorResponseType, BasicRequestTokenError},
+};
+use rand::RngCore;
 use reqwest::Url;
 use sea_orm::{ActiveValue::*, IntoActiveModel, prelude::*};
-use sea_orm::{ConnectionTrait, DatabaseConnection, EntityTrait, Set, TransactionTrait};
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, EntityTrait, QuerySelect, Set, TransactionTrait,
+};
 use serde::Deserialize;
 
-use crate::entities::{household, organization, person, prelude::*, user};
+use crate::entities::{
+    account, household, organization, person, prelude::*, sea_orm_active_enums::RecordSource, user,
+};
 use crate::pco::person::get_user_info;
 
+/// Tokens are refreshed this long before they expire.
+const REFRESH_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+/// How long Planning Center tokens last, for responses that don't say.
+const DEFAULT_TOKEN_LIFETIME: std::time::Duration = std::time::Duration::from_secs(7200);
+
 impl AuthUser for user::Model {
     type Id = i32;
 
@@ -24,7 +39,7 @@ impl AuthUser for user::Model {
     }
 
     fn session_auth_hash(&self) -> &[u8] {
-        self.access_token.as_bytes()
+        self.session_secret.as_bytes()
     }
 }
 
@@ -33,6 +48,9 @@ pub struct Credentials {
     pub code: String,
     pub old_state: CsrfToken,
     pub new_state: CsrfToken,
+    /// The account of a signed-in user who is linking another organization
+    /// to it, rather than signing in.
+    pub link_account: Option<i32>,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -53,15 +71,38 @@ pub enum BackendError {
     OAuth2(#[from] BasicRequestTokenError<HttpClientError<reqwest::Error>>),
 }
 
+/// Why we couldn't get a Planning Center token for a user.
+#[derive(Debug, thiserror::Error)]
+pub enum TokenError {
+    #[error(transparent)]
+    Seaorm(#[from] sea_orm::DbErr),
+
+    #[error(transparent)]
+    Keys(#[from] TokenKeyError),
+
+    #[error(transparent)]
+    OAuth2(#[from] BasicRequestTokenError<HttpClientError<reqwest::Error>>),
+
+    /// Planning Center no longer accepts the refresh token, so the user has
+    /// to sign in again.
+    #[error("Planning Center access was revoked")]
+    Revoked,
+}
+
 #[derive(Debug, Clone)]
 pub struct Backend {
     db: DatabaseConnection,
     client: OauthClient,
+    token_keys: TokenKeys,
 }
 
 impl Backend {
-    pub fn new(db: DatabaseConnection, client: OauthClient) -> Self {
-        Self { db, client }
+    pub fn new(db: DatabaseConnection, client: OauthClient, token_keys: TokenKeys) -> Self {
+        Self {
+            db,
+            client,
+            token_keys,
+        }
     }
 
     pub fn authorize_url(&self) -> (Url, CsrfToken) {
@@ -87,26 +128,16 @@ impl AuthnBackend for Backend {
             return Ok(None);
         };
 
-        let http_client = reqwest::ClientBuilder::new()
-            // Following redirects opens the client up to SSRF vulnerabilities.
-            .redirect(reqwest::redirect::Policy::none())
-            .build()
-            .expect("Client should build");
-
         // Process authorization code, expecting a token response back.
         let token_res = self
             .client
             .exchange_code(AuthorizationCode::new(creds.code))
-            .request_async(&http_client)
+            .request_async(&oauth_http_client())
             .await?;
 
         let access_token = token_res.access_token().secret().to_string();
         let refresh_token = token_res.refresh_token().map(|t| t.secret().to_string());
-        let expires_in = token_res
-            .expires_in()
-            .unwrap_or_else(|| std::time::Duration::from_secs(7200));
-        let token_expires_at =
-            chrono::Utc::now().naive_utc() + TimeDelta::seconds(expires_in.as_secs() as i64);
+        let token_expires_at = token_expires_at(token_res.expires_in());
         let user_info = get_user_info(&access_token).await?;
 
         if user_info.is_none() {
@@ -114,6 +145,8 @@ impl AuthnBackend for Backend {
         }
 
         let user_data = user_info.ok_or(Self::Error::UnknownUser)?;
+        let token_keys = self.token_keys.clone();
+        let link_account = creds.link_account;
 
         let user = self
             .db
@@ -171,7 +204,7 @@ impl AuthnBackend for Backend {
                             person::ActiveModel {
                                 id: NotSet,
                                 organization_id: Set(organization.id),
-                                pco_id: Set(user_data.id),
+                                pco_id: Set(Some(user_data.id)),
                                 name: Set(user_data.name),
                                 avatar_url: Set(user_data.avatar),
                                 email: Set(user_data.email),
@@ -181,6 +214,9 @@ impl AuthnBackend for Backend {
                                 is_signed_up: Set(false),
                                 is_child: Set(false),
                                 household_id: Set(None),
+                                signed_up_at: Set(None),
+                                dietary_notes: Set(None),
+                                source: Set(RecordSource::Pco),
                                 created_at: Set(chrono::Utc::now().naive_utc()),
                                 updated_at: Set(chrono::Utc::now().naive_utc()),
                             }
@@ -207,7 +243,7 @@ impl AuthnBackend for Backend {
                                     // Create new household
                                     let household_model = household::ActiveModel {
                                         organization_id: Set(organization.id),
-                                        pco_id: Set(household.id),
+                                        pco_id: Set(Some(household.id)),
                                         name: Set(household.name),
                                         avatar_url: Set(household.avatar),
                                         ..Default::default()
@@ -239,26 +275,53 @@ impl AuthnBackend for Backend {
                     let user = match user {
                         Some(user) => {
                             let mut user_model = user.into_active_model();
-                            user_model.access_token = Set(access_token.clone());
-                            user_model.refresh_token = Set(refresh_token.clone());
+                            if let Some(account_id) = link_account {
+                                user_model.account_id = Set(account_id);
+                            }
+                            seal_tokens(
+                                &mut user_model,
+                                &token_keys,
+                                &access_token,
+                                refresh_token.as_deref(),
+                            );
                             user_model.token_expires_at = Set(token_expires_at);
                             user_model.update(txn).await?
                         }
                         None => {
-                            let user_model = user::ActiveModel {
+                            let account_id = match link_account {
+                                Some(account_id) => account_id,
+                                None => {
+                                    let now = Utc::now().naive_utc();
+                                    let account = account::ActiveModel {
+                                        created_at: Set(now),
+                                        updated_at: Set(now),
+                                        ..Default::default()
+                                    };
+                                    account.insert(txn).await?.id
+                                }
+                            };
+                            let mut user_model = user::ActiveModel {
+                                account_id: Set(account_id),
                                 person_id: Set(person.id),
                                 organization_id: Set(organization.id),
-                                access_token: Set(access_token.clone()),
-                                refresh_token: Set(refresh_token.clone()),
                                 token_expires_at: Set(token_expires_at),
+                                session_secret: Set(new_session_secret()),
                                 ..Default::default()
                             };
+                            seal_tokens(
+                                &mut user_model,
+                                &token_keys,
+                                &access_token,
+                                refresh_token.as_deref(),
+                            );
                             user_model.insert(txn).await?
                         }
                     };
 
-                    if is_new_person && household.is_some() {
-                        let pco_id = household.unwrap().pco_id;
+                    if is_new_person
+                        && let Some(household) = household
+                        && let Some(pco_id) = household.pco_id
+                    {
                         get_household_data(pco_id, txn).await?;
                     }
 
@@ -311,32 +374,201 @@ where
     Ok(())
 }
 
+fn oauth_http_client() -> reqwest::Client {
+    reqwest::ClientBuilder::new()
+        // Following redirects opens the client up to SSRF vulnerabilities.
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Client should build")
+}
+
+/// A random value that stands in for the user's credentials in their
+/// session, so sessions survive token refreshes.
+fn new_session_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Stores the tokens on the user encrypted with the current key.
+fn seal_tokens(
+    model: &mut UserActiveModel,
+    token_keys: &TokenKeys,
+    access_token: &str,
+    refresh_token: Option<&str>,
+) {
+    let access = token_keys.encrypt(access_token);
+    model.access_token = Set(access.ciphertext);
+    model.refresh_token = Set(refresh_token.map(|t| token_keys.encrypt(t).ciphertext));
+    model.token_key_id = Set(Some(access.key_id));
+}
+
+/// The user's access and refresh tokens in plaintext. Rows saved before
+/// tokens were encrypted have no key id and are read as they are.
+fn open_tokens(
+    user: &UserModel,
+    token_keys: &TokenKeys,
+) -> Result<(String, Option<String>), TokenKeyError> {
+    let Some(key_id) = user.token_key_id else {
+        return Ok((user.access_token.clone(), user.refresh_token.clone()));
+    };
+    let access_token = token_keys.decrypt(key_id, &user.access_token)?;
+    let refresh_token = user
+        .refresh_token
+        .as_deref()
+        .map(|t| token_keys.decrypt(key_id, t))
+        .transpose()?;
+    Ok((access_token, refresh_token))
+}
+
+/// When a token should be treated as expired: a little before it actually
+/// is, so it doesn't run out partway through a request.
+fn needs_refresh(user: &UserModel) -> bool {
+    user.token_expires_at - REFRESH_MARGIN < Utc::now().naive_utc()
+}
+
+fn token_expires_at(expires_in: Option<std::time::Duration>) -> chrono::NaiveDateTime {
+    let expires_in = expires_in.unwrap_or(DEFAULT_TOKEN_LIFETIME);
+    Utc::now().naive_utc() + TimeDelta::seconds(expires_in.as_secs() as i64)
+}
+
+/// Forgets a refresh token Planning Center no longer accepts and ends the
+/// user's sessions, so their next request sends them to sign in again.
+async fn revoke_tokens<C>(db: &C, user: UserModel) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut model: UserActiveModel = user.into();
+    model.refresh_token = Set(None);
+    model.token_expires_at = Set(Utc::now().naive_utc());
+    model.session_secret = Set(new_session_secret());
+    model.update(db).await?;
+    Ok(())
+}
+
+/// Returns a usable Planning Center access token for the user, refreshing
+/// it first if it is about to expire. Tokens that were refreshed, or that
+/// weren't encrypted with the current key, are saved again with it.
+///
+/// The user's row stays locked while a refresh runs, so concurrent requests
+/// wait for it and use its result instead of each spending the same
+/// refresh token.
 pub async fn ensure_valid_access_token(
     user: &mut UserModel,
     db: &DatabaseConnection,
     oauth_client: &OauthClient,
-) -> Result<(), anyhow::Error> {
-    if user.token_expires_at < Utc::now().naive_utc() {
-        let refresh_token = user
-            .refresh_token
-            .clone()
-            .ok_or_else(|| anyhow::anyhow!("No refresh token"))?;
-        let token_result = oauth_client
-            .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token))
-            .request_async(&reqwest::Client::new())
-            .await?;
+    token_keys: &TokenKeys,
+) -> Result<String, TokenError> {
+    if !needs_refresh(user) && user.token_key_id == Some(token_keys.current_id()) {
+        return Ok(open_tokens(user, token_keys)?.0);
+    }
+
+    let txn = db.begin().await?;
+    // Another request may have refreshed while we waited for the lock.
+    let locked = UserEntity::find_by_id(user.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or(TokenError::Revoked)?;
+    let (mut access_token, mut refresh_token) = open_tokens(&locked, token_keys)?;
+    let mut expires_at = locked.token_expires_at;
+    let refreshed = needs_refresh(&locked);
+    if refreshed {
+        let Some(current) = refresh_token.clone() else {
+            revoke_tokens(&txn, locked).await?;
+            txn.commit().await?;
+            return Err(TokenError::Revoked);
+        };
+        let response = oauth_client
+            .exchange_refresh_token(&RefreshToken::new(current))
+            .request_async(&oauth_http_client())
+            .await;
+        let token_result = match response {
+            Ok(token_result) => token_result,
+            Err(RequestTokenError::ServerResponse(e))
+                if *e.error() == BasicErrorResponseType::InvalidGrant =>
+            {
+                revoke_tokens(&txn, locked).await?;
+                txn.commit().await?;
+                return Err(TokenError::Revoked);
+            }
+            Err(e) => return Err(e.into()),
+        };
 
-        user.access_token = token_result.access_token().secret().to_string();
-        user.refresh_token = token_result.refresh_token().map(|t| t.secret().to_string());
-        user.token_expires_at = Utc::now().naive_utc()
-            + chrono::Duration::from_std(token_result.expires_in().unwrap()).unwrap();
-
-        // Save updated user to DB
-        let mut active_model: UserActiveModel = user.clone().into();
-        active_model.access_token = Set(user.access_token.clone());
-        active_model.refresh_token = Set(user.refresh_token.clone());
-        active_model.token_expires_at = Set(user.token_expires_at);
-        UserEntity::update(active_model).exec(db).await?;
+        access_token = token_result.access_token().secret().to_string();
+        // Keep the current refresh token if no new one was issued.
+        if let Some(new) = token_result.refresh_token() {
+            refresh_token = Some(new.secret().to_string());
+        }
+        expires_at = token_expires_at(token_result.expires_in());
     }
-    Ok(())
-}
\ No newline at end of file
+
+    *user = if refreshed || locked.token_key_id != Some(token_keys.current_id()) {
+        let mut model: UserActiveModel = locked.into();
+        seal_tokens(
+            &mut model,
+            token_keys,
+            &access_token,
+            refresh_token.as_deref(),
+        );
+        model.token_expires_at = Set(expires_at);
+        model.update(&txn).await?
+    } else {
+        locked
+    };
+    txn.commit().await?;
+    Ok(access_token)
+}
+
+/// Re-encrypts every user's tokens that aren't on the current key, so an
+/// old key can be removed from `TOKEN_KEYS`. Returns how many users changed.
+pub async fn rotate_token_keys(
+    db: &DatabaseConnection,
+    token_keys: &TokenKeys,
+) -> Result<usize, TokenError> {
+    let stale = user::Column::TokenKeyId
+        .ne(token_keys.current_id())
+        .or(user::Column::TokenKeyId.is_null());
+    reseal_tokens(db, token_keys, stale).await
+}
+
+/// Encrypts tokens saved before tokens were encrypted, so none stay in the
+/// database as plaintext after an upgrade. Run at startup; returns how many
+/// users changed.
+pub async fn encrypt_plaintext_tokens(
+    db: &DatabaseConnection,
+    token_keys: &TokenKeys,
+) -> Result<usize, TokenError> {
+    reseal_tokens(db, token_keys, user::Column::TokenKeyId.is_null()).await
+}
+
+/// Saves the tokens of the users matching `condition` again with the
+/// current key.
+async fn reseal_tokens(
+    db: &DatabaseConnection,
+    token_keys: &TokenKeys,
+    condition: sea_orm::sea_query::SimpleExpr,
+) -> Result<usize, TokenError> {
+    // Locked like a token refresh does, so a refresh running at the same
+    // time isn't overwritten with the tokens read here.
+    let txn = db.begin().await?;
+    let users = UserEntity::find()
+        .filter(condition)
+        .lock_exclusive()
+        .all(&txn)
+        .await?;
+    let rotated = users.len();
+    for user in users {
+        let (access_token, refresh_token) = open_tokens(&user, token_keys)?;
+        let mut model: UserActiveModel = user.into();
+        seal_tokens(
+            &mut model,
+            token_keys,
+            &access_token,
+            refresh_token.as_deref(),
+        );
+        model.update(&txn).await?;
+    }
+    txn.commit().await?;
+    Ok(rotated)
+}
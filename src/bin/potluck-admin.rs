@@ -0,0 +1,388 @@
+//! Command-line tools for running a potluck deployment without going through
+//! the web UI or writing SQL by hand.
+
+use anyhow::{Context, bail};
+use clap::{Parser, Subcommand, ValueEnum};
+use migration::{Migrator, MigratorTrait};
+use potluck_rs::{
+    auth::user::{encrypt_plaintext_tokens, rotate_token_keys},
+    config::Config,
+    database::{connect, setup_database},
+    entities::{
+        organization, person, potluck_round, potluck_series, sea_orm_active_enums::UserRole, user,
+    },
+    export::{GroupColumn, RosterColumn, select_columns, write_groups, write_roster},
+    import::{import_people, parse_rows},
+    pco::sync::resync_organization,
+    planning::{generate::generate_round, groups::load_round, participant::signed_up_participants},
+    router::setup_oauth_client,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Parser)]
+#[command(name = "potluck-admin", about = "Administer a potluck deployment")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Apply, roll back or list database migrations.
+    Migrate {
+        #[command(subcommand)]
+        action: Option<MigrateAction>,
+    },
+    #[command(flatten)]
+    Task(Task),
+}
+
+/// Commands that run against an up-to-date database.
+#[derive(Subcommand)]
+enum Task {
+    /// List organizations.
+    Orgs,
+    /// List the series of an organization.
+    Series {
+        #[arg(long)]
+        org: i32,
+    },
+    /// List the users who can sign in to an organization.
+    Users {
+        #[arg(long)]
+        org: i32,
+    },
+    /// Build the groups for a round, replacing any it already has.
+    Generate {
+        #[arg(long)]
+        series: i32,
+        #[arg(long)]
+        round: i32,
+    },
+    /// Write a series roster as CSV: who is signed up, or with `--round`,
+    /// who is in which group.
+    Roster {
+        #[arg(long)]
+        series: i32,
+        #[arg(long)]
+        round: Option<i32>,
+        /// Comma-separated columns to include, in order. Defaults to all.
+        #[arg(long, value_delimiter = ',')]
+        columns: Vec<String>,
+        /// File to write to instead of stdout.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Add people who aren't in Planning Center from a CSV file.
+    Import {
+        #[arg(long)]
+        org: i32,
+        file: PathBuf,
+        /// Add them without signing them up for potlucks.
+        #[arg(long)]
+        no_sign_up: bool,
+    },
+    /// Refresh people and households from Planning Center.
+    Resync {
+        #[arg(long)]
+        org: i32,
+    },
+    /// Change what a user may do.
+    GrantRole {
+        #[arg(long)]
+        user: i32,
+        #[arg(long, value_enum)]
+        role: Role,
+    },
+    /// Re-encrypt stored Planning Center tokens with the first key in
+    /// `TOKEN_KEYS`, so older keys can be retired.
+    RotateTokenKeys,
+}
+
+#[derive(Subcommand)]
+enum MigrateAction {
+    /// Apply pending migrations (the default).
+    Up,
+    /// Roll back the most recent migrations.
+    Down {
+        #[arg(long, default_value_t = 1)]
+        steps: u32,
+    },
+    /// Show which migrations have been applied.
+    Status,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Role {
+    Member,
+    Organizer,
+    Admin,
+}
+
+impl From<Role> for UserRole {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Member => UserRole::Member,
+            Role::Organizer => UserRole::Organizer,
+            Role::Admin => UserRole::Admin,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let config = Config::from_env()?;
+
+    // Migrations run on their own connection so `down` isn't undone by the
+    // automatic upgrade in `setup_database`.
+    let task = match cli.command {
+        Command::Migrate { action } => {
+            let db = connect(&config.database_url).await?;
+            return migrate(&db, action.unwrap_or(MigrateAction::Up)).await;
+        }
+        Command::Task(task) => task,
+    };
+
+    let (db, _) = setup_database(&config.database_url).await?;
+    encrypt_plaintext_tokens(&db, &config.token_keys).await?;
+    match task {
+        Task::Orgs => list_organizations(&db).await,
+        Task::Series { org } => list_series(&db, org).await,
+        Task::Users { org } => list_users(&db, org).await,
+        Task::Generate { series, round } => generate(&db, series, round).await,
+        Task::Roster {
+            series,
+            round,
+            columns,
+            output,
+        } => {
+            let out: Box<dyn io::Write> = match output {
+                Some(path) => {
+                    Box::new(File::create(&path).with_context(|| path.display().to_string())?)
+                }
+                None => Box::new(io::stdout()),
+            };
+            export_roster(&db, series, round, &columns, out).await
+        }
+        Task::Import {
+            org,
+            file,
+            no_sign_up,
+        } => import(&db, org, &file, !no_sign_up).await,
+        Task::Resync { org } => {
+            let client = setup_oauth_client(&config)?;
+            let report = resync_organization(&db, &client, &config.token_keys, org).await?;
+            println!(
+                "Updated {} people and {} households",
+                report.people, report.households
+            );
+            for person in report.missing {
+                println!(
+                    "Not found in Planning Center: {} ({})",
+                    person.name,
+                    person.pco_id.unwrap_or_default()
+                );
+            }
+            Ok(())
+        }
+        Task::GrantRole { user, role } => grant_role(&db, user, role.into()).await,
+        Task::RotateTokenKeys => {
+            let rotated = rotate_token_keys(&db, &config.token_keys).await?;
+            println!(
+                "Re-encrypted tokens for {rotated} users with key {}",
+                config.token_keys.current_id()
+            );
+            Ok(())
+        }
+    }
+}
+
+async fn migrate(db: &DatabaseConnection, action: MigrateAction) -> anyhow::Result<()> {
+    match action {
+        MigrateAction::Up => Migrator::up(db, None).await?,
+        MigrateAction::Down { steps } => Migrator::down(db, Some(steps)).await?,
+        MigrateAction::Status => Migrator::status(db).await?,
+    }
+    Ok(())
+}
+
+async fn list_organizations(db: &DatabaseConnection) -> anyhow::Result<()> {
+    let organizations = organization::Entity::find()
+        .order_by_asc(organization::Column::Id)
+        .all(db)
+        .await?;
+    for org in organizations {
+        let people = person::Entity::find()
+            .filter(person::Column::OrganizationId.eq(org.id))
+            .count(db)
+            .await?;
+        println!(
+            "{}\t{}\t{} people\tPCO {}",
+            org.id, org.name, people, org.pco_id
+        );
+    }
+    Ok(())
+}
+
+async fn list_series(db: &DatabaseConnection, organization_id: i32) -> anyhow::Result<()> {
+    let series = potluck_series::Entity::find()
+        .filter(potluck_series::Column::OrganizationId.eq(organization_id))
+        .order_by_desc(potluck_series::Column::StartDate)
+        .all(db)
+        .await?;
+    for series in series {
+        let rounds = potluck_round::Entity::find()
+            .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+            .count(db)
+            .await?;
+        println!(
+            "{}\t{}\t{} to {}\t{} rounds",
+            series.id, series.name, series.start_date, series.end_date, rounds
+        );
+    }
+    Ok(())
+}
+
+async fn list_users(db: &DatabaseConnection, organization_id: i32) -> anyhow::Result<()> {
+    let users = user::Entity::find()
+        .filter(user::Column::OrganizationId.eq(organization_id))
+        .find_also_related(person::Entity)
+        .order_by_asc(user::Column::Id)
+        .all(db)
+        .await?;
+    for (user, person) in users {
+        let (name, email) = person
+            .map(|p| (p.name, p.email.unwrap_or_default()))
+            .unwrap_or_default();
+        println!("{}\t{}\t{}\t{:?}", user.id, name, email, user.role);
+    }
+    Ok(())
+}
+
+async fn find_series(
+    db: &DatabaseConnection,
+    series_id: i32,
+) -> anyhow::Result<potluck_series::Model> {
+    potluck_series::Entity::find_by_id(series_id)
+        .one(db)
+        .await?
+        .with_context(|| format!("No series with id {series_id}"))
+}
+
+async fn generate(db: &DatabaseConnection, series_id: i32, round: i32) -> anyhow::Result<()> {
+    let series = find_series(db, series_id).await?;
+    let Some(round) = potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+        .filter(potluck_round::Column::Round.eq(round))
+        .one(db)
+        .await?
+    else {
+        bail!("{} has no round {round}", series.name);
+    };
+
+    let txn = db.begin().await?;
+    let outcome = generate_round(&txn, &series, &round).await?;
+    txn.commit().await?;
+
+    println!(
+        "Round {} of {}: {} groups",
+        round.round,
+        series.name,
+        outcome.plan.groups.len()
+    );
+    for group in &outcome.plan.groups {
+        let guests: Vec<&str> = group.guests.iter().map(|g| g.name.as_str()).collect();
+        println!("  {}: {}", group.host.name, guests.join(", "));
+    }
+    if !outcome.plan.waitlist.is_empty() {
+        let names: Vec<&str> = outcome
+            .plan
+            .waitlist
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        println!("Waitlist: {}", names.join(", "));
+    }
+    if !outcome.unavailable.is_empty() {
+        let names: Vec<&str> = outcome
+            .unavailable
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        println!("Away: {}", names.join(", "));
+    }
+    Ok(())
+}
+
+async fn export_roster(
+    db: &DatabaseConnection,
+    series_id: i32,
+    round: Option<i32>,
+    columns: &[String],
+    out: Box<dyn io::Write>,
+) -> anyhow::Result<()> {
+    let series = find_series(db, series_id).await?;
+    match round {
+        None => {
+            let columns = select_columns(&RosterColumn::ALL, RosterColumn::key, columns)
+                .map_err(anyhow::Error::msg)?;
+            let participants = signed_up_participants(db, series.organization_id).await?;
+            write_roster(out, &participants, &columns)?;
+        }
+        Some(round) => {
+            let columns = select_columns(&GroupColumn::ALL, GroupColumn::key, columns)
+                .map_err(anyhow::Error::msg)?;
+            let groups = load_round(db, series.id, round).await?;
+            write_groups(out, &groups, &columns)?;
+        }
+    }
+    Ok(())
+}
+
+async fn import(
+    db: &DatabaseConnection,
+    organization_id: i32,
+    path: &Path,
+    sign_up: bool,
+) -> anyhow::Result<()> {
+    let file = File::open(path).with_context(|| path.display().to_string())?;
+    let rows = match parse_rows(file) {
+        Ok(rows) => rows,
+        Err(errors) => bail!("Nothing was imported:\n{}", errors.join("\n")),
+    };
+
+    let txn = db.begin().await?;
+    let report = import_people(&txn, organization_id, rows, sign_up, None).await?;
+    txn.commit().await?;
+
+    println!(
+        "Added {} people in {} new households",
+        report.created.len(),
+        report.households
+    );
+    for matched in report.matched {
+        println!("Already on file: {} -> {}", matched.name, matched.existing);
+    }
+    Ok(())
+}
+
+async fn grant_role(db: &DatabaseConnection, user_id: i32, role: UserRole) -> anyhow::Result<()> {
+    let user = user::Entity::find_by_id(user_id)
+        .one(db)
+        .await?
+        .with_context(|| format!("No user with id {user_id}"))?;
+    let mut model = user.into_active_model();
+    model.role = Set(role);
+    model.update(db).await?;
+    println!("User {user_id} is now {role:?}");
+    Ok(())
+}
@@ -1,5 +1,20 @@
+use anyhow::Context;
 use oauth2::{ClientId, ClientSecret, RedirectUrl};
-use std::env;
+use std::{env, net::SocketAddr};
+
+use crate::auth::crypto::TokenKeys;
+
+/// How the session cookie is set.
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    /// Only send the cookie over HTTPS. Defaults to on when the OAuth
+    /// redirect URL is HTTPS.
+    pub secure: bool,
+    pub cookie_name: String,
+    pub cookie_domain: Option<String>,
+    /// How long a session lasts without requests.
+    pub inactivity_hours: i64,
+}
 
 #[derive(Clone)]
 pub struct Config {
@@ -8,6 +23,12 @@ pub struct Config {
     pub client_id: ClientId,
     pub client_secret: ClientSecret,
     pub redirect_url: RedirectUrl,
+    pub token_keys: TokenKeys,
+    pub bind_address: SocketAddr,
+    pub session: SessionConfig,
+    /// Believe `X-Forwarded-For` and friends. Only turn this on behind a
+    /// reverse proxy that sets them, or clients can claim any address.
+    pub trust_proxy_headers: bool,
 }
 
 impl Config {
@@ -24,6 +45,20 @@ impl Config {
         let redirect_url = env::var("PLANNING_CENTER_REDIRECT_URI")
             .map(RedirectUrl::new)
             .expect("PLANNING_CENTER_REDIRECT_URI should be provided")?;
+        let token_keys =
+            TokenKeys::parse(&env::var("TOKEN_KEYS").expect("TOKEN_KEYS should be provided"))?;
+        let bind_address = parse_var("BIND_ADDRESS", "0.0.0.0:3000".parse()?)?;
+
+        let behind_https = redirect_url.url().scheme() == "https";
+        let session = SessionConfig {
+            secure: parse_flag("SESSION_COOKIE_SECURE", behind_https)?,
+            cookie_name: env::var("SESSION_COOKIE_NAME").unwrap_or_else(|_| "id".into()),
+            cookie_domain: env::var("SESSION_COOKIE_DOMAIN")
+                .ok()
+                .filter(|d| !d.is_empty()),
+            inactivity_hours: parse_var("SESSION_INACTIVITY_HOURS", 24)?,
+        };
+        let trust_proxy_headers = parse_flag("TRUST_PROXY_HEADERS", false)?;
 
         Ok(Self {
             database_url,
@@ -31,6 +66,36 @@ impl Config {
             client_id,
             client_secret,
             redirect_url,
+            token_keys,
+            bind_address,
+            session,
+            trust_proxy_headers,
         })
     }
 }
+
+/// Reads an optional variable, falling back to `default` when it is unset.
+fn parse_var<T>(name: &str, default: T) -> anyhow::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match env::var(name) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .with_context(|| format!("{name} is invalid")),
+        Err(_) => Ok(default),
+    }
+}
+
+fn parse_flag(name: &str, default: bool) -> anyhow::Result<bool> {
+    match env::var(name).map(|v| v.trim().to_ascii_lowercase()) {
+        Ok(value) => match value.as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" => Ok(false),
+            _ => anyhow::bail!("{name} should be true or false"),
+        },
+        Err(_) => Ok(default),
+    }
+}
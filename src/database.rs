@@ -1,6 +1,11 @@
 use migration::{Migrator, MigratorTrait};
 use sea_orm::{sqlx::PgPool, Database, DatabaseConnection};
 
+/// Connects without running migrations, for tools that manage them.
+pub async fn connect(db_url: &str) -> anyhow::Result<DatabaseConnection> {
+    Ok(Database::connect(db_url).await?)
+}
+
 pub async fn setup_database(db_url: &str) -> anyhow::Result<(DatabaseConnection, PgPool)> {
     let db = Database::connect(db_url)
         .await
@@ -0,0 +1,392 @@
+//! Signing people up from Planning Center, one at a time or many at once.
+//!
+//! Bulk sign-ups run as background jobs so a whole congregation can be
+//! enrolled without waiting on one request. Progress lives in memory, so a
+//! job in flight when the server restarts stops where it was; signing up
+//! again picks up anyone it missed.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, TransactionTrait,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditEntity, Change},
+    auth::{crypto::TokenKeys, user::ensure_valid_access_token},
+    entities::{household, organization, person, user},
+    pco::{
+        household::get_household_people,
+        person::{HouseholdInfo, PersonData, get_person},
+    },
+    router::OauthClient,
+};
+
+/// How long a finished job's summary stays available.
+const KEEP_FINISHED: Duration = Duration::from_secs(60 * 60);
+
+/// Signs up a Planning Center household and everyone in it. Returns what
+/// changed.
+pub async fn sign_up_household<C>(
+    db: &C,
+    organization_id: i32,
+    household_id: String,
+    household_info: HouseholdInfo,
+) -> Result<Vec<Change>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut changes = Vec::new();
+    let existing = household::Entity::find()
+        .filter(household::Column::PcoId.eq(&household_id))
+        .one(db)
+        .await?;
+    let household_model = match existing {
+        Some(existing) => {
+            let joining = !existing.is_signed_up;
+            let before = existing.clone();
+            let mut active_model: household::ActiveModel = existing.into();
+            active_model.name = Set(household_info.name);
+            active_model.avatar_url = Set(household_info.avatar);
+            active_model.is_signed_up = Set(true);
+            if joining {
+                active_model.signed_up_at = Set(Some(chrono::Utc::now().naive_utc()));
+            }
+            let model = active_model.update(db).await?;
+            changes.extend(Change::diff(
+                AuditEntity::Household,
+                model.id,
+                &model.name,
+                &before,
+                &model,
+            ));
+            model
+        }
+        None => {
+            let new_household = household::ActiveModel {
+                pco_id: Set(Some(household_id)),
+                organization_id: Set(organization_id),
+                name: Set(household_info.name),
+                avatar_url: Set(household_info.avatar),
+                is_signed_up: Set(true),
+                can_host: Set(false),
+                signed_up_at: Set(Some(chrono::Utc::now().naive_utc())),
+                ..Default::default()
+            };
+            let model = new_household.insert(db).await?;
+            changes.push(Change::created(
+                AuditEntity::Household,
+                model.id,
+                &model.name,
+            ));
+            model
+        }
+    };
+
+    if let Some(people) = household_info.people {
+        for pco_person in people {
+            let existing_person = person::Entity::find()
+                .filter(person::Column::PcoId.eq(&pco_person.id))
+                .one(db)
+                .await?;
+
+            if let Some(existing) = existing_person {
+                let before = existing.clone();
+                let mut active_model: person::ActiveModel = existing.into();
+                active_model.name = Set(pco_person.name);
+                active_model.email = Set(pco_person.email);
+                active_model.phone = Set(pco_person.phone);
+                active_model.address = Set(pco_person.address.unwrap_or_default());
+                active_model.avatar_url = Set(pco_person.avatar);
+                active_model.is_child = Set(pco_person.is_child);
+                active_model.household_id = Set(Some(household_model.id));
+                let model = active_model.update(db).await?;
+                changes.extend(Change::diff(
+                    AuditEntity::Person,
+                    model.id,
+                    &model.name,
+                    &before,
+                    &model,
+                ));
+            } else {
+                let new_person = person::ActiveModel {
+                    pco_id: Set(Some(pco_person.id)),
+                    organization_id: Set(organization_id),
+                    name: Set(pco_person.name),
+                    email: Set(pco_person.email),
+                    phone: Set(pco_person.phone),
+                    address: Set(pco_person.address.unwrap_or_default()),
+                    avatar_url: Set(pco_person.avatar),
+                    is_signed_up: Set(false),
+                    can_host: Set(false),
+                    is_child: Set(pco_person.is_child),
+                    household_id: Set(Some(household_model.id)),
+                    ..Default::default()
+                };
+                let model = new_person.insert(db).await?;
+                changes.push(Change::created(AuditEntity::Person, model.id, &model.name));
+            }
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Signs up one person from Planning Center, creating or refreshing their
+/// organization and their record. Returns the organization they were
+/// signed up in and what changed.
+pub async fn sign_up_person<C>(
+    db: &C,
+    organization_id: i32,
+    person_data: PersonData,
+) -> Result<(i32, Vec<Change>), DbErr>
+where
+    C: ConnectionTrait,
+{
+    // Handle Organization
+    let organization_model = if let Some(org_info) = person_data.organization.clone() {
+        let existing = organization::Entity::find()
+            .filter(organization::Column::PcoId.eq(&org_info.id))
+            .one(db)
+            .await?;
+        match existing {
+            Some(existing) => {
+                let mut active_model: organization::ActiveModel = existing.into();
+                active_model.name = Set(org_info.name);
+                active_model.avatar_url = Set(org_info.avatar_url);
+                active_model.update(db).await?
+            }
+            None => {
+                let new_org = organization::ActiveModel {
+                    pco_id: Set(org_info.id),
+                    name: Set(org_info.name),
+                    avatar_url: Set(org_info.avatar_url),
+                    ..Default::default()
+                };
+                new_org.insert(db).await?
+            }
+        }
+    } else {
+        // If no organization data from PCO, use the user's organization
+        organization::Entity::find_by_id(organization_id)
+            .one(db)
+            .await?
+            .ok_or_else(|| {
+                DbErr::RecordNotFound(format!("Organization {organization_id} not found for user"))
+            })?
+    };
+
+    // Handle Person
+    let existing_person = person::Entity::find()
+        .filter(person::Column::PcoId.eq(&person_data.id))
+        .one(db)
+        .await?;
+
+    let changes = if let Some(existing) = existing_person {
+        let joining = !existing.is_signed_up;
+        let before = existing.clone();
+        let mut active_model: person::ActiveModel = existing.into();
+        active_model.name = Set(person_data.name);
+        active_model.email = Set(person_data.email);
+        active_model.phone = Set(person_data.phone);
+        active_model.address = Set(person_data.address.unwrap_or_default());
+        active_model.avatar_url = Set(person_data.avatar);
+        active_model.is_child = Set(person_data.is_child);
+        active_model.is_signed_up = Set(true);
+        if joining {
+            active_model.signed_up_at = Set(Some(chrono::Utc::now().naive_utc()));
+        }
+        active_model.organization_id = Set(organization_model.id);
+        let model = active_model.update(db).await?;
+        Change::diff(AuditEntity::Person, model.id, &model.name, &before, &model)
+    } else {
+        let new_person = person::ActiveModel {
+            pco_id: Set(Some(person_data.id)),
+            organization_id: Set(organization_model.id),
+            name: Set(person_data.name),
+            email: Set(person_data.email),
+            phone: Set(person_data.phone),
+            address: Set(person_data.address.unwrap_or_default()),
+            avatar_url: Set(person_data.avatar),
+            is_signed_up: Set(true),
+            can_host: Set(false),
+            is_child: Set(person_data.is_child),
+            household_id: Set(None),
+            signed_up_at: Set(Some(chrono::Utc::now().naive_utc())),
+            ..Default::default()
+        };
+        let model = new_person.insert(db).await?;
+        vec![Change::created(AuditEntity::Person, model.id, &model.name)]
+    };
+    Ok((organization_model.id, changes))
+}
+
+/// Someone to sign up, by Planning Center id.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", content = "id", rename_all = "snake_case")]
+pub enum Target {
+    Person(String),
+    Household(String),
+}
+
+/// A target that couldn't be signed up, and why.
+#[derive(Debug, Clone, Serialize)]
+pub struct Failure {
+    pub target: Target,
+    pub error: String,
+}
+
+/// How far a bulk sign-up has got.
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub total: usize,
+    pub done: usize,
+    /// Names of the people and households signed up so far.
+    pub signed_up: Vec<String>,
+    pub failures: Vec<Failure>,
+    pub finished: bool,
+    #[serde(skip)]
+    organization_id: i32,
+    #[serde(skip)]
+    finished_at: Option<Instant>,
+}
+
+/// Bulk sign-ups that are running or finished recently.
+#[derive(Clone, Default)]
+pub struct Jobs(Arc<Mutex<HashMap<Uuid, Progress>>>);
+
+impl Jobs {
+    /// Starts signing up `targets` in the background with `user`'s
+    /// Planning Center login, and returns the job's id.
+    pub fn start(
+        &self,
+        db: DatabaseConnection,
+        client: OauthClient,
+        token_keys: TokenKeys,
+        user: user::Model,
+        targets: Vec<Target>,
+    ) -> Uuid {
+        let id = Uuid::new_v4();
+        {
+            let mut jobs = self.0.lock().unwrap();
+            jobs.retain(|_, job| {
+                job.finished_at
+                    .is_none_or(|at| at.elapsed() < KEEP_FINISHED)
+            });
+            jobs.insert(
+                id,
+                Progress {
+                    total: targets.len(),
+                    done: 0,
+                    signed_up: Vec::new(),
+                    failures: Vec::new(),
+                    finished: false,
+                    organization_id: user.organization_id,
+                    finished_at: None,
+                },
+            );
+        }
+        let jobs = self.clone();
+        tokio::spawn(async move {
+            jobs.run(id, &db, &client, &token_keys, user, targets).await;
+        });
+        id
+    }
+
+    /// The progress of a job started in `organization_id`.
+    pub fn progress(&self, id: Uuid, organization_id: i32) -> Option<Progress> {
+        let jobs = self.0.lock().unwrap();
+        jobs.get(&id)
+            .filter(|job| job.organization_id == organization_id)
+            .cloned()
+    }
+
+    fn update(&self, id: Uuid, f: impl FnOnce(&mut Progress)) {
+        if let Some(job) = self.0.lock().unwrap().get_mut(&id) {
+            f(job);
+        }
+    }
+
+    async fn run(
+        &self,
+        id: Uuid,
+        db: &DatabaseConnection,
+        client: &OauthClient,
+        token_keys: &TokenKeys,
+        mut user: user::Model,
+        targets: Vec<Target>,
+    ) {
+        for target in targets {
+            let result = enroll(db, client, token_keys, &mut user, &target).await;
+            if let Err(e) = &result {
+                tracing::warn!("Bulk sign-up of {target:?} failed: {e:#}");
+            }
+            self.update(id, |job| {
+                job.done += 1;
+                match result {
+                    Ok(name) => job.signed_up.push(name),
+                    Err(e) => job.failures.push(Failure {
+                        target,
+                        error: e.to_string(),
+                    }),
+                }
+            });
+        }
+        self.update(id, |job| {
+            job.finished = true;
+            job.finished_at = Some(Instant::now());
+        });
+    }
+}
+
+/// Signs up one target in its own transaction, so one failure doesn't undo
+/// the others. Returns the name of who was signed up.
+async fn enroll(
+    db: &DatabaseConnection,
+    client: &OauthClient,
+    token_keys: &TokenKeys,
+    user: &mut user::Model,
+    target: &Target,
+) -> anyhow::Result<String> {
+    let access_token = ensure_valid_access_token(user, db, client, token_keys)
+        .await
+        .context("Couldn't sign in to Planning Center")?;
+
+    let txn = db.begin().await?;
+    let (organization_id, name, changes) = match target {
+        Target::Person(id) => {
+            let person_data = get_person(&access_token, id)
+                .await
+                .context("Couldn't fetch them from Planning Center")?
+                .context("Planning Center has no such person")?;
+            let name = person_data.name.clone();
+            let (organization_id, changes) =
+                sign_up_person(&txn, user.organization_id, person_data)
+                    .await
+                    .context("Couldn't save them")?;
+            (organization_id, name, changes)
+        }
+        Target::Household(id) => {
+            let household_info = get_household_people(&access_token, id)
+                .await
+                .context("Couldn't fetch the household from Planning Center")?
+                .context("Planning Center has no such household")?;
+            let name = household_info.name.clone();
+            let changes = sign_up_household(&txn, user.organization_id, id.clone(), household_info)
+                .await
+                .context("Couldn't save the household")?;
+            (user.organization_id, name, changes)
+        }
+    };
+    audit::record(&txn, organization_id, Some(user.id), changes).await?;
+    txn.commit().await?;
+    Ok(name)
+}
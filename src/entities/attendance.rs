@@ -2,9 +2,11 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "attendance")]
+#[schema(as = Attendance)]
 pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
@@ -14,6 +16,9 @@ pub struct Model {
     pub organization_id: i32,
     pub attendee_person_id: Option<i32>,
     pub attendee_household_id: Option<i32>,
+    pub cancelled_at: Option<DateTime>,
+    pub moved_from_potluck_id: Option<i32>,
+    pub dish: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
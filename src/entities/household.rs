@@ -2,21 +2,27 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+use super::sea_orm_active_enums::RecordSource;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "household")]
+#[schema(as = Household)]
 pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     #[sea_orm(primary_key, auto_increment = true)]
     pub id: i32,
     #[sea_orm(unique)]
-    pub pco_id: String,
+    pub pco_id: Option<String>,
     pub organization_id: i32,
     pub name: String,
     pub is_signed_up: bool,
     pub can_host: bool,
     pub avatar_url: Option<String>,
+    pub signed_up_at: Option<DateTime>,
+    pub source: RecordSource,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -35,6 +41,10 @@ pub enum Relation {
     Person,
     #[sea_orm(has_many = "super::potluck::Entity")]
     Potluck,
+    #[sea_orm(has_many = "super::unavailability::Entity")]
+    Unavailability,
+    #[sea_orm(has_many = "super::waitlist::Entity")]
+    Waitlist,
 }
 
 impl Related<super::attendance::Entity> for Entity {
@@ -61,4 +71,16 @@ impl Related<super::potluck::Entity> for Entity {
     }
 }
 
+impl Related<super::unavailability::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Unavailability.def()
+    }
+}
+
+impl Related<super::waitlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waitlist.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,11 +2,21 @@
 
 pub mod prelude;
 
+pub mod account;
+pub mod api_token;
 pub mod attendance;
+pub mod audit_log;
 pub mod household;
+pub mod merge_record;
+pub mod notification;
 pub mod organization;
 pub mod pairing_history;
 pub mod person;
 pub mod potluck;
+pub mod potluck_round;
 pub mod potluck_series;
+pub mod sea_orm_active_enums;
+pub mod series_template;
+pub mod unavailability;
 pub mod user;
+pub mod waitlist;
@@ -18,32 +18,72 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::api_token::Entity")]
+    ApiToken,
     #[sea_orm(has_many = "super::attendance::Entity")]
     Attendance,
+    #[sea_orm(has_many = "super::audit_log::Entity")]
+    AuditLog,
     #[sea_orm(has_many = "super::household::Entity")]
     Household,
+    #[sea_orm(has_many = "super::merge_record::Entity")]
+    MergeRecord,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
     #[sea_orm(has_many = "super::pairing_history::Entity")]
     PairingHistory,
     #[sea_orm(has_many = "super::potluck::Entity")]
     Potluck,
+    #[sea_orm(has_many = "super::potluck_round::Entity")]
+    PotluckRound,
     #[sea_orm(has_many = "super::potluck_series::Entity")]
     PotluckSeries,
+    #[sea_orm(has_many = "super::series_template::Entity")]
+    SeriesTemplate,
+    #[sea_orm(has_many = "super::unavailability::Entity")]
+    Unavailability,
+    #[sea_orm(has_many = "super::waitlist::Entity")]
+    Waitlist,
     #[sea_orm(has_many = "super::user::Entity")]
     User,
 }
 
+impl Related<super::api_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiToken.def()
+    }
+}
+
 impl Related<super::attendance::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Attendance.def()
     }
 }
 
+impl Related<super::audit_log::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AuditLog.def()
+    }
+}
+
 impl Related<super::household::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Household.def()
     }
 }
 
+impl Related<super::merge_record::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MergeRecord.def()
+    }
+}
+
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl Related<super::pairing_history::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PairingHistory.def()
@@ -56,12 +96,36 @@ impl Related<super::potluck::Entity> for Entity {
     }
 }
 
+impl Related<super::potluck_round::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PotluckRound.def()
+    }
+}
+
 impl Related<super::potluck_series::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::PotluckSeries.def()
     }
 }
 
+impl Related<super::series_template::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SeriesTemplate.def()
+    }
+}
+
+impl Related<super::unavailability::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Unavailability.def()
+    }
+}
+
+impl Related<super::waitlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waitlist.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -2,26 +2,35 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+use super::sea_orm_active_enums::RecordSource;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "person")]
+#[schema(as = Person)]
 pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     #[sea_orm(primary_key, auto_increment = true)]
     pub id: i32,
     #[sea_orm(unique)]
-    pub pco_id: String,
+    pub pco_id: Option<String>,
     pub organization_id: i32,
     pub name: String,
     pub email: Option<String>,
     pub phone: Option<String>,
+    #[schema(value_type = Object)]
     pub address: Json,
     pub avatar_url: Option<String>,
     pub is_signed_up: bool,
     pub can_host: bool,
     pub is_child: bool,
     pub household_id: Option<i32>,
+    pub signed_up_at: Option<DateTime>,
+    /// Allergies and diets, shared with the people cooking for them.
+    pub dietary_notes: Option<String>,
+    pub source: RecordSource,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -36,8 +45,14 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Household,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
     #[sea_orm(has_many = "super::potluck::Entity")]
     Potluck,
+    #[sea_orm(has_many = "super::unavailability::Entity")]
+    Unavailability,
+    #[sea_orm(has_many = "super::waitlist::Entity")]
+    Waitlist,
     #[sea_orm(has_many = "super::user::Entity")]
     User,
 }
@@ -54,12 +69,30 @@ impl Related<super::household::Entity> for Entity {
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl Related<super::potluck::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Potluck.def()
     }
 }
 
+impl Related<super::unavailability::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Unavailability.def()
+    }
+}
+
+impl Related<super::waitlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waitlist.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
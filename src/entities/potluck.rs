@@ -2,19 +2,23 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "potluck")]
+#[schema(as = Potluck)]
 pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
-    #[sea_orm(primary_key, auto_increment = false)]
     #[sea_orm(primary_key, auto_increment = true)]
     pub id: i32,
     pub organization_id: i32,
     pub potluck_series_id: i32,
     pub host_person_id: Option<i32>,
     pub host_household_id: Option<i32>,
+    pub round: i32,
+    pub cancelled_at: Option<DateTime>,
+    pub host_cancelled_at: Option<DateTime>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,6 +33,8 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Household,
+    #[sea_orm(has_many = "super::notification::Entity")]
+    Notification,
     #[sea_orm(
         belongs_to = "super::organization::Entity",
         from = "Column::OrganizationId",
@@ -69,6 +75,12 @@ impl Related<super::household::Entity> for Entity {
     }
 }
 
+impl Related<super::notification::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Notification.def()
+    }
+}
+
 impl Related<super::organization::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Organization.def()
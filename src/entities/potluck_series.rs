@@ -2,9 +2,11 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, ToSchema)]
 #[sea_orm(table_name = "potluck_series")]
+#[schema(as = PotluckSeries)]
 pub struct Model {
     pub created_at: DateTime,
     pub updated_at: DateTime,
@@ -15,6 +17,7 @@ pub struct Model {
     pub start_date: Date,
     pub end_date: Date,
     pub description: Option<String>,
+    pub group_size: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -29,6 +32,12 @@ pub enum Relation {
     Organization,
     #[sea_orm(has_many = "super::potluck::Entity")]
     Potluck,
+    #[sea_orm(has_many = "super::potluck_round::Entity")]
+    PotluckRound,
+    #[sea_orm(has_many = "super::unavailability::Entity")]
+    Unavailability,
+    #[sea_orm(has_many = "super::waitlist::Entity")]
+    Waitlist,
 }
 
 impl Related<super::organization::Entity> for Entity {
@@ -43,4 +52,22 @@ impl Related<super::potluck::Entity> for Entity {
     }
 }
 
+impl Related<super::potluck_round::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PotluckRound.def()
+    }
+}
+
+impl Related<super::unavailability::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Unavailability.def()
+    }
+}
+
+impl Related<super::waitlist::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Waitlist.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
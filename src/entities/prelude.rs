@@ -1,11 +1,20 @@
 #![allow(unused_imports)]
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.8
 
+pub use super::account::Entity as Account;
+pub use super::api_token::Entity as ApiToken;
 pub use super::attendance::Entity as Attendance;
+pub use super::audit_log::Entity as AuditLog;
 pub use super::household::Entity as Household;
+pub use super::merge_record::Entity as MergeRecord;
+pub use super::notification::Entity as Notification;
 pub use super::organization::Entity as Organization;
 pub use super::pairing_history::Entity as PairingHistory;
 pub use super::person::Entity as Person;
 pub use super::potluck::Entity as Potluck;
+pub use super::potluck_round::Entity as PotluckRound;
 pub use super::potluck_series::Entity as PotluckSeries;
-pub use super::user::Entity as User;
\ No newline at end of file
+pub use super::series_template::Entity as SeriesTemplate;
+pub use super::unavailability::Entity as Unavailability;
+pub use super::user::Entity as User;
+pub use super::waitlist::Entity as Waitlist;
\ No newline at end of file
@@ -2,6 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "attendee_type")]
@@ -11,3 +12,81 @@ pub enum AttendeeType {
     #[sea_orm(string_value = "household")]
     Household,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    #[sea_orm(string_value = "member")]
+    Member,
+    #[sea_orm(string_value = "organizer")]
+    Organizer,
+    #[sea_orm(string_value = "admin")]
+    Admin,
+}
+
+/// Where a person or household record came from.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum RecordSource {
+    #[sea_orm(string_value = "pco")]
+    Pco,
+    #[sea_orm(string_value = "import")]
+    Import,
+    #[sea_orm(string_value = "manual")]
+    Manual,
+}
+
+/// Which kind of record a merge combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum MergeKind {
+    #[sea_orm(string_value = "person")]
+    Person,
+    #[sea_orm(string_value = "household")]
+    Household,
+}
+
+/// What happened to a record in the audit log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    #[sea_orm(string_value = "create")]
+    Create,
+    #[sea_orm(string_value = "update")]
+    Update,
+    #[sea_orm(string_value = "delete")]
+    Delete,
+    #[sea_orm(string_value = "merge")]
+    Merge,
+}
+
+/// The kind of record an audit log entry is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::N(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    #[sea_orm(string_value = "person")]
+    Person,
+    #[sea_orm(string_value = "household")]
+    Household,
+    #[sea_orm(string_value = "series")]
+    Series,
+    #[sea_orm(string_value = "round")]
+    Round,
+    #[sea_orm(string_value = "potluck")]
+    Potluck,
+    #[sea_orm(string_value = "attendance")]
+    Attendance,
+    #[sea_orm(string_value = "series_template")]
+    SeriesTemplate,
+    #[sea_orm(string_value = "unavailability")]
+    Unavailability,
+    #[sea_orm(string_value = "api_token")]
+    ApiToken,
+}
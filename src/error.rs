@@ -0,0 +1,211 @@
+//! The error type for page handlers, so a failure becomes the right status
+//! and something a person can read rather than a bare status or a panic.
+//!
+//! [`AppError`] responses carry an [`ErrorPage`] that [`render_errors`]
+//! turns into `error.html`, or into the `_error.html` banner for HTMX
+//! requests. Being signed out sends the browser to the login page instead,
+//! the same way [`guard::login_required`] does. Under `/api` the same
+//! errors, including panics and failed CSRF checks, are answered in the
+//! API's JSON error format instead, since API clients can't read pages.
+
+use axum::{
+    Json,
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use minijinja::context;
+use sea_orm::DbErr;
+use std::any::Any;
+
+use crate::{
+    auth::{guard, user::TokenError},
+    router::AppState,
+    routes::api::v1::ErrorBody,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("That page doesn't exist")]
+    NotFound,
+
+    #[error("You need to sign in")]
+    Unauthorized,
+
+    #[error("You aren't allowed to do that")]
+    Forbidden,
+
+    #[error("This page has expired. Reload it and try again.")]
+    CsrfMismatch,
+
+    #[error("{0}")]
+    BadRequest(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    /// A well-formed form whose values don't make sense together.
+    #[error("{0}")]
+    Unprocessable(String),
+
+    #[error(transparent)]
+    Database(#[from] DbErr),
+
+    #[error(transparent)]
+    Template(#[from] minijinja::Error),
+
+    #[error(transparent)]
+    Token(#[from] TokenError),
+
+    #[error("Planning Center request failed: {0}")]
+    Pco(#[from] reqwest::Error),
+
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl AppError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound | AppError::Database(DbErr::RecordNotFound(_)) => {
+                StatusCode::NOT_FOUND
+            }
+            AppError::Unauthorized | AppError::Token(TokenError::Revoked) => {
+                StatusCode::UNAUTHORIZED
+            }
+            AppError::Forbidden | AppError::CsrfMismatch => StatusCode::FORBIDDEN,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Pco(_) | AppError::Token(TokenError::OAuth2(_)) => StatusCode::BAD_GATEWAY,
+            AppError::Database(_)
+            | AppError::Template(_)
+            | AppError::Token(_)
+            | AppError::Other(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// What the person sees. Server errors are logged rather than shown.
+    fn message(&self) -> String {
+        match self.status() {
+            StatusCode::BAD_GATEWAY => {
+                "Planning Center didn't respond as expected. Try again in a moment.".to_string()
+            }
+            StatusCode::NOT_FOUND => AppError::NotFound.to_string(),
+            status if status.is_server_error() => {
+                "Something went wrong on our end. Try again, and let an admin know if it keeps happening.".to_string()
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Left on error responses for [`render_errors`].
+#[derive(Clone, Debug)]
+pub struct ErrorPage {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            tracing::error!(error = ?self, "{self}");
+        } else {
+            tracing::debug!("{self}");
+        }
+
+        let page = ErrorPage {
+            status,
+            message: self.message(),
+        };
+        let mut response = (status, page.message.clone()).into_response();
+        response.extensions_mut().insert(page);
+        response
+    }
+}
+
+/// Turns a handler panic into an ordinary server error, so one bad request
+/// doesn't take its connection down with it.
+pub fn panic_response(panic: Box<dyn Any + Send + 'static>) -> Response {
+    let detail = panic
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| panic.downcast_ref::<&str>().copied())
+        .unwrap_or("unknown panic");
+    AppError::Other(anyhow::anyhow!("Handler panicked: {detail}")).into_response()
+}
+
+/// Whether the request is for the JSON API under `/api`.
+pub(crate) fn is_api(request: &Request) -> bool {
+    let path = request.uri().path();
+    path == "/api" || path.starts_with("/api/")
+}
+
+/// Middleware that renders [`AppError`] responses as pages, or for HTMX
+/// requests as a banner swapped into `#error-banner`.
+pub async fn render_errors(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let api = is_api(&request);
+    let htmx = guard::is_htmx(&request);
+    let return_to = guard::return_to(&request);
+    let response = next.run(request).await;
+    let Some(page) = response.extensions().get::<ErrorPage>().cloned() else {
+        return response;
+    };
+
+    if api {
+        let body = ErrorBody::for_status(page.status, page.message);
+        return (page.status, Json(body)).into_response();
+    }
+
+    if page.status == StatusCode::UNAUTHORIZED {
+        return guard::login_redirect(htmx, return_to.as_deref());
+    }
+
+    let template = if htmx { "_error.html" } else { "error.html" };
+    let html = state.templates.get_template(template).and_then(|tmpl| {
+        tmpl.render(context! {
+            status => page.status.as_u16(),
+            reason => page.status.canonical_reason(),
+            message => page.message,
+        })
+    });
+    let Ok(html) = html else {
+        return response;
+    };
+
+    let mut rendered = (page.status, Html(html)).into_response();
+    if htmx {
+        let headers = rendered.headers_mut();
+        headers.insert("HX-Retarget", HeaderValue::from_static("#error-banner"));
+        headers.insert("HX-Reswap", HeaderValue::from_static("innerHTML"));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, extract::Request};
+
+    use super::is_api;
+
+    fn request(path: &str) -> Request {
+        Request::builder().uri(path).body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn api_paths_are_recognised() {
+        for path in ["/api", "/api/v1/series", "/api/v1/people?page=2"] {
+            assert!(is_api(&request(path)), "{path}");
+        }
+        for path in ["/", "/apiary", "/people/api", "/me/tokens"] {
+            assert!(!is_api(&request(path)), "{path}");
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! CSV exports of series rosters and round groups, for organizers who print
+//! lists or hand them to small-group leaders.
+//!
+//! Both exports take the columns to include, in order, so the same code
+//! serves the web download and `potluck-admin roster`.
+
+use std::io;
+
+use serde_json::Value;
+
+use crate::{
+    entities::person,
+    planning::{groups::Group, participant::Participant},
+};
+
+/// Columns of the roster export, one row per person.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RosterColumn {
+    Name,
+    Household,
+    Email,
+    Phone,
+    Address,
+    CanHost,
+    DietaryNotes,
+}
+
+impl RosterColumn {
+    pub const ALL: [Self; 7] = [
+        Self::Name,
+        Self::Household,
+        Self::Email,
+        Self::Phone,
+        Self::Address,
+        Self::CanHost,
+        Self::DietaryNotes,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Household => "household",
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::Address => "address",
+            Self::CanHost => "can_host",
+            Self::DietaryNotes => "dietary_notes",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Name => "Name",
+            Self::Household => "Household",
+            Self::Email => "Email",
+            Self::Phone => "Phone",
+            Self::Address => "Address",
+            Self::CanHost => "Can host",
+            Self::DietaryNotes => "Dietary notes",
+        }
+    }
+}
+
+/// Columns of the groups export, one row per potluck.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupColumn {
+    Round,
+    Potluck,
+    Host,
+    Address,
+    Attendees,
+    Adults,
+    Children,
+    DietaryNotes,
+}
+
+impl GroupColumn {
+    pub const ALL: [Self; 8] = [
+        Self::Round,
+        Self::Potluck,
+        Self::Host,
+        Self::Address,
+        Self::Attendees,
+        Self::Adults,
+        Self::Children,
+        Self::DietaryNotes,
+    ];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::Round => "round",
+            Self::Potluck => "potluck",
+            Self::Host => "host",
+            Self::Address => "address",
+            Self::Attendees => "attendees",
+            Self::Adults => "adults",
+            Self::Children => "children",
+            Self::DietaryNotes => "dietary_notes",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Round => "Round",
+            Self::Potluck => "Potluck",
+            Self::Host => "Host",
+            Self::Address => "Address",
+            Self::Attendees => "Attendees",
+            Self::Adults => "Adults",
+            Self::Children => "Children",
+            Self::DietaryNotes => "Dietary notes",
+        }
+    }
+}
+
+/// Picks columns by key, keeping the order given. Unknown keys are an error
+/// so a typo doesn't silently drop a column; no keys at all means every
+/// column.
+pub fn select_columns<T: Copy>(
+    all: &[T],
+    key: impl Fn(T) -> &'static str,
+    keys: &[String],
+) -> Result<Vec<T>, String> {
+    if keys.is_empty() {
+        return Ok(all.to_vec());
+    }
+    keys.iter()
+        .flat_map(|k| k.split(','))
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(|k| {
+            all.iter()
+                .copied()
+                .find(|&c| key(c) == k)
+                .ok_or_else(|| format!("Unknown column: {k}"))
+        })
+        .collect()
+}
+
+/// One line for a PCO address, e.g. `12 Main St, Springfield, IL 62701`.
+pub fn format_address(address: &Value) -> String {
+    let field = |name: &str| address[name].as_str().unwrap_or("").trim().to_string();
+    let region = [field("state"), field("zip")]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    [
+        field("street_line_1"),
+        field("street_line_2"),
+        field("city"),
+        region,
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// The first address on file among a participant's members.
+pub fn participant_address(participant: &Participant) -> String {
+    participant
+        .members
+        .iter()
+        .map(|member| format_address(&member.address))
+        .find(|address| !address.is_empty())
+        .unwrap_or_default()
+}
+
+/// Keeps spreadsheet apps from running a cell as a formula. Names and notes
+/// come from Planning Center or imported files, so anything starting like a
+/// formula is prefixed with `'`, which Excel and others treat as "text".
+fn cell(value: String) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{value}")
+    } else {
+        value
+    }
+}
+
+/// Everyone's dietary notes in a party, prefixed with their name.
+fn dietary_notes<'a>(members: impl IntoIterator<Item = &'a person::Model>) -> String {
+    members
+        .into_iter()
+        .filter_map(|member| {
+            let notes = member.dietary_notes.as_deref()?.trim();
+            (!notes.is_empty()).then(|| format!("{}: {notes}", member.name))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+pub fn write_roster<W: io::Write>(
+    out: W,
+    participants: &[Participant],
+    columns: &[RosterColumn],
+) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(columns.iter().map(|c| c.label()))?;
+
+    for participant in participants {
+        let household = match participant.id.household_id() {
+            Some(_) => participant.name.as_str(),
+            None => "",
+        };
+        for member in &participant.members {
+            writer.write_record(columns.iter().map(|column| {
+                cell(match column {
+                    RosterColumn::Name => member.name.clone(),
+                    RosterColumn::Household => household.to_string(),
+                    RosterColumn::Email => member.email.clone().unwrap_or_default(),
+                    RosterColumn::Phone => member.phone.clone().unwrap_or_default(),
+                    RosterColumn::Address => format_address(&member.address),
+                    RosterColumn::CanHost => {
+                        if participant.can_host { "yes" } else { "no" }.to_string()
+                    }
+                    RosterColumn::DietaryNotes => member.dietary_notes.clone().unwrap_or_default(),
+                })
+            }))?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes one row per potluck that is still on. Cancelled attendees are left
+/// out, and so is a host who has cancelled.
+pub fn write_groups<W: io::Write>(
+    out: W,
+    groups: &[Group],
+    columns: &[GroupColumn],
+) -> csv::Result<()> {
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(columns.iter().map(|c| c.label()))?;
+
+    for group in groups.iter().filter(|g| !g.is_cancelled()) {
+        let host = group
+            .host
+            .as_ref()
+            .filter(|_| group.potluck.host_cancelled_at.is_none());
+        let attendees: Vec<&Participant> =
+            group.active_attendees().map(|a| &a.participant).collect();
+        let headcount = group.headcount();
+
+        writer.write_record(columns.iter().map(|column| {
+            cell(match column {
+                GroupColumn::Round => group.potluck.round.to_string(),
+                GroupColumn::Potluck => group.potluck.id.to_string(),
+                GroupColumn::Host => host.map(|h| h.name.clone()).unwrap_or_default(),
+                GroupColumn::Address => host.map(participant_address).unwrap_or_default(),
+                GroupColumn::Attendees => attendees
+                    .iter()
+                    .map(|a| a.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; "),
+                GroupColumn::Adults => headcount.adults.to_string(),
+                GroupColumn::Children => headcount.children.to_string(),
+                GroupColumn::DietaryNotes => dietary_notes(
+                    host.into_iter()
+                        .chain(attendees.iter().copied())
+                        .flat_map(|p| &p.members),
+                ),
+            })
+        }))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{RosterColumn, cell, format_address, select_columns};
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[test]
+    fn no_keys_selects_every_column() {
+        let columns = select_columns(&RosterColumn::ALL, RosterColumn::key, &[]);
+        assert_eq!(columns, Ok(RosterColumn::ALL.to_vec()));
+    }
+
+    #[test]
+    fn keys_keep_their_order_and_may_be_comma_separated() {
+        let columns = select_columns(
+            &RosterColumn::ALL,
+            RosterColumn::key,
+            &keys(&["phone, name", "", "email"]),
+        );
+        assert_eq!(
+            columns,
+            Ok(vec![
+                RosterColumn::Phone,
+                RosterColumn::Name,
+                RosterColumn::Email
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_an_error() {
+        let columns = select_columns(&RosterColumn::ALL, RosterColumn::key, &keys(&["nmae"]));
+        assert_eq!(columns, Err("Unknown column: nmae".to_string()));
+    }
+
+    #[test]
+    fn addresses_skip_missing_parts() {
+        let full = json!({
+            "street_line_1": "12 Main St",
+            "street_line_2": "Apt 4",
+            "city": "Springfield",
+            "state": "IL",
+            "zip": "62701",
+        });
+        assert_eq!(
+            format_address(&full),
+            "12 Main St, Apt 4, Springfield, IL 62701"
+        );
+
+        let partial =
+            json!({ "street_line_1": " 12 Main St ", "street_line_2": "", "zip": "62701" });
+        assert_eq!(format_address(&partial), "12 Main St, 62701");
+        assert_eq!(format_address(&json!({})), "");
+    }
+
+    #[test]
+    fn formula_like_cells_are_quoted() {
+        for value in ["=1+1", "+1 555 0100", "-2", "@SUM(A1)", "\tx"] {
+            assert_eq!(cell(value.to_string()), format!("'{value}"));
+        }
+        for value in ["Ada Lovelace", "no nuts = fine", "", "3"] {
+            assert_eq!(cell(value.to_string()), value);
+        }
+    }
+}
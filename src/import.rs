@@ -0,0 +1,453 @@
+//! CSV import of people who aren't in Planning Center, such as neighbours or
+//! friends who come along to potlucks without being church members.
+//!
+//! Rows that share a `household` value become one household. Anyone whose
+//! email or phone already belongs to someone in the organization is matched
+//! to that record instead of being added a second time.
+
+use std::{collections::HashMap, io};
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Set,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    audit::{self, AuditEntity, Change},
+    entities::{household, person, sea_orm_active_enums::RecordSource},
+};
+
+/// Headers we understand, after lowercasing and turning spaces into
+/// underscores. Other columns are ignored so a spreadsheet can be uploaded
+/// as it is.
+pub const COLUMNS: [&str; 12] = [
+    "name",
+    "email",
+    "phone",
+    "household",
+    "street",
+    "street_2",
+    "city",
+    "state",
+    "zip",
+    "child",
+    "can_host",
+    "dietary_notes",
+];
+
+/// A person read from the file, before it is matched or saved.
+#[derive(Debug, Clone)]
+pub struct ImportRow {
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub household: Option<String>,
+    pub address: Value,
+    pub is_child: bool,
+    pub can_host: bool,
+    pub dietary_notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportMatch {
+    pub name: String,
+    pub existing: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub created: Vec<String>,
+    pub matched: Vec<ImportMatch>,
+    pub households: usize,
+}
+
+fn normalize_header(header: &str) -> String {
+    header.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+/// The trimmed value, or `None` for a blank field.
+pub fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "no" | "n" | "false" | "0" => Some(false),
+        "yes" | "y" | "true" | "1" | "x" => Some(true),
+        _ => None,
+    }
+}
+
+/// Reads every row, or every problem found. Nothing is imported from a file
+/// with errors, so fixing it and uploading again doesn't create duplicates.
+pub fn parse_rows<R: io::Read>(input: R) -> Result<Vec<ImportRow>, Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+    let headers: Vec<String> = match reader.headers() {
+        Ok(headers) => headers.iter().map(normalize_header).collect(),
+        Err(e) => return Err(vec![format!("Could not read the header row: {e}")]),
+    };
+    if !headers.iter().any(|h| h == "name") {
+        return Err(vec!["The file needs a \"name\" column".to_string()]);
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(e.to_string());
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line() as usize);
+        let field = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h == name)
+                .and_then(|i| record.get(i))
+                .unwrap_or("")
+        };
+        if record.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        let Some(name) = non_empty(field("name")) else {
+            errors.push(format!("Line {line}: name is required"));
+            continue;
+        };
+        let mut flag = |column: &str| {
+            parse_flag(field(column)).unwrap_or_else(|| {
+                errors.push(format!("Line {line}: {column} must be yes or no"));
+                false
+            })
+        };
+        let is_child = flag("child");
+        let can_host = flag("can_host");
+
+        let address: Map<String, Value> = [
+            ("street_line_1", "street"),
+            ("street_line_2", "street_2"),
+            ("city", "city"),
+            ("state", "state"),
+            ("zip", "zip"),
+        ]
+        .into_iter()
+        .filter_map(|(key, column)| {
+            Some((key.to_string(), Value::String(non_empty(field(column))?)))
+        })
+        .collect();
+
+        rows.push(ImportRow {
+            name,
+            email: non_empty(field("email")),
+            phone: non_empty(field("phone")),
+            household: non_empty(field("household")),
+            address: if address.is_empty() {
+                Value::Null
+            } else {
+                Value::Object(address)
+            },
+            is_child,
+            can_host,
+            dietary_notes: non_empty(field("dietary_notes")),
+        });
+    }
+
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(errors)
+    }
+}
+
+fn email_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Digits only, and only the last ten so a leading country code doesn't
+/// hide a match.
+fn phone_key(phone: &str) -> Option<String> {
+    let digits: String = phone.chars().filter(char::is_ascii_digit).collect();
+    let start = digits.len().saturating_sub(10);
+    (digits.len() >= 7).then(|| digits[start..].to_string())
+}
+
+/// Finds people in the organization by email or phone.
+#[derive(Default)]
+struct Directory {
+    by_email: HashMap<String, person::Model>,
+    by_phone: HashMap<String, person::Model>,
+}
+
+impl Directory {
+    fn add(&mut self, person: &person::Model) {
+        if let Some(email) = &person.email {
+            self.by_email.insert(email_key(email), person.clone());
+        }
+        if let Some(phone) = person.phone.as_deref().and_then(phone_key) {
+            self.by_phone.insert(phone, person.clone());
+        }
+    }
+
+    fn find(&self, row: &ImportRow) -> Option<&person::Model> {
+        row.email
+            .as_deref()
+            .and_then(|email| self.by_email.get(&email_key(email)))
+            .or_else(|| {
+                row.phone
+                    .as_deref()
+                    .and_then(phone_key)
+                    .and_then(|phone| self.by_phone.get(&phone))
+            })
+    }
+}
+
+/// Saves imported rows as local people and households. With `sign_up` the
+/// new records are signed up for potlucks straight away, the same way a
+/// Planning Center sign-up would. Run it in a transaction so a failure part
+/// way through leaves nothing behind. `imported_by` is recorded in the audit
+/// log against each new record.
+pub async fn import_people<C>(
+    db: &C,
+    organization_id: i32,
+    rows: Vec<ImportRow>,
+    sign_up: bool,
+    imported_by: Option<i32>,
+) -> Result<ImportReport, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut directory = Directory::default();
+    for existing in person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(organization_id))
+        .all(db)
+        .await?
+    {
+        directory.add(&existing);
+    }
+
+    // Keep households in the order they first appear in the file.
+    let mut groups: Vec<(Option<String>, Vec<ImportRow>)> = Vec::new();
+    for row in rows {
+        let key = row.household.as_deref().map(str::to_lowercase);
+        match groups.iter_mut().find(|(k, _)| k.is_some() && *k == key) {
+            Some((_, members)) => members.push(row),
+            None => groups.push((key, vec![row])),
+        }
+    }
+
+    let now = Utc::now().naive_utc();
+    let mut report = ImportReport::default();
+    let mut changes = Vec::new();
+    for (key, members) in groups {
+        let mut new_members = Vec::new();
+        let mut household_id = None;
+        for row in members {
+            match directory.find(&row) {
+                Some(existing) => {
+                    household_id = household_id.or(existing.household_id);
+                    report.matched.push(ImportMatch {
+                        name: row.name,
+                        existing: existing.name.clone(),
+                    });
+                }
+                None => new_members.push(row),
+            }
+        }
+        if new_members.is_empty() {
+            continue;
+        }
+
+        // Whether the new members' household brings them along, so they
+        // needn't be signed up themselves.
+        let mut household_signed_up = false;
+        if key.is_some() && household_id.is_none() {
+            let name = new_members
+                .iter()
+                .find_map(|row| row.household.clone())
+                .unwrap_or_default();
+            let created = household::ActiveModel {
+                organization_id: Set(organization_id),
+                name: Set(name),
+                is_signed_up: Set(sign_up),
+                can_host: Set(new_members.iter().any(|row| row.can_host)),
+                signed_up_at: Set(sign_up.then_some(now)),
+                source: Set(RecordSource::Import),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+            household_id = Some(created.id);
+            household_signed_up = sign_up;
+            report.households += 1;
+            changes.push(Change::created(
+                AuditEntity::Household,
+                created.id,
+                &created.name,
+            ));
+        } else if sign_up && let Some(id) = household_id {
+            // Joining a household already on file signs up only the new
+            // members, unless the household is signed up already.
+            household_signed_up = household::Entity::find_by_id(id)
+                .one(db)
+                .await?
+                .is_some_and(|existing| existing.is_signed_up);
+        }
+
+        for row in new_members {
+            let signed_up = sign_up && !household_signed_up;
+            let created = person::ActiveModel {
+                organization_id: Set(organization_id),
+                pco_id: Set(None),
+                name: Set(row.name),
+                email: Set(row.email),
+                phone: Set(row.phone),
+                address: Set(row.address),
+                avatar_url: Set(None),
+                is_signed_up: Set(signed_up),
+                can_host: Set(row.can_host),
+                is_child: Set(row.is_child),
+                household_id: Set(household_id),
+                signed_up_at: Set(signed_up.then_some(now)),
+                dietary_notes: Set(row.dietary_notes),
+                source: Set(RecordSource::Import),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(db)
+            .await?;
+            directory.add(&created);
+            changes.push(Change::created(
+                AuditEntity::Person,
+                created.id,
+                &created.name,
+            ));
+            report.created.push(created.name);
+        }
+    }
+
+    audit::record(db, organization_id, imported_by, changes).await?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Directory, ImportRow, parse_rows, phone_key};
+    use crate::entities::{person, sea_orm_active_enums::RecordSource};
+
+    fn person(id: i32, email: Option<&str>, phone: Option<&str>) -> person::Model {
+        person::Model {
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            id,
+            pco_id: None,
+            organization_id: 1,
+            name: format!("Person {id}"),
+            email: email.map(str::to_string),
+            phone: phone.map(str::to_string),
+            address: serde_json::json!({}),
+            avatar_url: None,
+            is_signed_up: false,
+            can_host: false,
+            is_child: false,
+            household_id: None,
+            signed_up_at: None,
+            dietary_notes: None,
+            source: RecordSource::Manual,
+        }
+    }
+
+    fn row(email: Option<&str>, phone: Option<&str>) -> ImportRow {
+        ImportRow {
+            name: "Someone".to_string(),
+            email: email.map(str::to_string),
+            phone: phone.map(str::to_string),
+            household: None,
+            address: serde_json::Value::Null,
+            is_child: false,
+            can_host: false,
+            dietary_notes: None,
+        }
+    }
+
+    #[test]
+    fn rows_are_read_with_loose_headers() {
+        let csv = "Name,Email,Phone,Household,Street,Zip,Child,Can-Host,Dietary Notes,Shoe size\n\
+                   Ada Lovelace, ada@example.com ,555-0100,Lovelace,12 Main St,62701,no,yes,Vegetarian,9\n\
+                   ,,,,,,,,,\n\
+                   Byron Lovelace,,,Lovelace,,,y,,,\n";
+        let rows = parse_rows(csv.as_bytes()).unwrap();
+        assert_eq!(rows.len(), 2);
+
+        let ada = &rows[0];
+        assert_eq!(ada.name, "Ada Lovelace");
+        assert_eq!(ada.email.as_deref(), Some("ada@example.com"));
+        assert_eq!(ada.household.as_deref(), Some("Lovelace"));
+        assert_eq!(
+            ada.address,
+            serde_json::json!({ "street_line_1": "12 Main St", "zip": "62701" })
+        );
+        assert!(ada.can_host && !ada.is_child);
+        assert_eq!(ada.dietary_notes.as_deref(), Some("Vegetarian"));
+
+        let byron = &rows[1];
+        assert!(byron.is_child && !byron.can_host);
+        assert_eq!(byron.email, None);
+        assert_eq!(byron.address, serde_json::Value::Null);
+    }
+
+    #[test]
+    fn a_file_without_names_is_refused() {
+        let errors = parse_rows("email\nada@example.com\n".as_bytes()).unwrap_err();
+        assert_eq!(errors, ["The file needs a \"name\" column"]);
+    }
+
+    #[test]
+    fn every_bad_row_is_reported() {
+        let csv = "name,child,can_host\n,yes,no\nAda,maybe,no\nByron,no,sometimes\n";
+        let errors = parse_rows(csv.as_bytes()).unwrap_err();
+        assert_eq!(
+            errors,
+            [
+                "Line 2: name is required",
+                "Line 3: child must be yes or no",
+                "Line 4: can_host must be yes or no",
+            ]
+        );
+    }
+
+    #[test]
+    fn phones_compare_by_their_last_ten_digits() {
+        assert_eq!(phone_key("(555) 010-0123").as_deref(), Some("5550100123"));
+        assert_eq!(phone_key("+1 555 010 0123").as_deref(), Some("5550100123"));
+        assert_eq!(phone_key("010-0123").as_deref(), Some("0100123"));
+        assert_eq!(phone_key("ext 12"), None);
+    }
+
+    #[test]
+    fn rows_match_existing_people_by_email_then_phone() {
+        let mut directory = Directory::default();
+        directory.add(&person(1, Some("Ada@Example.com"), None));
+        directory.add(&person(2, None, Some("+1 (555) 010-0123")));
+
+        let by_email = directory.find(&row(Some(" ada@example.com"), None));
+        assert_eq!(by_email.map(|p| p.id), Some(1));
+
+        let by_phone = directory.find(&row(Some("other@example.com"), Some("555.010.0123")));
+        assert_eq!(by_phone.map(|p| p.id), Some(2));
+
+        assert!(
+            directory
+                .find(&row(Some("new@example.com"), Some("555 010 9999")))
+                .is_none()
+        );
+        assert!(directory.find(&row(None, None)).is_none());
+    }
+}
@@ -0,0 +1,15 @@
+pub mod audit;
+pub mod auth;
+pub mod config;
+pub mod database;
+pub mod enroll;
+pub mod entities;
+pub mod error;
+pub mod export;
+pub mod import;
+pub mod packet;
+pub mod pco;
+pub mod planning;
+pub mod router;
+pub mod routes;
+pub mod util;
@@ -1,19 +1,11 @@
-mod auth;
-mod config;
-mod database;
-mod entities;
-mod pco;
-mod router;
-mod routes;
-mod util;
-
-use crate::{
+use axum_login::tower_sessions::ExpiredDeletion;
+use potluck_rs::{
+    auth::user::encrypt_plaintext_tokens,
     config::Config,
     database::setup_database,
-    router::{create_router, shutdown_signal, OauthClient},
+    router::{create_router, setup_oauth_client, shutdown_signal},
 };
-use axum_login::tower_sessions::ExpiredDeletion;
-use oauth2::{AuthUrl, TokenUrl, basic::BasicClient};
+use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower_sessions_sqlx_store::PostgresStore;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -27,6 +19,10 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let (db, pool) = setup_database(&config.database_url).await?;
+    let encrypted = encrypt_plaintext_tokens(&db, &config.token_keys).await?;
+    if encrypted > 0 {
+        tracing::info!("Encrypted stored tokens for {encrypted} users");
+    }
 
     let client = setup_oauth_client(&config)?;
 
@@ -39,26 +35,18 @@ async fn main() -> anyhow::Result<()> {
             .continuously_delete_expired(tokio::time::Duration::from_secs(60)),
     );
 
-    let app = create_router(db, client, session_store).await?;
+    let app = create_router(&config, db, client, session_store).await?;
 
-    let listener = TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(deletion_task.abort_handle()))
-        .await?;
+    let listener = TcpListener::bind(config.bind_address).await?;
+    tracing::info!("Listening on {}", config.bind_address);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal(deletion_task.abort_handle()))
+    .await?;
 
     deletion_task.await??;
 
     Ok(())
 }
-
-fn setup_oauth_client(config: &Config) -> anyhow::Result<OauthClient> {
-    let auth_url =
-        AuthUrl::new("https://api.planningcenteronline.com/oauth/authorize".to_string())?;
-    let token_url = TokenUrl::new("https://api.planningcenteronline.com/oauth/token".to_string())?;
-    let client = BasicClient::new(config.client_id.clone())
-        .set_client_secret(config.client_secret.clone())
-        .set_auth_uri(auth_url)
-        .set_token_uri(token_url)
-        .set_redirect_uri(config.redirect_url.clone());
-    Ok(client)
-}
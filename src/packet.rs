@@ -0,0 +1,344 @@
+//! Host packets: a one-page sheet per potluck telling the host when it is,
+//! who is coming, how many to cook for, what to watch out for and who is
+//! bringing what.
+//!
+//! Packets are plain data so the same sheet can be rendered as print-ready
+//! HTML from the templates or as a PDF built here.
+
+use printpdf::{
+    BuiltinFont, IndirectFontRef, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference,
+};
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+use serde::Serialize;
+
+use crate::{
+    entities::{potluck, potluck_round, potluck_series},
+    export::participant_address,
+    planning::{
+        groups::{Group, load_group, load_round},
+        participant::{Headcount, Participant},
+    },
+};
+
+/// A household or person at the table.
+#[derive(Debug, Clone, Serialize)]
+pub struct PacketParty {
+    pub name: String,
+    pub headcount: Headcount,
+    pub children: Vec<String>,
+    pub dish: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DietaryNote {
+    pub name: String,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostPacket {
+    pub potluck_id: i32,
+    pub series: String,
+    pub round: i32,
+    /// The round's date, e.g. `Sunday, October 18, 2026`.
+    pub date: Option<String>,
+    pub cancelled: bool,
+    /// Left out once the host has cancelled.
+    pub host: Option<PacketParty>,
+    pub address: String,
+    pub guests: Vec<PacketParty>,
+    pub headcount: Headcount,
+    pub dietary_notes: Vec<DietaryNote>,
+}
+
+impl PacketParty {
+    fn new(participant: &Participant, dish: Option<&str>) -> Self {
+        Self {
+            name: participant.name.clone(),
+            headcount: participant.headcount(),
+            children: participant
+                .members
+                .iter()
+                .filter(|p| p.is_child)
+                .map(|p| p.name.clone())
+                .collect(),
+            dish: dish.map(str::to_string),
+        }
+    }
+}
+
+/// Builds the packet for one potluck. Cancelled attendees are left out.
+pub fn host_packet(
+    series: &potluck_series::Model,
+    round: Option<&potluck_round::Model>,
+    group: &Group,
+) -> HostPacket {
+    let host = group
+        .host
+        .as_ref()
+        .filter(|_| group.potluck.host_cancelled_at.is_none());
+    let attendees: Vec<_> = group.active_attendees().collect();
+
+    let dietary_notes = host
+        .into_iter()
+        .chain(attendees.iter().map(|a| &a.participant))
+        .flat_map(|p| &p.members)
+        .filter_map(|member| {
+            let notes = member.dietary_notes.as_deref()?.trim();
+            (!notes.is_empty()).then(|| DietaryNote {
+                name: member.name.clone(),
+                notes: notes.to_string(),
+            })
+        })
+        .collect();
+
+    HostPacket {
+        potluck_id: group.potluck.id,
+        series: series.name.clone(),
+        round: group.potluck.round,
+        date: round.map(|r| r.scheduled_on.format("%A, %B %-d, %Y").to_string()),
+        cancelled: group.is_cancelled(),
+        host: host.map(|h| PacketParty::new(h, None)),
+        address: host.map(participant_address).unwrap_or_default(),
+        guests: attendees
+            .iter()
+            .map(|a| PacketParty::new(&a.participant, a.attendance.dish.as_deref()))
+            .collect(),
+        headcount: group.headcount(),
+        dietary_notes,
+    }
+}
+
+async fn find_round<C>(
+    db: &C,
+    series_id: i32,
+    round: i32,
+) -> Result<Option<potluck_round::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series_id))
+        .filter(potluck_round::Column::Round.eq(round))
+        .one(db)
+        .await
+}
+
+pub async fn load_packet<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    potluck: potluck::Model,
+) -> Result<HostPacket, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let round = find_round(db, series.id, potluck.round).await?;
+    let group = load_group(db, potluck).await?;
+    Ok(host_packet(series, round.as_ref(), &group))
+}
+
+/// Packets for every potluck in a round that is still on.
+pub async fn load_round_packets<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    round: i32,
+) -> Result<Vec<HostPacket>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let scheduled = find_round(db, series.id, round).await?;
+    let groups = load_round(db, series.id, round).await?;
+    Ok(groups
+        .iter()
+        .filter(|g| !g.is_cancelled())
+        .map(|g| host_packet(series, scheduled.as_ref(), g))
+        .collect())
+}
+
+// US Letter, which is what most of our hosts have in their printers.
+const PAGE_WIDTH: f32 = 215.9;
+const PAGE_HEIGHT: f32 = 279.4;
+const MARGIN: f32 = 18.0;
+const BODY_SIZE: f32 = 11.0;
+/// Rough characters per line at body size; Helvetica averages about half an
+/// em per character.
+const WRAP_AT: usize = 92;
+
+/// Writes lines top to bottom, starting a new page when one fills up.
+struct Sheet<'a> {
+    doc: &'a PdfDocumentReference,
+    layer: PdfLayerReference,
+    y: f32,
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+}
+
+impl Sheet<'_> {
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Packet");
+        self.layer = self.doc.get_page(page).get_layer(layer);
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn write(&mut self, text: &str, size: f32, bold: bool, indent: f32) {
+        // One point is 0.3528mm; leave a little leading between lines.
+        let height = size * 0.3528 * 1.4;
+        let wrap_at = (WRAP_AT as f32 * BODY_SIZE / size) as usize;
+        for line in wrap(text, wrap_at) {
+            if self.y - height < MARGIN {
+                self.new_page();
+            }
+            self.y -= height;
+            let font = if bold { &self.bold } else { &self.regular };
+            self.layer
+                .use_text(line, size, Mm(MARGIN + indent), Mm(self.y), font);
+        }
+    }
+
+    fn gap(&mut self) {
+        self.y -= 4.0;
+    }
+}
+
+/// Breaks text into lines of at most `width` characters at spaces.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn party_size(headcount: Headcount) -> String {
+    let adults = match headcount.adults {
+        1 => "1 adult".to_string(),
+        n => format!("{n} adults"),
+    };
+    match headcount.children {
+        0 => adults,
+        1 => format!("{adults}, 1 child"),
+        n => format!("{adults}, {n} children"),
+    }
+}
+
+fn write_packet(sheet: &mut Sheet, packet: &HostPacket) {
+    sheet.write(
+        &format!("{} - Round {}", packet.series, packet.round),
+        18.0,
+        true,
+        0.0,
+    );
+    if let Some(date) = &packet.date {
+        sheet.write(date, 13.0, false, 0.0);
+    }
+    if packet.cancelled {
+        sheet.write("This potluck has been cancelled.", 13.0, true, 0.0);
+    }
+    sheet.gap();
+
+    match &packet.host {
+        Some(host) => sheet.write(&format!("Hosted by {}", host.name), BODY_SIZE, true, 0.0),
+        None => sheet.write("No host yet", BODY_SIZE, true, 0.0),
+    }
+    if !packet.address.is_empty() {
+        sheet.write(&packet.address, BODY_SIZE, false, 0.0);
+    }
+    sheet.write(
+        &format!(
+            "Cooking for {}: {}",
+            packet.headcount.total(),
+            party_size(packet.headcount)
+        ),
+        BODY_SIZE,
+        false,
+        0.0,
+    );
+    sheet.gap();
+
+    sheet.write("Guests", 13.0, true, 0.0);
+    if packet.guests.is_empty() {
+        sheet.write(
+            "Nobody is assigned to this potluck yet.",
+            BODY_SIZE,
+            false,
+            0.0,
+        );
+    }
+    for guest in &packet.guests {
+        sheet.write(
+            &format!("{} ({})", guest.name, party_size(guest.headcount)),
+            BODY_SIZE,
+            true,
+            0.0,
+        );
+        if !guest.children.is_empty() {
+            sheet.write(
+                &format!("Children: {}", guest.children.join(", ")),
+                BODY_SIZE,
+                false,
+                5.0,
+            );
+        }
+        let dish = guest.dish.as_deref().unwrap_or("Not assigned");
+        sheet.write(&format!("Bringing: {dish}"), BODY_SIZE, false, 5.0);
+    }
+    if let Some(host) = &packet.host
+        && !host.children.is_empty()
+    {
+        sheet.write(
+            &format!("Host's children: {}", host.children.join(", ")),
+            BODY_SIZE,
+            false,
+            0.0,
+        );
+    }
+    sheet.gap();
+
+    sheet.write("Allergies and dietary notes", 13.0, true, 0.0);
+    if packet.dietary_notes.is_empty() {
+        sheet.write("None noted.", BODY_SIZE, false, 0.0);
+    }
+    for note in &packet.dietary_notes {
+        sheet.write(
+            &format!("{}: {}", note.name, note.notes),
+            BODY_SIZE,
+            false,
+            0.0,
+        );
+    }
+}
+
+/// Renders packets as a PDF, each starting on a new page. The built-in PDF
+/// fonts only cover Latin characters, so anything else is left out.
+pub fn render_pdf(title: &str, packets: &[HostPacket]) -> Result<Vec<u8>, printpdf::Error> {
+    let (doc, page, layer) = PdfDocument::new(title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Packet");
+    let mut sheet = Sheet {
+        layer: doc.get_page(page).get_layer(layer),
+        y: PAGE_HEIGHT - MARGIN,
+        regular: doc.add_builtin_font(BuiltinFont::Helvetica)?,
+        bold: doc.add_builtin_font(BuiltinFont::HelveticaBold)?,
+        doc: &doc,
+    };
+
+    if packets.is_empty() {
+        sheet.write("There are no potlucks to print.", BODY_SIZE, false, 0.0);
+    }
+    for (i, packet) in packets.iter().enumerate() {
+        if i > 0 {
+            sheet.new_page();
+        }
+        write_packet(&mut sheet, packet);
+    }
+
+    doc.save_to_bytes()
+}
@@ -0,0 +1,212 @@
+//! Narrows people searches by the ways Planning Center groups people:
+//! campus, membership, age, Lists and List categories.
+//!
+//! Planning Center has no tags on people themselves. Lists are filed under
+//! categories instead, so a category serves as a tag: filtering by one finds
+//! everyone on any List filed under it.
+
+use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{BASE_URL, PCOResource};
+
+/// A people search. Fields left empty don't filter.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeopleFilter {
+    /// Part of the person's name.
+    pub name: Option<String>,
+    /// Planning Center id of their primary campus.
+    pub campus: Option<String>,
+    /// Membership type, exactly as Planning Center spells it.
+    pub membership: Option<String>,
+    /// `child` or `adult`.
+    pub age: Option<String>,
+    /// Planning Center id of a List they are on.
+    pub list: Option<String>,
+    /// Planning Center id of a List category. Matches people on any List
+    /// filed under it; ignored once a List is chosen.
+    pub category: Option<String>,
+}
+
+fn value(field: &Option<String>) -> Option<&str> {
+    field.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+impl PeopleFilter {
+    pub fn by_name(name: Option<String>) -> Self {
+        PeopleFilter {
+            name,
+            ..Default::default()
+        }
+    }
+
+    pub fn list(&self) -> Option<&str> {
+        value(&self.list)
+    }
+
+    pub fn category(&self) -> Option<&str> {
+        match self.list() {
+            Some(_) => None,
+            None => value(&self.category),
+        }
+    }
+
+    /// The collection to search: the chosen List's people, or everyone.
+    pub(super) fn collection(&self) -> String {
+        match self.list() {
+            Some(list) => format!(
+                "{BASE_URL}lists/{}/people",
+                utf8_percent_encode(list, NON_ALPHANUMERIC)
+            ),
+            None => format!("{BASE_URL}people"),
+        }
+    }
+
+    /// The `where[...]` parameters for everything but the List.
+    pub(super) fn conditions(&self) -> String {
+        let mut conditions = vec![("status", "active")];
+        if let Some(name) = value(&self.name) {
+            conditions.push(("search_name", name));
+        }
+        if let Some(campus) = value(&self.campus) {
+            conditions.push(("primary_campus_id", campus));
+        }
+        if let Some(membership) = value(&self.membership) {
+            conditions.push(("membership", membership));
+        }
+        match value(&self.age) {
+            Some("child") => conditions.push(("child", "true")),
+            Some("adult") => conditions.push(("child", "false")),
+            _ => {}
+        }
+        conditions
+            .into_iter()
+            .map(|(key, value)| {
+                format!(
+                    "&where[{key}]={}",
+                    utf8_percent_encode(value, NON_ALPHANUMERIC)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Something a search can be narrowed to, for the filter menus.
+#[derive(Debug, Clone, Serialize)]
+pub struct FilterOption {
+    pub id: String,
+    pub name: String,
+    /// For Lists, the category they are filed under.
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Collection {
+    data: Vec<PCOResource>,
+    #[serde(default)]
+    included: Vec<PCOResource>,
+}
+
+async fn fetch(access_token: &str, url: String) -> Result<Collection, reqwest::Error> {
+    reqwest::Client::new()
+        .get(url)
+        .bearer_auth(access_token)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<Collection>()
+        .await
+}
+
+fn name(resource: &PCOResource) -> String {
+    resource.attributes["name"]
+        .as_str()
+        .unwrap_or("")
+        .to_string()
+}
+
+pub async fn get_campuses(access_token: &str) -> Result<Vec<FilterOption>, reqwest::Error> {
+    let campuses = fetch(access_token, format!("{BASE_URL}campuses?per_page=100")).await?;
+    Ok(campuses
+        .data
+        .iter()
+        .map(|campus| FilterOption {
+            id: campus.id.clone(),
+            name: name(campus),
+            category: None,
+        })
+        .collect())
+}
+
+/// The categories Lists are filed under, sorted by name.
+pub async fn get_list_categories(access_token: &str) -> Result<Vec<FilterOption>, reqwest::Error> {
+    let categories = fetch(
+        access_token,
+        format!("{BASE_URL}list_categories?per_page=100&order=name"),
+    )
+    .await?;
+    Ok(categories
+        .data
+        .iter()
+        .map(|category| FilterOption {
+            id: category.id.clone(),
+            name: name(category),
+            category: None,
+        })
+        .collect())
+}
+
+/// Ids of the Lists filed under a category.
+pub(super) async fn lists_in_category(
+    access_token: &str,
+    category_id: &str,
+) -> Result<Vec<String>, reqwest::Error> {
+    let lists = fetch(access_token, format!("{BASE_URL}lists?per_page=100")).await?;
+    Ok(lists
+        .data
+        .into_iter()
+        .filter(|list| {
+            list.relationships
+                .as_ref()
+                .and_then(|r| r["category"]["data"]["id"].as_str())
+                == Some(category_id)
+        })
+        .map(|list| list.id)
+        .collect())
+}
+
+/// The organization's Lists, sorted by category and then name.
+pub async fn get_lists(access_token: &str) -> Result<Vec<FilterOption>, reqwest::Error> {
+    let lists = fetch(
+        access_token,
+        format!("{BASE_URL}lists?per_page=100&order=name&include=category"),
+    )
+    .await?;
+    let categories: HashMap<&str, String> = lists
+        .included
+        .iter()
+        .filter(|item| item.resource_type == "ListCategory")
+        .map(|category| (category.id.as_str(), name(category)))
+        .collect();
+
+    let mut options: Vec<FilterOption> = lists
+        .data
+        .iter()
+        .map(|list| FilterOption {
+            id: list.id.clone(),
+            name: name(list),
+            category: list
+                .relationships
+                .as_ref()
+                .and_then(|r| r["category"]["data"]["id"].as_str())
+                .and_then(|id| categories.get(id).cloned()),
+        })
+        .collect();
+    options.sort_by(|a, b| {
+        a.category
+            .cmp(&b.category)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(options)
+}
@@ -0,0 +1,94 @@
+//! Attaches people and households that were added locally to their Planning
+//! Center records once those exist.
+//!
+//! If the Planning Center record has already been synced as a separate row,
+//! the local one is merged into it so nothing it took part in is lost.
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, Set,
+};
+
+use crate::{
+    entities::{household, person, sea_orm_active_enums::RecordSource},
+    pco::person::{HouseholdInfo, PersonData},
+    planning::merge::{merge_households, merge_people},
+};
+
+fn other_organization() -> DbErr {
+    DbErr::Custom("That Planning Center record belongs to another organization".to_string())
+}
+
+/// Links a local person to `remote` and refreshes their details from it.
+/// `linked_by` is recorded against any merge this causes.
+pub async fn link_person<C>(
+    db: &C,
+    local: person::Model,
+    remote: PersonData,
+    linked_by: Option<i32>,
+) -> Result<person::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let existing = person::Entity::find()
+        .filter(person::Column::PcoId.eq(&remote.id))
+        .one(db)
+        .await?;
+    let local = match existing {
+        Some(existing) if existing.organization_id != local.organization_id => {
+            return Err(other_organization());
+        }
+        Some(existing) if existing.id != local.id => {
+            merge_people(db, existing, local, linked_by).await?.0
+        }
+        _ => local,
+    };
+
+    let mut model = local.clone().into_active_model();
+    model.pco_id = Set(Some(remote.id));
+    model.source = Set(RecordSource::Pco);
+    model.name = Set(remote.name);
+    model.email = Set(remote.email.or(local.email));
+    model.phone = Set(remote.phone.or(local.phone));
+    if let Some(address) = remote.address {
+        model.address = Set(address);
+    }
+    model.avatar_url = Set(remote.avatar.or(local.avatar_url));
+    model.is_child = Set(remote.is_child);
+    model.updated_at = Set(Utc::now().naive_utc());
+    model.update(db).await
+}
+
+/// Links a local household to `remote` and takes its name and avatar.
+pub async fn link_household<C>(
+    db: &C,
+    local: household::Model,
+    remote: HouseholdInfo,
+    linked_by: Option<i32>,
+) -> Result<household::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let existing = household::Entity::find()
+        .filter(household::Column::PcoId.eq(&remote.id))
+        .one(db)
+        .await?;
+    let local = match existing {
+        Some(existing) if existing.organization_id != local.organization_id => {
+            return Err(other_organization());
+        }
+        Some(existing) if existing.id != local.id => {
+            merge_households(db, existing, local, linked_by).await?.0
+        }
+        _ => local,
+    };
+
+    let mut model = local.clone().into_active_model();
+    model.pco_id = Set(Some(remote.id));
+    model.source = Set(RecordSource::Pco);
+    model.name = Set(remote.name);
+    model.avatar_url = Set(remote.avatar.or(local.avatar_url));
+    model.updated_at = Set(Utc::now().naive_utc());
+    model.update(db).await
+}
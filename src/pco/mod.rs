@@ -4,7 +4,10 @@ use serde_json::Value;
 use crate::pco::person::{HouseholdInfo, OrganizationInfo};
 
 pub mod person;
+pub mod filter;
 pub mod household;
+pub mod link;
+pub mod sync;
 
 const BASE_URL: &str = "https://api.planningcenteronline.com/people/v2/";
 const INCLUDED: &str = "include=addresses,emails,households,organization,phone_numbers";
@@ -1,17 +1,22 @@
-use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use reqwest;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use utoipa::ToSchema;
 
-use super::{process_included, PCOPersonResponse, PCOResource, INCLUDED, BASE_URL};
+use super::{
+    BASE_URL, INCLUDED, PCOPersonResponse, PCOResource,
+    filter::{PeopleFilter, lists_in_category},
+    process_included,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PersonData {
     pub id: String,
     pub name: String,
     pub avatar: Option<String>,
     pub email: Option<String>,
+    #[schema(value_type = Option<Object>)]
     pub address: Option<Value>, // Store full address JSON
     pub phone: Option<String>,
     pub is_child: bool,
@@ -20,18 +25,19 @@ pub struct PersonData {
     pub is_signed_up: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct HouseholdInfo {
     pub id: String,
     pub name: String,
     pub avatar: Option<String>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(no_recursion)]
     pub people: Option<Vec<PersonData>>,
     pub is_signed_up: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct OrganizationInfo {
     pub id: String,
     pub name: String,
@@ -45,7 +51,7 @@ pub struct PCOMeResponse {
     pub included: Vec<PCOResource>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, ToSchema)]
 pub struct PeoplePage {
     pub people: Vec<PersonData>,
     pub total_count: usize,
@@ -81,7 +87,8 @@ pub async fn get_person(
 }
 
 fn parse_complete_response(response: PCOMeResponse) -> Option<PersonData> {
-    let (addresses, emails, phones, organizations, households) = process_included(response.included);
+    let (addresses, emails, phones, organizations, households) =
+        process_included(response.included);
 
     parse_person_resource(
         response.data,
@@ -120,37 +127,33 @@ pub fn parse_person_resource(
 
     // Process address relationships
     if let Some(relationships) = relationships {
-        if let Some(address_data) = relationships["addresses"]["data"].as_array() {
-            if !address_data.is_empty() {
-                if let Some(addr_id) = address_data[0]["id"].as_str() {
-                    person_address = addresses.get(addr_id).cloned();
-                }
-            }
+        if let Some(address_data) = relationships["addresses"]["data"].as_array()
+            && !address_data.is_empty()
+            && let Some(addr_id) = address_data[0]["id"].as_str()
+        {
+            person_address = addresses.get(addr_id).cloned();
         }
-        if let Some(address_data) = relationships["addresses"]["data"].as_array() {
-            if !address_data.is_empty() {
-                if let Some(addr_id) = address_data[0]["id"].as_str() {
-                    person_address = addresses.get(addr_id).cloned();
-                }
-            }
+        if let Some(address_data) = relationships["addresses"]["data"].as_array()
+            && !address_data.is_empty()
+            && let Some(addr_id) = address_data[0]["id"].as_str()
+        {
+            person_address = addresses.get(addr_id).cloned();
         }
 
         // Process email relationships
-        if let Some(email_data) = relationships["emails"]["data"].as_array() {
-            if !email_data.is_empty() {
-                if let Some(email_id) = email_data[0]["id"].as_str() {
-                    person_email = emails.get(email_id).cloned();
-                }
-            }
+        if let Some(email_data) = relationships["emails"]["data"].as_array()
+            && !email_data.is_empty()
+            && let Some(email_id) = email_data[0]["id"].as_str()
+        {
+            person_email = emails.get(email_id).cloned();
         }
 
         // Process phone relationships
-        if let Some(phone_data) = relationships["phone_numbers"]["data"].as_array() {
-            if !phone_data.is_empty() {
-                if let Some(phone_id) = phone_data[0]["id"].as_str() {
-                    person_phone = phones.get(phone_id).cloned();
-                }
-            }
+        if let Some(phone_data) = relationships["phone_numbers"]["data"].as_array()
+            && !phone_data.is_empty()
+            && let Some(phone_id) = phone_data[0]["id"].as_str()
+        {
+            person_phone = phones.get(phone_id).cloned();
         }
 
         // Process organization relationship
@@ -159,14 +162,13 @@ pub fn parse_person_resource(
         }
 
         // Process household relationships
-        if let Some(household_data) = relationships["households"]["data"].as_array() {
-            if !household_data.is_empty() {
-                if let Some(household_id) = household_data[0]["id"].as_str() {
-                    person_household = households.get(household_id).cloned();
-                    if let Some(h) = &mut person_household {
-                        h.is_signed_up = None;
-                    }
-                }
+        if let Some(household_data) = relationships["households"]["data"].as_array()
+            && !household_data.is_empty()
+            && let Some(household_id) = household_data[0]["id"].as_str()
+        {
+            person_household = households.get(household_id).cloned();
+            if let Some(h) = &mut person_household {
+                h.is_signed_up = None;
             }
         }
     }
@@ -190,19 +192,81 @@ pub async fn get_people(
     access_token: &str,
     page: usize,
     per_page: usize,
-    name: Option<String>,
+    filter: &PeopleFilter,
+) -> Result<PeoplePage, reqwest::Error> {
+    let Some(category) = filter.category() else {
+        return fetch_page(access_token, page, per_page, filter).await;
+    };
+    let people = get_category_people(access_token, filter, category).await?;
+    let total_count = people.len();
+    let people: Vec<PersonData> = people
+        .into_iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .collect();
+    Ok(PeoplePage {
+        count: people.len(),
+        people,
+        total_count,
+        page,
+    })
+}
+
+/// Everyone matching `filter`, fetched a page at a time.
+pub async fn get_all_people(
+    access_token: &str,
+    filter: &PeopleFilter,
+) -> Result<Vec<PersonData>, reqwest::Error> {
+    match filter.category() {
+        Some(category) => get_category_people(access_token, filter, category).await,
+        None => fetch_all(access_token, filter).await,
+    }
+}
+
+/// Everyone on any List in a category who matches the rest of `filter`,
+/// once each and ordered by last name. Categories have no people of their
+/// own in Planning Center, so each List is fetched in full and combined
+/// here.
+async fn get_category_people(
+    access_token: &str,
+    filter: &PeopleFilter,
+    category: &str,
+) -> Result<Vec<PersonData>, reqwest::Error> {
+    let mut people = Vec::new();
+    let mut seen = HashSet::new();
+    for list in lists_in_category(access_token, category).await? {
+        let on_list = PeopleFilter {
+            list: Some(list),
+            ..filter.clone()
+        };
+        for person in fetch_all(access_token, &on_list).await? {
+            if seen.insert(person.id.clone()) {
+                people.push(person);
+            }
+        }
+    }
+    people.sort_by_cached_key(|person| {
+        let last_name = person.name.split_whitespace().last().unwrap_or("");
+        (last_name.to_lowercase(), person.name.to_lowercase())
+    });
+    Ok(people)
+}
+
+/// One page of a Planning Center people collection.
+async fn fetch_page(
+    access_token: &str,
+    page: usize,
+    per_page: usize,
+    filter: &PeopleFilter,
 ) -> Result<PeoplePage, reqwest::Error> {
     let offset = (page - 1) * per_page;
-    let mut url = format!(
-        "{BASE_URL}people?{INCLUDED}&per_page={}&offset={}&order=last_name&where[status]=active",
-        per_page, offset
+    let url = format!(
+        "{}?{INCLUDED}&per_page={}&offset={}&order=last_name{}",
+        filter.collection(),
+        per_page,
+        offset,
+        filter.conditions()
     );
-    if let Some(name) = name {
-        url.push_str(&format!(
-            "&where[search_name]={}",
-            utf8_percent_encode(&name, NON_ALPHANUMERIC)
-        ));
-    }
     let response = reqwest::Client::new()
         .get(url)
         .bearer_auth(access_token)
@@ -211,7 +275,8 @@ pub async fn get_people(
         .json::<PCOPersonResponse>()
         .await?;
 
-    let (addresses, emails, phones, organizations, households) = process_included(response.included);
+    let (addresses, emails, phones, organizations, households) =
+        process_included(response.included);
 
     // Parse each person in the response
     let mut people = Vec::new();
@@ -238,3 +303,20 @@ pub async fn get_people(
         page,
     })
 }
+
+async fn fetch_all(
+    access_token: &str,
+    filter: &PeopleFilter,
+) -> Result<Vec<PersonData>, reqwest::Error> {
+    const PER_PAGE: usize = 100;
+    let mut people = Vec::new();
+    for page in 1.. {
+        let batch = fetch_page(access_token, page, PER_PAGE, filter).await?;
+        let done = batch.count < PER_PAGE || people.len() + batch.count >= batch.total_count;
+        people.extend(batch.people);
+        if done {
+            break;
+        }
+    }
+    Ok(people)
+}
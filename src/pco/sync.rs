@@ -0,0 +1,97 @@
+//! Refreshes the people and households we store locally from Planning
+//! Center, for when profiles change there after someone signed up.
+
+use anyhow::Context;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, Set,
+};
+use std::collections::HashSet;
+
+use crate::{
+    auth::{crypto::TokenKeys, user::ensure_valid_access_token},
+    entities::{household, person, user},
+    pco::person::get_person,
+    router::OauthClient,
+};
+
+/// What a resync changed.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    pub people: usize,
+    pub households: usize,
+    /// People Planning Center no longer returns, left untouched.
+    pub missing: Vec<person::Model>,
+}
+
+/// Re-fetches every person in the organization from Planning Center, using
+/// the most recently active user's token, and updates their contact details
+/// and the names of their households.
+pub async fn resync_organization(
+    db: &DatabaseConnection,
+    client: &OauthClient,
+    token_keys: &TokenKeys,
+    organization_id: i32,
+) -> anyhow::Result<SyncReport> {
+    let mut user = user::Entity::find()
+        .filter(user::Column::OrganizationId.eq(organization_id))
+        .order_by_desc(user::Column::UpdatedAt)
+        .one(db)
+        .await?
+        .context("No user in this organization has a Planning Center login")?;
+    let access_token = ensure_valid_access_token(&mut user, db, client, token_keys).await?;
+
+    let people = person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(organization_id))
+        .order_by_asc(person::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut report = SyncReport::default();
+    let mut synced_households = HashSet::new();
+    for local in people {
+        // People added locally have nothing to refresh.
+        let Some(pco_id) = local.pco_id.as_deref() else {
+            continue;
+        };
+        let Some(remote) = get_person(&access_token, pco_id).await.ok().flatten() else {
+            report.missing.push(local);
+            continue;
+        };
+
+        let mut model = local.into_active_model();
+        model.name = Set(remote.name);
+        model.avatar_url = Set(remote.avatar);
+        model.email = Set(remote.email);
+        model.phone = Set(remote.phone);
+        model.address = Set(remote.address.unwrap_or_default());
+        model.is_child = Set(remote.is_child);
+        model.updated_at = Set(Utc::now().naive_utc());
+        model.update(db).await?;
+        report.people += 1;
+
+        let Some(remote_household) = remote.household else {
+            continue;
+        };
+        if !synced_households.insert(remote_household.id.clone()) {
+            continue;
+        }
+        let Some(local_household) = household::Entity::find()
+            .filter(household::Column::PcoId.eq(&remote_household.id))
+            .filter(household::Column::OrganizationId.eq(organization_id))
+            .one(db)
+            .await?
+        else {
+            continue;
+        };
+        let mut model = local_household.into_active_model();
+        model.name = Set(remote_household.name);
+        model.avatar_url = Set(remote_household.avatar);
+        model.updated_at = Set(Utc::now().naive_utc());
+        model.update(db).await?;
+        report.households += 1;
+    }
+
+    Ok(report)
+}
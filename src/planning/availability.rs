@@ -0,0 +1,103 @@
+use chrono::NaiveDate;
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+
+use crate::entities::unavailability;
+
+use super::participant::{Participant, ParticipantId};
+
+/// A blackout entry resolved to the name of whoever recorded it.
+#[derive(Debug, Serialize)]
+pub struct Entry<'a> {
+    pub entry: &'a unavailability::Model,
+    pub name: Option<String>,
+}
+
+/// Blackout dates recorded for a series.
+#[derive(Debug, Default, Clone)]
+pub struct Availability(Vec<unavailability::Model>);
+
+impl Availability {
+    pub fn new(rows: Vec<unavailability::Model>) -> Self {
+        Self(rows)
+    }
+
+    fn covers(&self, id: ParticipantId, date: NaiveDate) -> bool {
+        self.0.iter().any(|row| {
+            ParticipantId::from_columns(row.person_id, row.household_id) == Some(id)
+                && row.starts_on <= date
+                && date <= row.ends_on
+        })
+    }
+
+    /// Pairs each entry with a display name, looking people up through the
+    /// households they belong to.
+    pub fn entries_with_participants(&self, participants: &[Participant]) -> Vec<Entry<'_>> {
+        self.0
+            .iter()
+            .map(|entry| {
+                let name = match ParticipantId::from_columns(entry.person_id, entry.household_id) {
+                    Some(ParticipantId::Household(id)) => participants
+                        .iter()
+                        .find(|p| p.id == ParticipantId::Household(id))
+                        .map(|p| p.name.clone()),
+                    Some(ParticipantId::Person(id)) => participants
+                        .iter()
+                        .flat_map(|p| &p.members)
+                        .find(|m| m.id == id)
+                        .map(|m| m.name.clone()),
+                    None => None,
+                };
+                Entry { entry, name }
+            })
+            .collect()
+    }
+
+    /// Works out who can make it on `date`.
+    ///
+    /// A household marked away is out entirely. A member marked away on their
+    /// own just stays home, and the household drops out only once none of
+    /// its adults are left.
+    pub fn split(
+        &self,
+        participants: Vec<Participant>,
+        date: NaiveDate,
+    ) -> (Vec<Participant>, Vec<Participant>) {
+        let mut available = Vec::new();
+        let mut out = Vec::new();
+
+        for mut participant in participants {
+            if self.covers(participant.id, date) {
+                out.push(participant);
+                continue;
+            }
+
+            if let ParticipantId::Household(_) = participant.id {
+                participant
+                    .members
+                    .retain(|p| !self.covers(ParticipantId::Person(p.id), date));
+                if participant.adults().next().is_none() {
+                    out.push(participant);
+                    continue;
+                }
+            }
+
+            available.push(participant);
+        }
+
+        (available, out)
+    }
+}
+
+pub async fn load_availability<C>(db: &C, series_id: i32) -> Result<Availability, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let rows = unavailability::Entity::find()
+        .filter(unavailability::Column::PotluckSeriesId.eq(series_id))
+        .order_by_asc(unavailability::Column::StartsOn)
+        .all(db)
+        .await?;
+
+    Ok(Availability::new(rows))
+}
@@ -0,0 +1,231 @@
+use chrono::Utc;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel, Set};
+
+use crate::entities::{attendance, potluck};
+
+use super::generate::record_pairing;
+use super::groups::{Attendee, Group};
+use super::notify::notify;
+use super::pairing::PairingCounts;
+use super::participant::Participant;
+use super::rebalance::{Placement, place_orphans};
+
+fn active_attendee(group: &Group, attendance_id: i32) -> Result<&Attendee, DbErr> {
+    group
+        .find_attendee(attendance_id)
+        .filter(|a| !a.is_cancelled())
+        .ok_or_else(|| DbErr::RecordNotFound(format!("attendance {attendance_id}")))
+}
+
+/// Marks a participant as no longer coming to a potluck and lets the host know.
+pub async fn cancel_attendance<C>(db: &C, group: &Group, attendance_id: i32) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let attendee = active_attendee(group, attendance_id)?;
+
+    let mut row = attendee.attendance.clone().into_active_model();
+    row.cancelled_at = Set(Some(Utc::now().naive_utc()));
+    row.update(db).await?;
+
+    if let Some(host) = &group.host {
+        let message = format!(
+            "{} can no longer make it to your round {} potluck.",
+            attendee.participant.name, group.potluck.round
+        );
+        notify(
+            db,
+            group.potluck.organization_id,
+            host,
+            Some(group.potluck.id),
+            &message,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Records that the host has fallen through. The potluck keeps its host
+/// columns until an organizer promotes a replacement or cancels it.
+pub async fn cancel_host<C>(db: &C, group: &Group) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut potluck = group.potluck.clone().into_active_model();
+    potluck.host_cancelled_at = Set(Some(Utc::now().naive_utc()));
+    potluck.update(db).await?;
+    Ok(())
+}
+
+/// Whether the potluck has a host who is still coming, and so can't be
+/// replaced.
+pub fn host_is_seated(group: &Group) -> bool {
+    group.host.is_some() && group.potluck.host_cancelled_at.is_none()
+}
+
+/// Promotes an attendee to host and tells the rest of the table. Only a host
+/// who has cancelled can be replaced.
+pub async fn replace_host<C>(db: &C, group: &Group, attendance_id: i32) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    if host_is_seated(group) {
+        return Err(DbErr::Custom(
+            "The host hasn't cancelled, so they can't be replaced".to_string(),
+        ));
+    }
+    let new_host = active_attendee(group, attendance_id)?;
+
+    let mut potluck = group.potluck.clone().into_active_model();
+    potluck.host_person_id = Set(new_host.participant.id.person_id());
+    potluck.host_household_id = Set(new_host.participant.id.household_id());
+    potluck.host_cancelled_at = Set(None);
+    potluck.update(db).await?;
+
+    attendance::Entity::delete_by_id(attendance_id)
+        .exec(db)
+        .await?;
+
+    let organization_id = group.potluck.organization_id;
+    let potluck_id = Some(group.potluck.id);
+    let message = format!(
+        "You are now hosting your round {} potluck.",
+        group.potluck.round
+    );
+    notify(
+        db,
+        organization_id,
+        &new_host.participant,
+        potluck_id,
+        &message,
+    )
+    .await?;
+
+    let message = format!(
+        "{} is now hosting your round {} potluck.",
+        new_host.participant.name, group.potluck.round
+    );
+    for attendee in group
+        .active_attendees()
+        .filter(|a| a.attendance.id != attendance_id)
+    {
+        notify(
+            db,
+            organization_id,
+            &attendee.participant,
+            potluck_id,
+            &message,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Cancels a potluck outright and moves its attendees, and its host unless
+/// they had already fallen through, into the other groups of the same round.
+/// Attendees that fit nowhere stay on the cancelled potluck so organizers can
+/// see who still needs a table; a host that fits nowhere is only in the
+/// returned placements.
+pub async fn cancel_potluck<C>(
+    db: &C,
+    group: &Group,
+    round: &[Group],
+    capacity: usize,
+    pairings: &PairingCounts,
+) -> Result<Vec<Placement>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut potluck: potluck::ActiveModel = group.potluck.clone().into_active_model();
+    potluck.cancelled_at = Set(Some(Utc::now().naive_utc()));
+    potluck.update(db).await?;
+
+    let others: Vec<Group> = round
+        .iter()
+        .filter(|g| g.potluck.id != group.potluck.id)
+        .cloned()
+        .collect();
+    let host = group.host.as_ref().filter(|_| host_is_seated(group));
+    let orphans: Vec<Participant> = group
+        .active_attendees()
+        .map(|a| a.participant.clone())
+        .chain(host.cloned())
+        .collect();
+    let placements = place_orphans(&orphans, &others, capacity, pairings);
+
+    let organization_id = group.potluck.organization_id;
+    for placement in &placements {
+        let Some(participant) = orphans.iter().find(|p| p.id == placement.participant) else {
+            continue;
+        };
+        // The host has no attendance row of their own.
+        let attendance = group
+            .active_attendees()
+            .find(|a| a.participant.id == placement.participant)
+            .map(|a| &a.attendance);
+
+        let target = placement
+            .potluck_id
+            .and_then(|id| others.iter().find(|g| g.potluck.id == id));
+
+        match target {
+            Some(target) => {
+                match attendance {
+                    Some(attendance) => {
+                        let mut row = attendance.clone().into_active_model();
+                        row.potluck_id = Set(target.potluck.id);
+                        row.moved_from_potluck_id = Set(Some(group.potluck.id));
+                        row.update(db).await?;
+                    }
+                    None => {
+                        attendance::ActiveModel {
+                            potluck_id: Set(target.potluck.id),
+                            organization_id: Set(organization_id),
+                            attendee_person_id: Set(participant.id.person_id()),
+                            attendee_household_id: Set(participant.id.household_id()),
+                            moved_from_potluck_id: Set(Some(group.potluck.id)),
+                            ..Default::default()
+                        }
+                        .insert(db)
+                        .await?;
+                    }
+                }
+
+                for other in target.participant_ids() {
+                    record_pairing(db, &target.potluck, participant.id, other).await?;
+                }
+
+                let host_name = target
+                    .host
+                    .as_ref()
+                    .map_or("another host", |h| h.name.as_str());
+                let message = format!(
+                    "Your round {} potluck was cancelled. You have been moved to {}'s table.",
+                    group.potluck.round, host_name
+                );
+                let potluck_id = Some(target.potluck.id);
+                notify(db, organization_id, participant, potluck_id, &message).await?;
+
+                if let Some(host) = &target.host {
+                    let message = format!(
+                        "{} is joining your round {} potluck.",
+                        participant.name, target.potluck.round
+                    );
+                    notify(db, organization_id, host, potluck_id, &message).await?;
+                }
+            }
+            None => {
+                let message = format!(
+                    "Your round {} potluck was cancelled. An organizer will be in touch about a new table.",
+                    group.potluck.round
+                );
+                let potluck_id = Some(group.potluck.id);
+                notify(db, organization_id, participant, potluck_id, &message).await?;
+            }
+        }
+    }
+
+    Ok(placements)
+}
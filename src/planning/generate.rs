@@ -0,0 +1,377 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, Set,
+};
+use serde::Serialize;
+
+use crate::entities::{attendance, pairing_history, potluck, potluck_round, potluck_series};
+
+use super::availability::load_availability;
+use super::groups::{Attendee, load_round};
+use super::notify::notify;
+use super::pairing::{PairingCounts, load_pairing_counts};
+use super::participant::{Headcount, Participant, ParticipantId, signed_up_participants};
+use super::rebalance::place_orphans;
+use super::waitlist::save_waitlist;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedGroup {
+    pub host: Participant,
+    pub guests: Vec<Participant>,
+}
+
+impl PlannedGroup {
+    pub fn headcount(&self) -> Headcount {
+        self.host.headcount() + self.guests.iter().map(|g| g.headcount()).sum()
+    }
+
+    fn participant_ids(&self) -> impl Iterator<Item = ParticipantId> + '_ {
+        std::iter::once(self.host.id).chain(self.guests.iter().map(|g| g.id))
+    }
+}
+
+/// Groups for one round, worked out before anything is written.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RoundPlan {
+    pub groups: Vec<PlannedGroup>,
+    /// Participants that did not fit under the group size.
+    pub waitlist: Vec<Participant>,
+}
+
+/// Splits participants into groups for a round.
+///
+/// We open as many tables as the headcount needs, limited by how many people
+/// can host, and hand hosting to whoever has hosted least so far. Everyone
+/// else is seated biggest party first at the table where they have met the
+/// fewest people before, breaking ties towards the emptiest table.
+pub fn plan_round(
+    participants: Vec<Participant>,
+    capacity: usize,
+    pairings: &PairingCounts,
+    times_hosted: &HashMap<ParticipantId, usize>,
+) -> RoundPlan {
+    let capacity = capacity.max(1);
+    let total: usize = participants.iter().map(|p| p.headcount().total()).sum();
+    let wanted = total.div_ceil(capacity).max(1);
+
+    let (mut hosts, mut guests): (Vec<Participant>, Vec<Participant>) =
+        participants.into_iter().partition(|p| p.can_host);
+    hosts.sort_by(|a, b| {
+        let hosted = |p: &Participant| times_hosted.get(&p.id).copied().unwrap_or(0);
+        hosted(a)
+            .cmp(&hosted(b))
+            .then(b.headcount().total().cmp(&a.headcount().total()))
+            .then(a.name.cmp(&b.name))
+    });
+    if hosts.len() > wanted {
+        guests.extend(hosts.split_off(wanted));
+    }
+
+    let mut plan = RoundPlan {
+        groups: hosts
+            .into_iter()
+            .map(|host| PlannedGroup {
+                host,
+                guests: Vec::new(),
+            })
+            .collect(),
+        waitlist: Vec::new(),
+    };
+
+    guests.sort_by_key(|p| std::cmp::Reverse(p.headcount().total()));
+    for guest in guests {
+        seat(&mut plan, guest, capacity, pairings);
+    }
+
+    plan
+}
+
+/// Puts one participant at the best table with room, or on the waitlist.
+pub fn seat(plan: &mut RoundPlan, guest: Participant, capacity: usize, pairings: &PairingCounts) {
+    let size = guest.headcount().total();
+    let best = plan
+        .groups
+        .iter_mut()
+        .map(|group| (group.headcount().total(), group))
+        .filter(|(headcount, _)| headcount + size <= capacity)
+        .min_by_key(|(headcount, group)| {
+            (
+                pairings.repeats(guest.id, group.participant_ids()),
+                *headcount,
+            )
+        });
+
+    match best {
+        Some((_, group)) => group.guests.push(guest),
+        None => plan.waitlist.push(guest),
+    }
+}
+
+/// Everything that happened when generating a round.
+#[derive(Debug, Default, Serialize)]
+pub struct RoundOutcome {
+    pub plan: RoundPlan,
+    /// Participants skipped because of their blackout dates.
+    pub unavailable: Vec<Participant>,
+}
+
+/// How often each participant has hosted in a series so far.
+pub async fn times_hosted<C>(db: &C, series_id: i32) -> Result<HashMap<ParticipantId, usize>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let potlucks = potluck::Entity::find()
+        .filter(potluck::Column::PotluckSeriesId.eq(series_id))
+        .filter(potluck::Column::CancelledAt.is_null())
+        .all(db)
+        .await?;
+
+    let mut counts = HashMap::new();
+    for host in potlucks.iter().filter_map(ParticipantId::host_of) {
+        *counts.entry(host).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+/// Builds the groups for a round from scratch, replacing any groups the
+/// round already had.
+pub async fn generate_round<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    round: &potluck_round::Model,
+) -> Result<RoundOutcome, DbErr>
+where
+    C: ConnectionTrait,
+{
+    // Attendance and pairing history go with the potlucks.
+    potluck::Entity::delete_many()
+        .filter(potluck::Column::PotluckSeriesId.eq(series.id))
+        .filter(potluck::Column::Round.eq(round.round))
+        .exec(db)
+        .await?;
+
+    let participants = signed_up_participants(db, series.organization_id).await?;
+    let availability = load_availability(db, series.id).await?;
+    let (available, unavailable) = availability.split(participants, round.scheduled_on);
+
+    let pairings = load_pairing_counts(db, series.organization_id).await?;
+    let hosted = times_hosted(db, series.id).await?;
+    let plan = plan_round(
+        available,
+        series.group_size.max(0) as usize,
+        &pairings,
+        &hosted,
+    );
+
+    for group in &plan.groups {
+        save_group(db, series, round.round, group).await?;
+    }
+    save_waitlist(db, series, round.round, &plan.waitlist).await?;
+
+    Ok(RoundOutcome { plan, unavailable })
+}
+
+/// A late joiner and the host whose table they were added to.
+#[derive(Debug, Serialize)]
+pub struct Seated {
+    pub participant: Participant,
+    pub host: Option<String>,
+}
+
+/// Everything that happened when adding late joiners to a round.
+#[derive(Debug, Serialize)]
+pub struct JoinOutcome {
+    pub round: i32,
+    pub seated: Vec<Seated>,
+    /// Late joiners still without a table.
+    pub waitlist: Vec<Participant>,
+    pub unavailable: Vec<Participant>,
+}
+
+/// Adds signed-up participants who are not yet part of a round to its
+/// existing groups, leaving everyone already seated where they are.
+///
+/// Late joiners are seated in the order they signed up, each at the table
+/// with room where they have met the fewest people before. Whoever does not
+/// fit goes on the round's waitlist. A household counts as seated as soon as
+/// any of its members is. Rounds without groups are left alone and return
+/// `None`; they still need a full [`generate_round`].
+pub async fn place_late_joiners<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    round: &potluck_round::Model,
+) -> Result<Option<JoinOutcome>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut groups = load_round(db, series.id, round.round).await?;
+    if groups.is_empty() {
+        return Ok(None);
+    }
+
+    let mut seated_ids = HashSet::new();
+    let mut seated_people = HashSet::new();
+    for group in &groups {
+        for participant in group
+            .host
+            .iter()
+            .chain(group.attendees.iter().map(|a| &a.participant))
+        {
+            seated_ids.insert(participant.id);
+            seated_people.extend(participant.person_ids());
+        }
+    }
+
+    let participants = signed_up_participants(db, series.organization_id).await?;
+    let availability = load_availability(db, series.id).await?;
+    let (available, unavailable) = availability.split(participants, round.scheduled_on);
+
+    let mut newcomers: Vec<Participant> = available
+        .into_iter()
+        .filter(|p| {
+            !seated_ids.contains(&p.id) && !p.person_ids().any(|id| seated_people.contains(&id))
+        })
+        .collect();
+    newcomers.sort_by(|a, b| a.joined_at.cmp(&b.joined_at).then(a.name.cmp(&b.name)));
+
+    let mut pairings = load_pairing_counts(db, series.organization_id).await?;
+    let capacity = series.group_size.max(0) as usize;
+    let mut seated = Vec::new();
+    let mut waitlist = Vec::new();
+
+    for newcomer in newcomers {
+        let placement = place_orphans(
+            std::slice::from_ref(&newcomer),
+            &groups,
+            capacity,
+            &pairings,
+        );
+        let Some(group) = placement
+            .first()
+            .and_then(|p| p.potluck_id)
+            .and_then(|id| groups.iter_mut().find(|g| g.potluck.id == id))
+        else {
+            waitlist.push(newcomer);
+            continue;
+        };
+
+        let attendance = add_attendee(db, &group.potluck, newcomer.id).await?;
+        for other in group.participant_ids() {
+            record_pairing(db, &group.potluck, newcomer.id, other).await?;
+            pairings.record(newcomer.id, other);
+        }
+
+        let host = group.host.as_ref().map(|h| h.name.clone());
+        let potluck_id = Some(group.potluck.id);
+        let message = match &host {
+            Some(name) => format!(
+                "You have been added to {name}'s table for round {}.",
+                round.round
+            ),
+            None => format!("You have been added to a table for round {}.", round.round),
+        };
+        notify(db, series.organization_id, &newcomer, potluck_id, &message).await?;
+        if let Some(host) = &group.host {
+            let message = format!(
+                "{} is joining your round {} potluck.",
+                newcomer.name, round.round
+            );
+            notify(db, series.organization_id, host, potluck_id, &message).await?;
+        }
+
+        group.attendees.push(Attendee {
+            attendance,
+            participant: newcomer.clone(),
+        });
+        seated.push(Seated {
+            participant: newcomer,
+            host,
+        });
+    }
+
+    save_waitlist(db, series, round.round, &waitlist).await?;
+
+    Ok(Some(JoinOutcome {
+        round: round.round,
+        seated,
+        waitlist,
+        unavailable,
+    }))
+}
+
+async fn save_group<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    round: i32,
+    group: &PlannedGroup,
+) -> Result<potluck::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let potluck = potluck::ActiveModel {
+        organization_id: Set(series.organization_id),
+        potluck_series_id: Set(series.id),
+        host_person_id: Set(group.host.id.person_id()),
+        host_household_id: Set(group.host.id.household_id()),
+        round: Set(round),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    for guest in &group.guests {
+        add_attendee(db, &potluck, guest.id).await?;
+    }
+
+    let ids: Vec<ParticipantId> = group.participant_ids().collect();
+    for (i, a) in ids.iter().enumerate() {
+        for b in &ids[i + 1..] {
+            record_pairing(db, &potluck, *a, *b).await?;
+        }
+    }
+
+    Ok(potluck)
+}
+
+pub async fn add_attendee<C>(
+    db: &C,
+    potluck: &potluck::Model,
+    id: ParticipantId,
+) -> Result<attendance::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    attendance::ActiveModel {
+        potluck_id: Set(potluck.id),
+        organization_id: Set(potluck.organization_id),
+        attendee_person_id: Set(id.person_id()),
+        attendee_household_id: Set(id.household_id()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+pub async fn record_pairing<C>(
+    db: &C,
+    potluck: &potluck::Model,
+    a: ParticipantId,
+    b: ParticipantId,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    pairing_history::ActiveModel {
+        potluck_id: Set(potluck.id),
+        organization_id: Set(potluck.organization_id),
+        entity_a_person_id: Set(a.person_id()),
+        entity_a_household_id: Set(a.household_id()),
+        entity_b_person_id: Set(b.person_id()),
+        entity_b_household_id: Set(b.household_id()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::Serialize;
+
+use crate::entities::{attendance, potluck};
+
+use super::participant::{Headcount, Participant, ParticipantId, load_participants};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Attendee {
+    pub attendance: attendance::Model,
+    pub participant: Participant,
+}
+
+impl Attendee {
+    pub fn is_cancelled(&self) -> bool {
+        self.attendance.cancelled_at.is_some()
+    }
+}
+
+/// A potluck together with its host and everyone recorded as attending it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Group {
+    pub potluck: potluck::Model,
+    pub host: Option<Participant>,
+    pub attendees: Vec<Attendee>,
+}
+
+impl Group {
+    pub fn is_cancelled(&self) -> bool {
+        self.potluck.cancelled_at.is_some()
+    }
+
+    pub fn active_attendees(&self) -> impl Iterator<Item = &Attendee> {
+        self.attendees.iter().filter(|a| !a.is_cancelled())
+    }
+
+    /// Everyone expected at the table, the host included unless they have
+    /// fallen through.
+    pub fn headcount(&self) -> Headcount {
+        let host = match &self.host {
+            Some(host) if self.potluck.host_cancelled_at.is_none() => host.headcount(),
+            _ => Headcount::default(),
+        };
+        host + self
+            .active_attendees()
+            .map(|a| a.participant.headcount())
+            .sum()
+    }
+
+    pub fn participant_ids(&self) -> Vec<ParticipantId> {
+        self.host
+            .iter()
+            .map(|h| h.id)
+            .chain(self.active_attendees().map(|a| a.participant.id))
+            .collect()
+    }
+
+    /// Attendees who could take over hosting. They already share the table,
+    /// so promoting one of them creates no new pairings.
+    pub fn host_candidates(&self) -> Vec<&Attendee> {
+        self.active_attendees()
+            .filter(|a| a.participant.can_host)
+            .collect()
+    }
+
+    pub fn find_attendee(&self, attendance_id: i32) -> Option<&Attendee> {
+        self.attendees
+            .iter()
+            .find(|a| a.attendance.id == attendance_id)
+    }
+}
+
+pub async fn load_group<C>(db: &C, potluck: potluck::Model) -> Result<Group, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let mut groups = load_groups(db, vec![potluck]).await?;
+    Ok(groups.remove(0))
+}
+
+/// Loads every potluck in a round of a series, cancelled ones included.
+pub async fn load_round<C>(db: &C, series_id: i32, round: i32) -> Result<Vec<Group>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let potlucks = potluck::Entity::find()
+        .filter(potluck::Column::PotluckSeriesId.eq(series_id))
+        .filter(potluck::Column::Round.eq(round))
+        .order_by_asc(potluck::Column::Id)
+        .all(db)
+        .await?;
+
+    load_groups(db, potlucks).await
+}
+
+/// Loads every potluck of a series ordered by round.
+pub async fn load_series<C>(db: &C, series_id: i32) -> Result<Vec<Group>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let potlucks = potluck::Entity::find()
+        .filter(potluck::Column::PotluckSeriesId.eq(series_id))
+        .order_by_asc(potluck::Column::Round)
+        .order_by_asc(potluck::Column::Id)
+        .all(db)
+        .await?;
+
+    load_groups(db, potlucks).await
+}
+
+async fn load_groups<C>(db: &C, potlucks: Vec<potluck::Model>) -> Result<Vec<Group>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let potluck_ids: Vec<i32> = potlucks.iter().map(|p| p.id).collect();
+    let rows = attendance::Entity::find()
+        .filter(attendance::Column::PotluckId.is_in(potluck_ids))
+        .order_by_asc(attendance::Column::Id)
+        .all(db)
+        .await?;
+
+    let mut ids: Vec<ParticipantId> = rows
+        .iter()
+        .filter_map(ParticipantId::from_attendance)
+        .collect();
+    ids.extend(potlucks.iter().filter_map(ParticipantId::host_of));
+
+    let participants: HashMap<ParticipantId, Participant> = load_participants(db, &ids)
+        .await?
+        .into_iter()
+        .map(|p| (p.id, p))
+        .collect();
+
+    let groups = potlucks
+        .into_iter()
+        .map(|potluck| {
+            let host =
+                ParticipantId::host_of(&potluck).and_then(|id| participants.get(&id).cloned());
+            let attendees: Vec<Attendee> = rows
+                .iter()
+                .filter(|row| row.potluck_id == potluck.id)
+                .filter_map(|row| {
+                    let participant = participants.get(&ParticipantId::from_attendance(row)?)?;
+                    Some(Attendee {
+                        attendance: row.clone(),
+                        participant: participant.clone(),
+                    })
+                })
+                .collect();
+
+            // A person whose household is also at this table is already
+            // counted through the household.
+            let covered: HashSet<i32> = host
+                .iter()
+                .chain(attendees.iter().map(|a| &a.participant))
+                .filter(|p| matches!(p.id, ParticipantId::Household(_)))
+                .flat_map(|p| p.person_ids())
+                .collect();
+            let attendees = attendees
+                .into_iter()
+                .filter(|a| match a.participant.id {
+                    ParticipantId::Person(id) => !covered.contains(&id),
+                    ParticipantId::Household(_) => true,
+                })
+                .collect();
+
+            Group {
+                potluck,
+                host,
+                attendees,
+            }
+        })
+        .collect();
+
+    Ok(groups)
+}
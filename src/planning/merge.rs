@@ -0,0 +1,647 @@
+//! Combines two records for the same person or household, such as a guest
+//! added by hand who later turns up in Planning Center.
+//!
+//! Everything that points at the duplicate is moved to the record being
+//! kept, so attendance, pairing history and hosting carry over, and the
+//! duplicate is then deleted. Each merge leaves a `merge_record` behind
+//! with a copy of the deleted row. Two records that host potlucks in the
+//! same round can't be merged until one of them is replaced as host.
+
+use std::collections::HashSet;
+
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, Set, TransactionTrait, sea_query::Expr,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{self, AuditEntity, Change},
+    entities::{
+        attendance, household, merge_record, notification, pairing_history, person, potluck,
+        sea_orm_active_enums::{MergeKind, RecordSource},
+        unavailability, user, waitlist,
+    },
+};
+
+/// How many rows a merge moved onto the kept record.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct MergeCounts {
+    pub attendance: u64,
+    pub pairings: u64,
+    pub hosting: u64,
+    pub waitlist: u64,
+    pub unavailability: u64,
+    pub notifications: u64,
+    pub logins: u64,
+    pub members: u64,
+}
+
+impl MergeCounts {
+    /// A short sentence fragment such as "3 potluck seats, 1 login".
+    pub fn describe(&self) -> String {
+        let moved: Vec<String> = [
+            (self.attendance, "potluck seats"),
+            (self.hosting, "hosted potlucks"),
+            (self.pairings, "past pairings"),
+            (self.waitlist, "waitlist entries"),
+            (self.unavailability, "unavailable rounds"),
+            (self.notifications, "notifications"),
+            (self.logins, "logins"),
+            (self.members, "household members"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, label)| format!("{count} {label}"))
+        .collect();
+        if moved.is_empty() {
+            "nothing else".to_string()
+        } else {
+            moved.join(", ")
+        }
+    }
+}
+
+/// The columns that refer to a participant of one kind.
+struct References {
+    attendance: attendance::Column,
+    pairing_a: pairing_history::Column,
+    pairing_b: pairing_history::Column,
+    host: potluck::Column,
+    waitlist: waitlist::Column,
+    unavailability: unavailability::Column,
+}
+
+const PERSON: References = References {
+    attendance: attendance::Column::AttendeePersonId,
+    pairing_a: pairing_history::Column::EntityAPersonId,
+    pairing_b: pairing_history::Column::EntityBPersonId,
+    host: potluck::Column::HostPersonId,
+    waitlist: waitlist::Column::PersonId,
+    unavailability: unavailability::Column::PersonId,
+};
+
+const HOUSEHOLD: References = References {
+    attendance: attendance::Column::AttendeeHouseholdId,
+    pairing_a: pairing_history::Column::EntityAHouseholdId,
+    pairing_b: pairing_history::Column::EntityBHouseholdId,
+    host: potluck::Column::HostHouseholdId,
+    waitlist: waitlist::Column::HouseholdId,
+    unavailability: unavailability::Column::HouseholdId,
+};
+
+/// Points every `column = from` row of an entity at `to` instead.
+async fn repoint<E, C>(db: &C, column: E::Column, from: i32, to: i32) -> Result<u64, DbErr>
+where
+    E: EntityTrait,
+    C: ConnectionTrait,
+{
+    let result = E::update_many()
+        .col_expr(column, Expr::value(to))
+        .filter(column.eq(from))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+/// A round both records host a potluck in. One record can't host two
+/// potlucks at once, so such a merge is refused.
+fn hosting_clash<'a>(
+    keep: &[potluck::Model],
+    duplicate: &'a [potluck::Model],
+) -> Option<&'a potluck::Model> {
+    duplicate.iter().find(|theirs| {
+        keep.iter().any(|ours| {
+            ours.id != theirs.id
+                && ours.potluck_series_id == theirs.potluck_series_id
+                && ours.round == theirs.round
+        })
+    })
+}
+
+/// Seats that become redundant once both records are one: any seat at a
+/// table the merged record hosts, and the duplicate's seat anywhere the kept
+/// record already sits.
+fn redundant_seats(
+    keep: &[attendance::Model],
+    duplicate: &[attendance::Model],
+    hosted: &[i32],
+) -> Vec<i32> {
+    let seated: HashSet<i32> = keep.iter().map(|a| a.potluck_id).collect();
+    keep.iter()
+        .filter(|a| hosted.contains(&a.potluck_id))
+        .chain(
+            duplicate
+                .iter()
+                .filter(|a| hosted.contains(&a.potluck_id) || seated.contains(&a.potluck_id)),
+        )
+        .map(|a| a.id)
+        .collect()
+}
+
+async fn move_references<C>(
+    db: &C,
+    refs: &References,
+    keep: i32,
+    duplicate: i32,
+) -> Result<MergeCounts, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let hosts = |id: i32| potluck::Entity::find().filter(refs.host.eq(id)).all(db);
+    let (keep_hosts, duplicate_hosts) = (hosts(keep).await?, hosts(duplicate).await?);
+    if let Some(potluck) = hosting_clash(&keep_hosts, &duplicate_hosts) {
+        return Err(DbErr::Custom(format!(
+            "Both host a potluck in round {}. Replace one of them as host first.",
+            potluck.round
+        )));
+    }
+    let hosting = repoint::<potluck::Entity, _>(db, refs.host, duplicate, keep).await?;
+    let hosted: Vec<i32> = keep_hosts
+        .iter()
+        .chain(&duplicate_hosts)
+        .map(|p| p.id)
+        .collect();
+
+    let seats = |id: i32| {
+        attendance::Entity::find()
+            .filter(refs.attendance.eq(id))
+            .all(db)
+    };
+    let redundant = redundant_seats(&seats(keep).await?, &seats(duplicate).await?, &hosted);
+    attendance::Entity::delete_many()
+        .filter(attendance::Column::Id.is_in(redundant))
+        .exec(db)
+        .await?;
+    let attendance = repoint::<attendance::Entity, _>(db, refs.attendance, duplicate, keep).await?;
+
+    let pairings = repoint::<pairing_history::Entity, _>(db, refs.pairing_a, duplicate, keep)
+        .await?
+        + repoint::<pairing_history::Entity, _>(db, refs.pairing_b, duplicate, keep).await?;
+    // The two records may have been paired with each other.
+    pairing_history::Entity::delete_many()
+        .filter(refs.pairing_a.eq(keep))
+        .filter(refs.pairing_b.eq(keep))
+        .exec(db)
+        .await?;
+
+    let waiting: HashSet<(i32, i32)> = waitlist::Entity::find()
+        .filter(refs.waitlist.eq(keep))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|w| (w.potluck_series_id, w.round))
+        .collect();
+    let repeated: Vec<i32> = waitlist::Entity::find()
+        .filter(refs.waitlist.eq(duplicate))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|w| waiting.contains(&(w.potluck_series_id, w.round)))
+        .map(|w| w.id)
+        .collect();
+    waitlist::Entity::delete_many()
+        .filter(waitlist::Column::Id.is_in(repeated))
+        .exec(db)
+        .await?;
+    let waitlist = repoint::<waitlist::Entity, _>(db, refs.waitlist, duplicate, keep).await?;
+
+    let unavailability =
+        repoint::<unavailability::Entity, _>(db, refs.unavailability, duplicate, keep).await?;
+
+    Ok(MergeCounts {
+        attendance,
+        pairings,
+        hosting,
+        waitlist,
+        unavailability,
+        ..Default::default()
+    })
+}
+
+/// The two sides of a merge, before anything changed.
+struct Merged<'a, T> {
+    kind: MergeKind,
+    organization_id: i32,
+    keep: (i32, &'a str),
+    duplicate: (i32, &'a str),
+    removed: &'a T,
+}
+
+async fn record_merge<C, T>(
+    db: &C,
+    merged: Merged<'_, T>,
+    counts: MergeCounts,
+    merged_by: Option<i32>,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+    T: Serialize,
+{
+    let removed = serde_json::to_value(merged.removed).map_err(|e| DbErr::Json(e.to_string()))?;
+    let counts = serde_json::to_value(counts).map_err(|e| DbErr::Json(e.to_string()))?;
+    let now = Utc::now().naive_utc();
+    merge_record::ActiveModel {
+        organization_id: Set(merged.organization_id),
+        user_id: Set(merged_by),
+        kind: Set(merged.kind),
+        kept_id: Set(merged.keep.0),
+        kept_name: Set(merged.keep.1.to_string()),
+        duplicate_id: Set(merged.duplicate.0),
+        duplicate_name: Set(merged.duplicate.1.to_string()),
+        duplicate: Set(removed),
+        counts: Set(counts),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    let entity = match merged.kind {
+        MergeKind::Person => AuditEntity::Person,
+        MergeKind::Household => AuditEntity::Household,
+    };
+    let change = Change::merged(entity, merged.keep.0, merged.keep.1, merged.duplicate.1);
+    audit::record(db, merged.organization_id, merged_by, [change]).await
+}
+
+fn earliest<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Merges `duplicate` into `keep` and deletes it. Details missing from the
+/// kept record are filled in from the duplicate, including its Planning
+/// Center id. `merged_by` is the user recorded as doing it, if any.
+pub async fn merge_people<C>(
+    db: &C,
+    keep: person::Model,
+    duplicate: person::Model,
+    merged_by: Option<i32>,
+) -> Result<(person::Model, MergeCounts), DbErr>
+where
+    C: ConnectionTrait,
+{
+    if keep.id == duplicate.id || keep.organization_id != duplicate.organization_id {
+        return Err(DbErr::Custom(
+            "Only two different people in the same organization can be merged".to_string(),
+        ));
+    }
+
+    let mut counts = move_references(db, &PERSON, keep.id, duplicate.id).await?;
+    counts.notifications = repoint::<notification::Entity, _>(
+        db,
+        notification::Column::PersonId,
+        duplicate.id,
+        keep.id,
+    )
+    .await?;
+    counts.logins =
+        repoint::<user::Entity, _>(db, user::Column::PersonId, duplicate.id, keep.id).await?;
+
+    // The PCO id is unique, so it has to leave the duplicate first.
+    let pco_id = keep.pco_id.clone().or(duplicate.pco_id.clone());
+    let source = if pco_id.is_some() {
+        RecordSource::Pco
+    } else {
+        keep.source
+    };
+    person::Entity::delete_by_id(duplicate.id).exec(db).await?;
+    let removed = duplicate.clone();
+
+    let mut model = keep.clone().into_active_model();
+    model.pco_id = Set(pco_id);
+    model.source = Set(source);
+    model.email = Set(keep.email.or(duplicate.email));
+    model.phone = Set(keep.phone.or(duplicate.phone));
+    if keep.address.is_null() {
+        model.address = Set(duplicate.address);
+    }
+    model.avatar_url = Set(keep.avatar_url.or(duplicate.avatar_url));
+    model.household_id = Set(keep.household_id.or(duplicate.household_id));
+    model.dietary_notes = Set(keep.dietary_notes.or(duplicate.dietary_notes));
+    model.is_signed_up = Set(keep.is_signed_up || duplicate.is_signed_up);
+    model.can_host = Set(keep.can_host || duplicate.can_host);
+    model.signed_up_at = Set(earliest(keep.signed_up_at, duplicate.signed_up_at));
+    model.updated_at = Set(Utc::now().naive_utc());
+    let merged = model.update(db).await?;
+
+    let record = Merged {
+        kind: MergeKind::Person,
+        organization_id: merged.organization_id,
+        keep: (merged.id, &merged.name),
+        duplicate: (removed.id, &removed.name),
+        removed: &removed,
+    };
+    record_merge(db, record, counts, merged_by).await?;
+
+    Ok((merged, counts))
+}
+
+/// Merges `duplicate` into `keep`, moving its members across, and deletes
+/// it.
+pub async fn merge_households<C>(
+    db: &C,
+    keep: household::Model,
+    duplicate: household::Model,
+    merged_by: Option<i32>,
+) -> Result<(household::Model, MergeCounts), DbErr>
+where
+    C: ConnectionTrait,
+{
+    if keep.id == duplicate.id || keep.organization_id != duplicate.organization_id {
+        return Err(DbErr::Custom(
+            "Only two different households in the same organization can be merged".to_string(),
+        ));
+    }
+
+    let mut counts = move_references(db, &HOUSEHOLD, keep.id, duplicate.id).await?;
+    counts.members =
+        repoint::<person::Entity, _>(db, person::Column::HouseholdId, duplicate.id, keep.id)
+            .await?;
+
+    let pco_id = keep.pco_id.clone().or(duplicate.pco_id.clone());
+    let source = if pco_id.is_some() {
+        RecordSource::Pco
+    } else {
+        keep.source
+    };
+    household::Entity::delete_by_id(duplicate.id)
+        .exec(db)
+        .await?;
+    let removed = duplicate.clone();
+
+    let mut model = keep.clone().into_active_model();
+    model.pco_id = Set(pco_id);
+    model.source = Set(source);
+    model.avatar_url = Set(keep.avatar_url.or(duplicate.avatar_url));
+    model.is_signed_up = Set(keep.is_signed_up || duplicate.is_signed_up);
+    model.can_host = Set(keep.can_host || duplicate.can_host);
+    model.signed_up_at = Set(earliest(keep.signed_up_at, duplicate.signed_up_at));
+    model.updated_at = Set(Utc::now().naive_utc());
+    let merged = model.update(db).await?;
+
+    let record = Merged {
+        kind: MergeKind::Household,
+        organization_id: merged.organization_id,
+        keep: (merged.id, &merged.name),
+        duplicate: (removed.id, &removed.name),
+        removed: &removed,
+    };
+    record_merge(db, record, counts, merged_by).await?;
+
+    Ok((merged, counts))
+}
+
+/// Runs [`merge_people`] and rolls it back, to show what it would do.
+pub async fn preview_people<C>(
+    db: &C,
+    keep: person::Model,
+    duplicate: person::Model,
+) -> Result<(person::Model, MergeCounts), DbErr>
+where
+    C: TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = merge_people(&txn, keep, duplicate, None).await;
+    txn.rollback().await?;
+    result
+}
+
+/// Runs [`merge_households`] and rolls it back, to show what it would do.
+pub async fn preview_households<C>(
+    db: &C,
+    keep: household::Model,
+    duplicate: household::Model,
+) -> Result<(household::Model, MergeCounts), DbErr>
+where
+    C: TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let result = merge_households(&txn, keep, duplicate, None).await;
+    txn.rollback().await?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, ConnectionTrait, Database, DatabaseConnection, DbBackend,
+        DbErr, EntityTrait, IntoActiveModel, QueryFilter, Schema,
+    };
+
+    use super::{hosting_clash, merge_people, preview_people, redundant_seats};
+    use crate::entities::{
+        attendance, audit_log, household, merge_record, notification, pairing_history, person,
+        potluck, sea_orm_active_enums::RecordSource, unavailability, user, waitlist,
+    };
+
+    fn person(id: i32) -> person::Model {
+        person::Model {
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            id,
+            pco_id: None,
+            organization_id: 1,
+            name: format!("Person {id}"),
+            email: None,
+            phone: None,
+            address: serde_json::json!({}),
+            avatar_url: None,
+            is_signed_up: true,
+            can_host: false,
+            is_child: false,
+            household_id: None,
+            signed_up_at: None,
+            dietary_notes: None,
+            source: RecordSource::Manual,
+        }
+    }
+
+    fn potluck(id: i32, round: i32, host: Option<i32>) -> potluck::Model {
+        potluck::Model {
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            id,
+            organization_id: 1,
+            potluck_series_id: 1,
+            host_person_id: host,
+            host_household_id: None,
+            round,
+            cancelled_at: None,
+            host_cancelled_at: None,
+        }
+    }
+
+    fn seat(id: i32, potluck_id: i32, person_id: i32) -> attendance::Model {
+        attendance::Model {
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            id,
+            potluck_id,
+            organization_id: 1,
+            attendee_person_id: Some(person_id),
+            attendee_household_id: None,
+            cancelled_at: None,
+            moved_from_potluck_id: None,
+            dish: None,
+        }
+    }
+
+    /// An empty in-memory database with the tables a merge touches. Foreign
+    /// keys are off so tests only insert the rows they are about.
+    async fn database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        db.execute_unprepared("PRAGMA foreign_keys = OFF")
+            .await
+            .unwrap();
+        let schema = Schema::new(DbBackend::Sqlite);
+        for table in [
+            schema.create_table_from_entity(household::Entity),
+            schema.create_table_from_entity(person::Entity),
+            schema.create_table_from_entity(potluck::Entity),
+            schema.create_table_from_entity(attendance::Entity),
+            schema.create_table_from_entity(pairing_history::Entity),
+            schema.create_table_from_entity(waitlist::Entity),
+            schema.create_table_from_entity(unavailability::Entity),
+            schema.create_table_from_entity(notification::Entity),
+            schema.create_table_from_entity(user::Entity),
+            schema.create_table_from_entity(merge_record::Entity),
+            schema.create_table_from_entity(audit_log::Entity),
+        ] {
+            db.execute(db.get_database_backend().build(&table))
+                .await
+                .unwrap();
+        }
+        db
+    }
+
+    async fn insert<M, A>(db: &DatabaseConnection, models: impl IntoIterator<Item = M>)
+    where
+        M: IntoActiveModel<A>,
+        A: ActiveModelTrait + sea_orm::ActiveModelBehavior + Send,
+        <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+    {
+        for model in models {
+            model.into_active_model().insert(db).await.unwrap();
+        }
+    }
+
+    async fn seats_of(db: &DatabaseConnection, person_id: i32) -> Vec<i32> {
+        let mut potlucks: Vec<i32> = attendance::Entity::find()
+            .filter(attendance::Column::AttendeePersonId.eq(person_id))
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.potluck_id)
+            .collect();
+        potlucks.sort();
+        potlucks
+    }
+
+    #[test]
+    fn hosting_the_same_round_twice_clashes() {
+        let keep = [potluck(1, 1, Some(1)), potluck(2, 2, Some(1))];
+        assert_eq!(
+            hosting_clash(&keep, &[potluck(3, 2, Some(2))]).map(|p| p.id),
+            Some(3)
+        );
+        assert!(hosting_clash(&keep, &[potluck(3, 3, Some(2))]).is_none());
+        assert!(hosting_clash(&keep, &[]).is_none());
+    }
+
+    #[test]
+    fn seats_taken_twice_or_at_the_hosts_table_are_dropped() {
+        let keep = [seat(1, 10, 1), seat(2, 30, 1)];
+        let duplicate = [seat(3, 10, 2), seat(4, 20, 2)];
+        let mut redundant = redundant_seats(&keep, &duplicate, &[30]);
+        redundant.sort();
+        assert_eq!(redundant, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn merging_keeps_one_seat_per_potluck() {
+        let db = database().await;
+        insert(&db, [person(1), person(2)]).await;
+        insert(
+            &db,
+            [
+                potluck(10, 1, None),
+                potluck(20, 2, None),
+                potluck(30, 3, Some(2)),
+            ],
+        )
+        .await;
+        // Both sit at 10, the duplicate also sits at 20, and the kept
+        // person sits at the table the duplicate hosts.
+        insert(
+            &db,
+            [
+                seat(1, 10, 1),
+                seat(2, 30, 1),
+                seat(3, 10, 2),
+                seat(4, 20, 2),
+            ],
+        )
+        .await;
+
+        let (_, counts) = merge_people(&db, person(1), person(2), None).await.unwrap();
+        assert_eq!(counts.attendance, 1);
+        assert_eq!(counts.hosting, 1);
+        assert_eq!(seats_of(&db, 1).await, [10, 20]);
+        let hosted = potluck::Entity::find_by_id(30).one(&db).await.unwrap();
+        assert_eq!(hosted.unwrap().host_person_id, Some(1));
+        assert!(
+            person::Entity::find_by_id(2)
+                .one(&db)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn merging_two_hosts_of_one_round_is_refused() {
+        let db = database().await;
+        insert(&db, [person(1), person(2)]).await;
+        insert(&db, [potluck(10, 1, Some(1)), potluck(20, 1, Some(2))]).await;
+
+        let result = merge_people(&db, person(1), person(2), None).await;
+        assert!(matches!(result, Err(DbErr::Custom(_))), "{result:?}");
+        assert!(
+            person::Entity::find_by_id(2)
+                .one(&db)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn previews_change_nothing() {
+        let db = database().await;
+        insert(&db, [person(1), person(2)]).await;
+        insert(&db, [potluck(10, 1, None), potluck(20, 2, None)]).await;
+        insert(&db, [seat(1, 10, 1), seat(2, 20, 2)]).await;
+
+        let (merged, counts) = preview_people(&db, person(1), person(2)).await.unwrap();
+        assert_eq!(merged.id, 1);
+        assert_eq!(counts.attendance, 1);
+
+        assert!(
+            person::Entity::find_by_id(2)
+                .one(&db)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(seats_of(&db, 2).await, [20]);
+        let records = merge_record::Entity::find().all(&db).await.unwrap();
+        assert!(records.is_empty());
+    }
+}
@@ -0,0 +1,11 @@
+pub mod availability;
+pub mod cancellation;
+pub mod generate;
+pub mod groups;
+pub mod merge;
+pub mod notify;
+pub mod pairing;
+pub mod participant;
+pub mod rebalance;
+pub mod template;
+pub mod waitlist;
@@ -0,0 +1,31 @@
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DbErr, Set};
+
+use crate::entities::notification;
+
+use super::participant::Participant;
+
+/// Leaves an in-app notification for every adult of a participant.
+pub async fn notify<C>(
+    db: &C,
+    organization_id: i32,
+    participant: &Participant,
+    potluck_id: Option<i32>,
+    message: &str,
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    for person in participant.adults() {
+        notification::ActiveModel {
+            organization_id: Set(organization_id),
+            person_id: Set(person.id),
+            potluck_id: Set(potluck_id),
+            message: Set(message.to_string()),
+            read_at: Set(None),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+    Ok(())
+}
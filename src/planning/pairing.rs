@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter};
+
+use crate::entities::pairing_history;
+
+use super::participant::ParticipantId;
+
+/// How many times each pair of participants has already shared a table.
+#[derive(Debug, Default, Clone)]
+pub struct PairingCounts(HashMap<(ParticipantId, ParticipantId), usize>);
+
+impl PairingCounts {
+    pub fn from_history(rows: &[pairing_history::Model]) -> Self {
+        let mut counts = Self::default();
+        for (a, b) in rows.iter().filter_map(ParticipantId::pairing_sides) {
+            counts.record(a, b);
+        }
+        counts
+    }
+
+    pub fn record(&mut self, a: ParticipantId, b: ParticipantId) {
+        *self.0.entry(Self::key(a, b)).or_default() += 1;
+    }
+
+    pub fn count(&self, a: ParticipantId, b: ParticipantId) -> usize {
+        self.0.get(&Self::key(a, b)).copied().unwrap_or(0)
+    }
+
+    /// Total number of previous meetings between `participant` and `others`.
+    pub fn repeats(
+        &self,
+        participant: ParticipantId,
+        others: impl IntoIterator<Item = ParticipantId>,
+    ) -> usize {
+        others
+            .into_iter()
+            .map(|other| self.count(participant, other))
+            .sum()
+    }
+
+    fn key(a: ParticipantId, b: ParticipantId) -> (ParticipantId, ParticipantId) {
+        if a <= b { (a, b) } else { (b, a) }
+    }
+}
+
+pub async fn load_pairing_counts<C>(db: &C, organization_id: i32) -> Result<PairingCounts, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let rows = pairing_history::Entity::find()
+        .filter(pairing_history::Column::OrganizationId.eq(organization_id))
+        .all(db)
+        .await?;
+
+    Ok(PairingCounts::from_history(&rows))
+}
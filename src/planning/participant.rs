@@ -0,0 +1,351 @@
+use std::collections::HashSet;
+
+use chrono::NaiveDateTime;
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+
+use crate::entities::{attendance, household, pairing_history, person, potluck};
+
+/// Identifies the person or household behind an `attendance`, `potluck` host
+/// or `pairing_history` row. Exactly one of the two id columns is ever set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "type", content = "id", rename_all = "snake_case")]
+pub enum ParticipantId {
+    Person(i32),
+    Household(i32),
+}
+
+impl ParticipantId {
+    pub fn from_columns(person_id: Option<i32>, household_id: Option<i32>) -> Option<Self> {
+        match (person_id, household_id) {
+            (Some(id), None) => Some(Self::Person(id)),
+            (None, Some(id)) => Some(Self::Household(id)),
+            _ => None,
+        }
+    }
+
+    pub fn from_attendance(attendance: &attendance::Model) -> Option<Self> {
+        Self::from_columns(
+            attendance.attendee_person_id,
+            attendance.attendee_household_id,
+        )
+    }
+
+    pub fn host_of(potluck: &potluck::Model) -> Option<Self> {
+        Self::from_columns(potluck.host_person_id, potluck.host_household_id)
+    }
+
+    pub fn pairing_sides(pairing: &pairing_history::Model) -> Option<(Self, Self)> {
+        let a = Self::from_columns(pairing.entity_a_person_id, pairing.entity_a_household_id)?;
+        let b = Self::from_columns(pairing.entity_b_person_id, pairing.entity_b_household_id)?;
+        Some((a, b))
+    }
+
+    pub fn person_id(self) -> Option<i32> {
+        match self {
+            Self::Person(id) => Some(id),
+            Self::Household(_) => None,
+        }
+    }
+
+    pub fn household_id(self) -> Option<i32> {
+        match self {
+            Self::Person(_) => None,
+            Self::Household(id) => Some(id),
+        }
+    }
+}
+
+/// Formats as `person:12` or `household:3`, the form used in HTML forms.
+impl std::fmt::Display for ParticipantId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Person(id) => write!(f, "person:{id}"),
+            Self::Household(id) => write!(f, "household:{id}"),
+        }
+    }
+}
+
+impl std::str::FromStr for ParticipantId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid participant id: {s}");
+        let (kind, id) = s.split_once(':').ok_or_else(invalid)?;
+        let id = id.parse().map_err(|_| invalid())?;
+        match kind {
+            "person" => Ok(Self::Person(id)),
+            "household" => Ok(Self::Household(id)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Headcount {
+    pub adults: usize,
+    pub children: usize,
+}
+
+impl Headcount {
+    pub fn total(&self) -> usize {
+        self.adults + self.children
+    }
+}
+
+impl std::ops::Add for Headcount {
+    type Output = Headcount;
+
+    fn add(self, other: Headcount) -> Headcount {
+        Headcount {
+            adults: self.adults + other.adults,
+            children: self.children + other.children,
+        }
+    }
+}
+
+impl std::iter::Sum for Headcount {
+    fn sum<I: Iterator<Item = Headcount>>(iter: I) -> Headcount {
+        iter.fold(Headcount::default(), |acc, h| acc + h)
+    }
+}
+
+/// A unit that gets placed into a potluck group: either a whole household or
+/// a person attending on their own.
+#[derive(Debug, Clone, Serialize)]
+pub struct Participant {
+    pub id: ParticipantId,
+    pub name: String,
+    pub can_host: bool,
+    /// Everyone this participant brings along. For a household this is every
+    /// member, regardless of the members' own sign-up flags.
+    pub members: Vec<person::Model>,
+    /// When the participant signed up, used to seat late joiners first come,
+    /// first served.
+    pub joined_at: Option<NaiveDateTime>,
+}
+
+impl Participant {
+    pub fn from_household(household: household::Model, members: Vec<person::Model>) -> Self {
+        Self {
+            id: ParticipantId::Household(household.id),
+            name: household.name,
+            can_host: household.can_host,
+            members,
+            joined_at: household.signed_up_at,
+        }
+    }
+
+    pub fn from_person(person: person::Model) -> Self {
+        Self {
+            id: ParticipantId::Person(person.id),
+            name: person.name.clone(),
+            can_host: person.can_host,
+            joined_at: person.signed_up_at,
+            members: vec![person],
+        }
+    }
+
+    pub fn headcount(&self) -> Headcount {
+        let children = self.members.iter().filter(|p| p.is_child).count();
+        Headcount {
+            adults: self.members.len() - children,
+            children,
+        }
+    }
+
+    pub fn person_ids(&self) -> impl Iterator<Item = i32> + '_ {
+        self.members.iter().map(|p| p.id)
+    }
+
+    /// Adults are the ones we contact about changes to a group.
+    pub fn adults(&self) -> impl Iterator<Item = &person::Model> {
+        self.members.iter().filter(|p| !p.is_child)
+    }
+
+    /// Postal code of the first member with an address, used as a rough
+    /// proximity signal when moving people between groups.
+    pub fn postal_code(&self) -> Option<&str> {
+        self.members
+            .iter()
+            .find_map(|p| p.address["zip"].as_str())
+            .filter(|zip| !zip.is_empty())
+    }
+}
+
+/// Combines household and individual sign-ups into a single list of
+/// participants.
+///
+/// A signed-up household brings all of its members. A person is only counted
+/// on their own when their household is not itself signed up, so nobody is
+/// ever counted twice.
+pub fn resolve_participants(
+    households: Vec<(household::Model, Vec<person::Model>)>,
+    people: Vec<person::Model>,
+) -> Vec<Participant> {
+    let mut participants: Vec<Participant> = households
+        .into_iter()
+        .map(|(household, members)| Participant::from_household(household, members))
+        .collect();
+
+    let covered: HashSet<i32> = participants.iter().flat_map(|p| p.person_ids()).collect();
+
+    participants.extend(
+        people
+            .into_iter()
+            .filter(|p| !covered.contains(&p.id))
+            .map(Participant::from_person),
+    );
+
+    participants
+}
+
+/// Loads every participant currently signed up in the organization.
+pub async fn signed_up_participants<C>(
+    db: &C,
+    organization_id: i32,
+) -> Result<Vec<Participant>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let households = household::Entity::find()
+        .filter(household::Column::OrganizationId.eq(organization_id))
+        .filter(household::Column::IsSignedUp.eq(true))
+        .order_by_asc(household::Column::Name)
+        .find_with_related(person::Entity)
+        .all(db)
+        .await?;
+
+    let people = person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(organization_id))
+        .filter(person::Column::IsSignedUp.eq(true))
+        .order_by_asc(person::Column::Name)
+        .all(db)
+        .await?;
+
+    Ok(resolve_participants(households, people))
+}
+
+/// Loads participants by id, keeping household members attached. Unlike
+/// [`resolve_participants`] this does not drop anyone; callers decide how
+/// overlapping ids combine.
+pub async fn load_participants<C>(db: &C, ids: &[ParticipantId]) -> Result<Vec<Participant>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let household_ids: Vec<i32> = ids.iter().filter_map(|id| id.household_id()).collect();
+    let person_ids: Vec<i32> = ids.iter().filter_map(|id| id.person_id()).collect();
+
+    let households = if household_ids.is_empty() {
+        Vec::new()
+    } else {
+        household::Entity::find()
+            .filter(household::Column::Id.is_in(household_ids))
+            .order_by_asc(household::Column::Name)
+            .find_with_related(person::Entity)
+            .all(db)
+            .await?
+    };
+
+    let people = if person_ids.is_empty() {
+        Vec::new()
+    } else {
+        person::Entity::find()
+            .filter(person::Column::Id.is_in(person_ids))
+            .order_by_asc(person::Column::Name)
+            .all(db)
+            .await?
+    };
+
+    Ok(households
+        .into_iter()
+        .map(|(household, members)| Participant::from_household(household, members))
+        .chain(people.into_iter().map(Participant::from_person))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParticipantId, resolve_participants};
+    use crate::entities::{household, person, sea_orm_active_enums::RecordSource};
+
+    fn household(id: i32) -> household::Model {
+        household::Model {
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            id,
+            pco_id: None,
+            organization_id: 1,
+            name: format!("Household {id}"),
+            is_signed_up: true,
+            can_host: false,
+            avatar_url: None,
+            signed_up_at: None,
+            source: RecordSource::Manual,
+        }
+    }
+
+    fn person(id: i32, household_id: Option<i32>) -> person::Model {
+        person::Model {
+            created_at: Default::default(),
+            updated_at: Default::default(),
+            id,
+            pco_id: None,
+            organization_id: 1,
+            name: format!("Person {id}"),
+            email: None,
+            phone: None,
+            address: serde_json::json!({}),
+            avatar_url: None,
+            is_signed_up: true,
+            can_host: false,
+            is_child: false,
+            household_id,
+            signed_up_at: None,
+            dietary_notes: None,
+            source: RecordSource::Manual,
+        }
+    }
+
+    #[test]
+    fn ids_round_trip_through_strings() {
+        for id in [ParticipantId::Person(12), ParticipantId::Household(3)] {
+            assert_eq!(id.to_string().parse::<ParticipantId>(), Ok(id));
+        }
+        assert_eq!(ParticipantId::Person(12).to_string(), "person:12");
+        assert_eq!(ParticipantId::Household(3).to_string(), "household:3");
+    }
+
+    #[test]
+    fn malformed_ids_are_rejected() {
+        for s in [
+            "",
+            "person",
+            "person:",
+            "person:x",
+            "pet:1",
+            "12",
+            "household:1:2",
+        ] {
+            assert!(s.parse::<ParticipantId>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn signed_up_households_cover_their_members() {
+        let participants = resolve_participants(
+            vec![(household(1), vec![person(10, Some(1)), person(11, Some(1))])],
+            vec![person(10, Some(1)), person(20, Some(2)), person(30, None)],
+        );
+        let ids: Vec<ParticipantId> = participants.iter().map(|p| p.id).collect();
+        assert_eq!(
+            ids,
+            [
+                ParticipantId::Household(1),
+                ParticipantId::Person(20),
+                ParticipantId::Person(30),
+            ]
+        );
+        assert_eq!(participants[0].headcount().total(), 2);
+    }
+}
@@ -0,0 +1,78 @@
+use serde::Serialize;
+
+use super::groups::Group;
+use super::pairing::PairingCounts;
+use super::participant::{Participant, ParticipantId};
+
+/// Where an orphaned participant should move to. `potluck_id` is `None` when
+/// no group in the round has room for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Placement {
+    pub participant: ParticipantId,
+    pub potluck_id: Option<i32>,
+}
+
+struct Slot {
+    potluck_id: i32,
+    headcount: usize,
+    members: Vec<ParticipantId>,
+    postal_code: Option<String>,
+}
+
+/// Spreads orphaned participants over the still-running groups of a round.
+///
+/// Only groups that stay within `capacity` are considered. Among those we
+/// prefer, in order: the fewest repeat pairings, a host in the same postal
+/// code, and the emptiest table. Bigger parties are placed first since they
+/// are the hardest to fit.
+pub fn place_orphans(
+    orphans: &[Participant],
+    groups: &[Group],
+    capacity: usize,
+    pairings: &PairingCounts,
+) -> Vec<Placement> {
+    let mut slots: Vec<Slot> = groups
+        .iter()
+        .filter(|g| !g.is_cancelled())
+        .map(|g| Slot {
+            potluck_id: g.potluck.id,
+            headcount: g.headcount().total(),
+            members: g.participant_ids(),
+            postal_code: g
+                .host
+                .as_ref()
+                .and_then(|h| h.postal_code())
+                .map(str::to_string),
+        })
+        .collect();
+
+    let mut orphans: Vec<&Participant> = orphans.iter().collect();
+    orphans.sort_by_key(|p| std::cmp::Reverse(p.headcount().total()));
+
+    orphans
+        .into_iter()
+        .map(|orphan| {
+            let size = orphan.headcount().total();
+            let best = slots
+                .iter_mut()
+                .filter(|slot| slot.headcount + size <= capacity)
+                .min_by_key(|slot| {
+                    let repeats = pairings.repeats(orphan.id, slot.members.iter().copied());
+                    let far = slot.postal_code.is_none()
+                        || slot.postal_code.as_deref() != orphan.postal_code();
+                    (repeats, far, slot.headcount)
+                });
+
+            let potluck_id = best.map(|slot| {
+                slot.headcount += size;
+                slot.members.push(orphan.id);
+                slot.potluck_id
+            });
+
+            Placement {
+                participant: orphan.id,
+                potluck_id,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+
+use chrono::{Days, NaiveDate, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryOrder, Set, TryIntoModel, sea_query::Expr,
+};
+use serde::Serialize;
+
+use crate::entities::{household, person, potluck_round, potluck_series, series_template};
+
+use super::groups::load_series;
+use super::participant::ParticipantId;
+
+/// Everything about a series that carries over to the next one: its
+/// settings and round schedule, with dates kept relative to the first day.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesShape {
+    pub description: Option<String>,
+    pub group_size: i32,
+    /// Days from the first day of the series to the last.
+    pub length_days: i32,
+    /// Days from the first day of the series to each round, in round order.
+    pub round_offsets: Vec<i32>,
+}
+
+impl SeriesShape {
+    pub fn from_series(series: &potluck_series::Model, rounds: &[potluck_round::Model]) -> Self {
+        let offset = |date: NaiveDate| (date - series.start_date).num_days() as i32;
+        Self {
+            description: series.description.clone(),
+            group_size: series.group_size,
+            length_days: offset(series.end_date),
+            round_offsets: rounds.iter().map(|r| offset(r.scheduled_on)).collect(),
+        }
+    }
+
+    pub fn from_template(template: &series_template::Model) -> Self {
+        Self {
+            description: template.description.clone(),
+            group_size: template.group_size,
+            length_days: template.length_days,
+            round_offsets: serde_json::from_value(template.round_offsets.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn date(start_date: NaiveDate, offset: i32) -> NaiveDate {
+        start_date + Days::new(offset.max(0) as u64)
+    }
+}
+
+pub async fn load_shape<C>(db: &C, series: &potluck_series::Model) -> Result<SeriesShape, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let rounds = potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+        .order_by_asc(potluck_round::Column::Round)
+        .all(db)
+        .await?;
+
+    Ok(SeriesShape::from_series(series, &rounds))
+}
+
+/// Saves the shape of `series` as a named template, replacing any template
+/// of the organization with the same name.
+pub async fn save_template<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    name: &str,
+) -> Result<series_template::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let shape = load_shape(db, series).await?;
+    let existing = series_template::Entity::find()
+        .filter(series_template::Column::OrganizationId.eq(series.organization_id))
+        .filter(series_template::Column::Name.eq(name))
+        .one(db)
+        .await?;
+
+    let mut template = match existing {
+        Some(existing) => existing.into_active_model(),
+        None => series_template::ActiveModel {
+            organization_id: Set(series.organization_id),
+            name: Set(name.to_string()),
+            ..Default::default()
+        },
+    };
+    template.description = Set(shape.description);
+    template.group_size = Set(shape.group_size);
+    template.length_days = Set(shape.length_days);
+    template.round_offsets = Set(serde_json::json!(shape.round_offsets));
+    template.updated_at = Set(Utc::now().naive_utc());
+    template.save(db).await?.try_into_model()
+}
+
+/// Creates a new series starting on `start_date` with the rounds of `shape`
+/// shifted along with it.
+pub async fn create_series<C>(
+    db: &C,
+    organization_id: i32,
+    name: &str,
+    start_date: NaiveDate,
+    shape: &SeriesShape,
+) -> Result<potluck_series::Model, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let series = potluck_series::ActiveModel {
+        organization_id: Set(organization_id),
+        name: Set(name.to_string()),
+        start_date: Set(start_date),
+        end_date: Set(SeriesShape::date(start_date, shape.length_days)),
+        description: Set(shape.description.clone()),
+        group_size: Set(shape.group_size),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    for (i, offset) in shape.round_offsets.iter().enumerate() {
+        potluck_round::ActiveModel {
+            organization_id: Set(organization_id),
+            potluck_series_id: Set(series.id),
+            round: Set(i as i32 + 1),
+            scheduled_on: Set(SeriesShape::date(start_date, *offset)),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(series)
+}
+
+/// Signs up again everyone who hosted or attended a potluck in `series_id`.
+/// Returns how many people and households were not signed up already.
+pub async fn sign_up_roster<C>(db: &C, series_id: i32) -> Result<u64, DbErr>
+where
+    C: ConnectionTrait,
+{
+    let roster: HashSet<ParticipantId> = load_series(db, series_id)
+        .await?
+        .iter()
+        .flat_map(|group| group.participant_ids())
+        .collect();
+    let now = Utc::now().naive_utc();
+
+    let person_ids: Vec<i32> = roster.iter().filter_map(|id| id.person_id()).collect();
+    let people = person::Entity::update_many()
+        .col_expr(person::Column::IsSignedUp, Expr::value(true))
+        .col_expr(person::Column::SignedUpAt, Expr::value(now))
+        .filter(person::Column::Id.is_in(person_ids))
+        .filter(person::Column::IsSignedUp.eq(false))
+        .exec(db)
+        .await?;
+
+    let household_ids: Vec<i32> = roster.iter().filter_map(|id| id.household_id()).collect();
+    let households = household::Entity::update_many()
+        .col_expr(household::Column::IsSignedUp, Expr::value(true))
+        .col_expr(household::Column::SignedUpAt, Expr::value(now))
+        .filter(household::Column::Id.is_in(household_ids))
+        .filter(household::Column::IsSignedUp.eq(false))
+        .exec(db)
+        .await?;
+
+    Ok(people.rows_affected + households.rows_affected)
+}
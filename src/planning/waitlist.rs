@@ -0,0 +1,68 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    Set,
+};
+
+use crate::entities::{potluck_series, waitlist};
+
+use super::participant::{Participant, ParticipantId};
+
+/// Replaces the waitlist of a round with `participants`.
+pub async fn save_waitlist<C>(
+    db: &C,
+    series: &potluck_series::Model,
+    round: i32,
+    participants: &[Participant],
+) -> Result<(), DbErr>
+where
+    C: ConnectionTrait,
+{
+    waitlist::Entity::delete_many()
+        .filter(waitlist::Column::PotluckSeriesId.eq(series.id))
+        .filter(waitlist::Column::Round.eq(round))
+        .exec(db)
+        .await?;
+
+    for participant in participants {
+        waitlist::ActiveModel {
+            organization_id: Set(series.organization_id),
+            potluck_series_id: Set(series.id),
+            round: Set(round),
+            person_id: Set(participant.id.person_id()),
+            household_id: Set(participant.id.household_id()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Loads everyone waiting for a table in a series, in the order they were
+/// put on the waitlist.
+pub async fn load_waitlist<C>(db: &C, series_id: i32) -> Result<Vec<waitlist::Model>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    waitlist::Entity::find()
+        .filter(waitlist::Column::PotluckSeriesId.eq(series_id))
+        .order_by_asc(waitlist::Column::Round)
+        .order_by_asc(waitlist::Column::Id)
+        .all(db)
+        .await
+}
+
+/// Participants waiting in `round`, skipping anyone who has since dropped out.
+pub fn waiting_in_round(
+    entries: &[waitlist::Model],
+    round: i32,
+    participants: &[Participant],
+) -> Vec<Participant> {
+    entries
+        .iter()
+        .filter(|entry| entry.round == round)
+        .filter_map(|entry| ParticipantId::from_columns(entry.person_id, entry.household_id))
+        .filter_map(|id| participants.iter().find(|p| p.id == id).cloned())
+        .collect()
+}
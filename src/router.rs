@@ -1,21 +1,29 @@
 use crate::{
     auth::{
-        router as auth_router,
+        crypto::TokenKeys,
+        csrf, guard, router as auth_router,
         user::{AuthSession, Backend},
     },
+    config::Config,
+    enroll,
+    error::{self, AppError},
     routes::{
-        api::{api_pco, api_people},
+        api,
         dashboard::dashboard,
-        me::me,
-        people,
-        search::{search, search_partial, sign_up_household, sign_up_person},
+        history, me, people, potlucks,
+        search::{
+            search, search_partial, sign_up_household, sign_up_list, sign_up_many, sign_up_person,
+            sign_up_progress,
+        },
+        series,
     },
-    util::asset_loader::AssetLoader,
+    util::{asset_loader::AssetLoader, client_ip::client_ip},
 };
 use axum::{
     Router,
-    extract::State,
-    response::{Html, IntoResponse, Redirect},
+    extract::{Request, State},
+    middleware,
+    response::{Html, IntoResponse, Redirect, Response},
     routing::{get, get_service, post},
 };
 use axum_login::{
@@ -26,24 +34,39 @@ use axum_login::{
     },
 };
 use minijinja::Environment;
-use oauth2::{EndpointNotSet, EndpointSet, basic::BasicClient};
+use oauth2::{AuthUrl, EndpointNotSet, EndpointSet, TokenUrl, basic::BasicClient};
 use sea_orm::DatabaseConnection;
 use std::sync::Arc;
 use tokio::{signal, task::AbortHandle};
-use tower_http::services::ServeDir;
+use tower_http::{catch_panic::CatchPanicLayer, services::ServeDir, trace::TraceLayer};
 use tower_sessions_sqlx_store::PostgresStore;
 
 pub type OauthClient =
     BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
 
+pub fn setup_oauth_client(config: &Config) -> anyhow::Result<OauthClient> {
+    let auth_url =
+        AuthUrl::new("https://api.planningcenteronline.com/oauth/authorize".to_string())?;
+    let token_url = TokenUrl::new("https://api.planningcenteronline.com/oauth/token".to_string())?;
+    let client = BasicClient::new(config.client_id.clone())
+        .set_client_secret(config.client_secret.clone())
+        .set_auth_uri(auth_url)
+        .set_token_uri(token_url)
+        .set_redirect_uri(config.redirect_url.clone());
+    Ok(client)
+}
+
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
     pub client: OauthClient,
+    pub token_keys: TokenKeys,
     pub templates: Arc<Environment<'static>>,
+    pub jobs: enroll::Jobs,
 }
 
 pub async fn create_router(
+    config: &Config,
     db: DatabaseConnection,
     client: OauthClient,
     session_store: PostgresStore,
@@ -53,42 +76,82 @@ pub async fn create_router(
     let state = AppState {
         db: db.clone(),
         client: client.clone(),
+        token_keys: config.token_keys.clone(),
         templates: Arc::new(templates),
+        jobs: enroll::Jobs::default(),
     };
 
-    let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(false)
+    let session = &config.session;
+    let mut session_layer = SessionManagerLayer::new(session_store)
+        .with_name(session.cookie_name.clone())
+        .with_secure(session.secure)
         .with_same_site(SameSite::Lax) // Ensure we send the cookie from the OAuth redirect.
-        .with_expiry(Expiry::OnInactivity(time::Duration::days(1)));
+        .with_expiry(Expiry::OnInactivity(time::Duration::hours(
+            session.inactivity_hours,
+        )));
+    if let Some(domain) = &session.cookie_domain {
+        session_layer = session_layer.with_domain(domain.clone());
+    }
 
     // Auth service.
     //
     // This combines the session layer with our backend to establish the auth
     // service which will provide the auth session as a request extension.
-    let backend = Backend::new(db, client);
+    let backend = Backend::new(db, client, config.token_keys.clone());
     let auth_layer = AuthManagerLayerBuilder::new(backend, session_layer).build();
 
-    let app = Router::new()
+    let trust_proxy_headers = config.trust_proxy_headers;
+
+    // Everything here needs a signed-in user, and all but their own profile
+    // needs an organizer to change anything.
+    let protected = Router::new()
         .route("/dashboard", get(dashboard))
-        .route("/me", get(me))
+        .nest(
+            "/history",
+            history::routes().route_layer(middleware::from_fn(guard::organizer_required)),
+        )
         .nest("/people", people::routes())
+        .nest("/potlucks", potlucks::routes())
+        .nest("/series", series::routes())
         .route("/search", get(search))
         .route("/search/partial", get(search_partial))
         .route(
             "/search/sign-up-household/{household_id}",
             post(sign_up_household),
         )
-        .route(
-            "/search/sign-up-person/{person_id}",
-            post(sign_up_person),
-        )
-        .route("/api/people", get(api_people))
-        .route("/api/pco", get(api_pco))
+        .route("/search/sign-up-person/{person_id}", post(sign_up_person))
+        .route("/search/sign-up-list/{list_id}", post(sign_up_list))
+        .route("/search/sign-up", post(sign_up_many))
+        .route("/search/sign-up/{job_id}", get(sign_up_progress))
+        .route_layer(middleware::from_fn(guard::organizer_required_for_changes))
+        .nest("/me", me::routes())
+        .route_layer(middleware::from_fn(guard::login_required));
+
+    let app = Router::new()
+        .merge(protected)
+        .nest("/api", api::routes(&session.cookie_name))
         .route("/", get(index))
         .merge(auth_router::router())
-        .with_state(state)
+        .with_state(state.clone())
+        .layer(middleware::from_fn(csrf::protect))
+        .layer(CatchPanicLayer::custom(error::panic_response))
+        .layer(middleware::from_fn_with_state(state, error::render_errors))
         .nest_service("/static", get_service(ServeDir::new("static")))
-        .layer(auth_layer);
+        .layer(auth_layer)
+        // Log each request with the address of the client that made it.
+        .layer(
+            TraceLayer::new_for_http().make_span_with(move |request: &Request| {
+                let client = client_ip(request, trust_proxy_headers)
+                    .map(|ip| ip.to_string())
+                    .unwrap_or_default();
+                tracing::info_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %request.uri(),
+                    client,
+                )
+            }),
+        );
     Ok(app)
 }
 
@@ -97,17 +160,20 @@ async fn setup_templates() -> Environment<'static> {
     env.set_loader(minijinja::path_loader("templates"));
     let asset_loader = AssetLoader::new();
     asset_loader.register(&mut env);
+    env.add_function("csrf_token", csrf::current_token);
     env
 }
 
-async fn index(State(state): State<AppState>, auth_session: AuthSession) -> impl IntoResponse {
+async fn index(
+    State(state): State<AppState>,
+    auth_session: AuthSession,
+) -> Result<Response, AppError> {
     if auth_session.user.is_some() {
-        Redirect::to("/dashboard").into_response()
-    } else {
-        let tmpl = state.templates.get_template("index.html").unwrap();
-        let html = tmpl.render(minijinja::context! {}).unwrap();
-        Html(html).into_response()
+        return Ok(Redirect::to("/dashboard").into_response());
     }
+    let tmpl = state.templates.get_template("index.html")?;
+    let html = tmpl.render(minijinja::context! {})?;
+    Ok(Html(html).into_response())
 }
 
 pub async fn shutdown_signal(deletion_task_abort_handle: AbortHandle) {
@@ -1,33 +1,65 @@
+mod openapi;
+pub mod v1;
+
 use crate::auth::user::ensure_valid_access_token;
 use crate::entities::user::Entity as UserEntity;
-use crate::pco::person::get_people;
-use crate::{router::AppState, auth::user::AuthSession};
+use crate::pco::{filter::PeopleFilter, person::get_people};
+use crate::router::AppState;
+use v1::ApiUser;
 use axum::{
-    Json,
+    Json, Router,
     extract::{Query, State},
     response::IntoResponse,
+    routing::get,
 };
 use percent_encoding::{NON_ALPHANUMERIC, utf8_percent_encode};
 use reqwest::StatusCode;
 use sea_orm::EntityTrait;
 use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+/// Routes under `/api`, plus the OpenAPI description of them at
+/// `/api/openapi.json`. `cookie_name` is the session cookie, for the
+/// description of how the API can be signed in to.
+pub fn routes(cookie_name: &str) -> Router<AppState> {
+    let (router, spec) = openapi::router(cookie_name).split_for_parts();
+    router.route("/openapi.json", get(move || async move { Json(spec) }))
+}
 
-#[derive(Deserialize)]
+fn api_routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(api_people))
+        .routes(routes!(api_pco))
+        .nest("/v1", v1::routes())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PeopleQuery {
     pub offset: Option<usize>,
+    /// Search by name.
     pub name: Option<String>,
 }
 
 /// Protected API endpoint to fetch paginated people from Planning Center
+#[utoipa::path(
+    get,
+    path = "/people",
+    tag = "pco",
+    params(PeopleQuery),
+    responses(
+        (status = 200, body = crate::pco::person::PeoplePage),
+        (status = 401),
+        (status = 502),
+    )
+)]
 pub async fn api_people(
     State(state): State<AppState>,
-    auth_session: AuthSession,
+    ApiUser(user): ApiUser,
     Query(query): Query<PeopleQuery>,
 ) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(u) => u.id,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
+    let user_id = user.id;
 
     // Fetch user from DB
     let mut user = match UserEntity::find_by_id(user_id).one(&state.db).await {
@@ -36,36 +68,49 @@ pub async fn api_people(
     };
 
     // Ensure access token is valid (refresh if needed)
-    if ensure_valid_access_token(&mut user, &state.db, &state.client).await.is_err() {
+    let Ok(access_token) =
+        ensure_valid_access_token(&mut user, &state.db, &state.client, &state.token_keys).await
+    else {
         return StatusCode::UNAUTHORIZED.into_response();
-    }
+    };
 
     let offset = query.offset.unwrap_or(0);
     let per_page = 25;
     let page = offset / per_page + 1;
-    match get_people(&user.access_token, page, per_page, query.name.clone()).await {
+    let filter = PeopleFilter::by_name(query.name.clone());
+    match get_people(&access_token, page, per_page, &filter).await {
         Ok(people) => Json(people).into_response(),
         Err(_) => StatusCode::BAD_GATEWAY.into_response(),
     }
 }
 
 /// Proxy endpoint to return raw Planning Center people API JSON (per_page=5)
+#[utoipa::path(
+    get,
+    path = "/pco",
+    tag = "pco",
+    params(PeopleQuery),
+    responses(
+        (status = 200, body = Object, description = "Planning Center response, unchanged"),
+        (status = 401),
+        (status = 502),
+    )
+)]
 pub async fn api_pco(
     State(state): State<AppState>,
-    auth_session: AuthSession,
+    ApiUser(user): ApiUser,
     Query(query): Query<PeopleQuery>,
 ) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(u) => u.id,
-        None => return StatusCode::UNAUTHORIZED.into_response(),
-    };
+    let user_id = user.id;
     let mut user = match UserEntity::find_by_id(user_id).one(&state.db).await {
         Ok(Some(u)) => u,
         _ => return StatusCode::UNAUTHORIZED.into_response(),
     };
-    if ensure_valid_access_token(&mut user, &state.db, &state.client).await.is_err() {
+    let Ok(access_token) =
+        ensure_valid_access_token(&mut user, &state.db, &state.client, &state.token_keys).await
+    else {
         return StatusCode::UNAUTHORIZED.into_response();
-    }
+    };
     let offset = query.offset.unwrap_or(0);
     let per_page = 5;
     let mut url = format!(
@@ -79,7 +124,7 @@ pub async fn api_pco(
         ));
     }
     let client = reqwest::Client::new();
-    let resp = client.get(url).bearer_auth(&user.access_token).send().await;
+    let resp = client.get(url).bearer_auth(&access_token).send().await;
     match resp {
         Ok(r) => match r.json::<serde_json::Value>().await {
             Ok(json) => Json(json).into_response(),
@@ -0,0 +1,85 @@
+//! OpenAPI description of the JSON API, built from the handler annotations
+//! so it can't drift from the routes that are actually served.
+
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+use utoipa_axum::router::OpenApiRouter;
+
+use crate::router::AppState;
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Potluck API", description = "JSON API for potluck planning data."),
+    servers((url = "/api")),
+    security(("api_token" = []), ("session" = [])),
+    tags(
+        (name = "series", description = "Potluck series"),
+        (name = "potlucks", description = "Potlucks within a series"),
+        (name = "attendance", description = "Who is attending which potluck"),
+        (name = "people", description = "People and households synced from Planning Center"),
+        (name = "pco", description = "Live Planning Center lookups"),
+    )
+)]
+struct ApiDoc;
+
+/// Personal API tokens for scripts, or the browser session cookie, which is
+/// named by `SESSION_COOKIE_NAME`.
+struct SecuritySchemes<'a> {
+    cookie_name: &'a str,
+}
+
+impl Modify for SecuritySchemes<'_> {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_token",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "Personal API token from the profile page. Read-only tokens may only make GET requests.",
+                    ))
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new(self.cookie_name))),
+        );
+    }
+}
+
+/// The `/api` routes along with their OpenAPI description.
+pub(super) fn router(cookie_name: &str) -> OpenApiRouter<AppState> {
+    let mut doc = ApiDoc::openapi();
+    SecuritySchemes { cookie_name }.modify(&mut doc);
+    OpenApiRouter::with_openapi(doc).merge(super::api_routes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::router;
+
+    const SNAPSHOT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+    /// Fails when the API changes without `openapi.json` being updated.
+    /// Regenerate it with `UPDATE_OPENAPI=1 cargo test openapi`.
+    #[test]
+    fn openapi_snapshot_is_current() {
+        let (_, spec) = router("id").split_for_parts();
+        let generated = spec.to_pretty_json().unwrap() + "\n";
+
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(SNAPSHOT, &generated).unwrap();
+            return;
+        }
+
+        let committed = std::fs::read_to_string(SNAPSHOT).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "openapi.json is out of date; run `UPDATE_OPENAPI=1 cargo test openapi` and commit the result"
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use axum_extra::extract::WithRejection;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    audit::{self, AuditEntity, Change, attendance_name},
+    entities::attendance,
+    router::AppState,
+};
+
+use super::{
+    ApiError, ApiUser, ErrorBody, Page, PageParams, check_participant, potlucks::find_potluck,
+    timestamp_flag,
+};
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_attendance, create_attendance))
+        .routes(routes!(
+            get_attendance,
+            update_attendance,
+            delete_attendance
+        ))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AttendanceFilter {
+    pub potluck_id: Option<i32>,
+    pub person_id: Option<i32>,
+    pub household_id: Option<i32>,
+    pub cancelled: Option<bool>,
+}
+
+/// Exactly one of the attendee ids is required.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateAttendance {
+    pub potluck_id: i32,
+    pub attendee_person_id: Option<i32>,
+    pub attendee_household_id: Option<i32>,
+}
+
+/// Fields left out are kept as they are. Moving to another potluck records
+/// where the attendee came from, and an empty dish clears the assignment.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateAttendance {
+    pub potluck_id: Option<i32>,
+    pub cancelled: Option<bool>,
+    pub dish: Option<String>,
+}
+
+async fn find_attendance(
+    state: &AppState,
+    user: &ApiUser,
+    id: i32,
+) -> Result<attendance::Model, ApiError> {
+    attendance::Entity::find_by_id(id)
+        .filter(attendance::Column::OrganizationId.eq(user.organization_id()))
+        .one(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound("Attendance"))
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "attendance",
+    params(PageParams, AttendanceFilter),
+    responses(
+        (status = 200, body = Page<attendance::Model>),
+        (status = 401, body = ErrorBody),
+    )
+)]
+pub async fn list_attendance(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Query(page), _): WithRejection<Query<PageParams>, ApiError>,
+    WithRejection(Query(filter), _): WithRejection<Query<AttendanceFilter>, ApiError>,
+) -> Result<Json<Page<attendance::Model>>, ApiError> {
+    let mut select = attendance::Entity::find()
+        .filter(attendance::Column::OrganizationId.eq(user.organization_id()))
+        .order_by_asc(attendance::Column::PotluckId)
+        .order_by_asc(attendance::Column::Id);
+    if let Some(potluck_id) = filter.potluck_id {
+        select = select.filter(attendance::Column::PotluckId.eq(potluck_id));
+    }
+    if let Some(person_id) = filter.person_id {
+        select = select.filter(attendance::Column::AttendeePersonId.eq(person_id));
+    }
+    if let Some(household_id) = filter.household_id {
+        select = select.filter(attendance::Column::AttendeeHouseholdId.eq(household_id));
+    }
+    match filter.cancelled {
+        Some(true) => select = select.filter(attendance::Column::CancelledAt.is_not_null()),
+        Some(false) => select = select.filter(attendance::Column::CancelledAt.is_null()),
+        None => {}
+    }
+
+    Ok(Json(Page::fetch(&state.db, select, &page).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "attendance",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = attendance::Model),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_attendance(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<Json<attendance::Model>, ApiError> {
+    Ok(Json(find_attendance(&state, &user, id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "attendance",
+    request_body = CreateAttendance,
+    responses(
+        (status = 201, body = attendance::Model),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 409, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+pub async fn create_attendance(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Json(body), _): WithRejection<Json<CreateAttendance>, ApiError>,
+) -> Result<(StatusCode, Json<attendance::Model>), ApiError> {
+    let potluck = find_potluck(&state, &user, body.potluck_id).await?;
+    check_participant(
+        &state.db,
+        user.organization_id(),
+        body.attendee_person_id,
+        body.attendee_household_id,
+    )
+    .await?;
+
+    let existing = attendance::Entity::find()
+        .filter(attendance::Column::PotluckId.eq(potluck.id))
+        .filter(
+            match (body.attendee_person_id, body.attendee_household_id) {
+                (Some(id), _) => attendance::Column::AttendeePersonId.eq(id),
+                (_, id) => attendance::Column::AttendeeHouseholdId.eq(id),
+            },
+        )
+        .one(&state.db)
+        .await?;
+    if existing.is_some() {
+        return Err(ApiError::Conflict(
+            "Attendee is already recorded for this potluck".to_string(),
+        ));
+    }
+
+    let txn = state.db.begin().await?;
+    let attendance = attendance::ActiveModel {
+        potluck_id: Set(potluck.id),
+        organization_id: Set(user.organization_id()),
+        attendee_person_id: Set(body.attendee_person_id),
+        attendee_household_id: Set(body.attendee_household_id),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    let name = attendance_name(&txn, &attendance).await?;
+    let change = Change::created(AuditEntity::Attendance, attendance.id, &name);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), [change]).await?;
+    txn.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(attendance)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "attendance",
+    params(("id" = i32, Path)),
+    request_body = UpdateAttendance,
+    responses(
+        (status = 200, body = attendance::Model),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+pub async fn update_attendance(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+    WithRejection(Json(body), _): WithRejection<Json<UpdateAttendance>, ApiError>,
+) -> Result<Json<attendance::Model>, ApiError> {
+    let current = find_attendance(&state, &user, id).await?;
+    let mut attendance = current.clone().into_active_model();
+    if let Some(potluck_id) = body.potluck_id
+        && potluck_id != current.potluck_id
+    {
+        let potluck = find_potluck(&state, &user, potluck_id).await?;
+        attendance.potluck_id = Set(potluck.id);
+        attendance.moved_from_potluck_id = Set(Some(current.potluck_id));
+    }
+    if let Some(cancelled) = body.cancelled {
+        attendance.cancelled_at = Set(timestamp_flag(cancelled));
+    }
+    if let Some(dish) = body.dish {
+        let dish = dish.trim();
+        attendance.dish = Set((!dish.is_empty()).then(|| dish.to_string()));
+    }
+
+    attendance.updated_at = Set(Utc::now().naive_utc());
+    let txn = state.db.begin().await?;
+    let attendance = attendance.update(&txn).await?;
+    let name = attendance_name(&txn, &attendance).await?;
+    let changes = Change::diff(
+        AuditEntity::Attendance,
+        attendance.id,
+        &name,
+        &current,
+        &attendance,
+    );
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), changes).await?;
+    txn.commit().await?;
+    Ok(Json(attendance))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "attendance",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn delete_attendance(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<StatusCode, ApiError> {
+    let attendance = find_attendance(&state, &user, id).await?;
+    let txn = state.db.begin().await?;
+    let name = attendance_name(&txn, &attendance).await?;
+    attendance::Entity::delete_by_id(attendance.id)
+        .exec(&txn)
+        .await?;
+    let change = Change::deleted(AuditEntity::Attendance, attendance.id, &name);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), [change]).await?;
+    txn.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,124 @@
+use axum::{
+    Json,
+    extract::rejection::{JsonRejection, PathRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use sea_orm::DbErr;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Errors returned by the JSON API. Every variant renders as
+/// `{"error": {"code": ..., "message": ...}}` with a matching status.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("Authentication required")]
+    Unauthorized,
+
+    #[error("This token does not have the {0} scope")]
+    Forbidden(&'static str),
+
+    #[error("Only organizers and admins can make changes")]
+    ReadOnly,
+
+    #[error("{0} not found")]
+    NotFound(&'static str),
+
+    #[error("{0}")]
+    Validation(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error(transparent)]
+    Json(#[from] JsonRejection),
+
+    #[error(transparent)]
+    Query(#[from] QueryRejection),
+
+    #[error(transparent)]
+    Path(#[from] PathRejection),
+
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetail,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorDetail {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ErrorBody {
+    /// The body for an error raised before an API handler runs, such as a
+    /// failed CSRF check or a panic, where only the status is known.
+    pub fn for_status(status: StatusCode, message: String) -> Self {
+        let code = match status {
+            StatusCode::UNAUTHORIZED => "unauthorized",
+            StatusCode::FORBIDDEN => "forbidden",
+            StatusCode::NOT_FOUND => "not_found",
+            StatusCode::CONFLICT => "conflict",
+            StatusCode::UNPROCESSABLE_ENTITY => "validation_failed",
+            status if status.is_client_error() => "bad_request",
+            _ => "internal_error",
+        };
+        ErrorBody {
+            error: ErrorDetail { code, message },
+        }
+    }
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Unauthorized => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) | ApiError::ReadOnly => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Json(rejection) => rejection.status(),
+            ApiError::Query(rejection) => rejection.status(),
+            ApiError::Path(rejection) => rejection.status(),
+            ApiError::Database(DbErr::RecordNotFound(_)) => StatusCode::NOT_FOUND,
+            ApiError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::Unauthorized => "unauthorized",
+            ApiError::Forbidden(_) | ApiError::ReadOnly => "forbidden",
+            ApiError::NotFound(_) | ApiError::Database(DbErr::RecordNotFound(_)) => "not_found",
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Json(_) | ApiError::Query(_) | ApiError::Path(_) => "bad_request",
+            ApiError::Database(_) => "internal_error",
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let message = match &self {
+            // Don't leak query details to API clients.
+            ApiError::Database(DbErr::RecordNotFound(_)) => "Record not found".to_string(),
+            ApiError::Database(e) => {
+                tracing::error!("API database error: {e}");
+                "Internal server error".to_string()
+            }
+            other => other.to_string(),
+        };
+        let body = ErrorBody {
+            error: ErrorDetail {
+                code: self.code(),
+                message,
+            },
+        };
+        (self.status(), Json(body)).into_response()
+    }
+}
@@ -0,0 +1,194 @@
+//! Versioned JSON API over the potluck data.
+//!
+//! Every endpoint is scoped to the organization of the signed-in user and
+//! reports failures as [`ApiError`] bodies. Requests authenticate either with
+//! the browser session or with a personal API token sent as
+//! `Authorization: Bearer <token>`. List endpoints are paginated
+//! with `page` (starting at 1) and `per_page` query parameters.
+
+mod attendance;
+mod error;
+mod people;
+mod potlucks;
+mod series;
+
+use axum::{
+    extract::FromRequestParts,
+    http::{Method, header::AUTHORIZATION, request::Parts},
+};
+use chrono::Utc;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, EntityTrait, FromQueryResult, PaginatorTrait, QueryFilter, Select,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::router::OpenApiRouter;
+
+use crate::{
+    auth::{
+        guard::has_role,
+        token::{self, Scope},
+        user::AuthSession,
+    },
+    entities::{household, person, sea_orm_active_enums::UserRole, user},
+    router::AppState,
+};
+
+pub use error::{ApiError, ErrorBody};
+
+const DEFAULT_PER_PAGE: u64 = 25;
+const MAX_PER_PAGE: u64 = 100;
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .nest("/series", series::routes())
+        .nest("/potlucks", potlucks::routes())
+        .nest("/attendance", attendance::routes())
+        .merge(people::routes())
+}
+
+/// The user making an API request, from either a bearer token or the
+/// session. Tokens without the `write` scope, and members, may only make
+/// `GET` requests.
+pub struct ApiUser(pub user::Model);
+
+impl ApiUser {
+    pub fn organization_id(&self) -> i32 {
+        self.0.organization_id
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.0.id
+    }
+}
+
+impl FromRequestParts<AppState> for ApiUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let bearer = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let needed = match parts.method {
+            Method::GET | Method::HEAD => Scope::Read,
+            _ => Scope::Write,
+        };
+        let user = if let Some(bearer) = bearer {
+            let grant = token::authenticate(&state.db, bearer.trim())
+                .await?
+                .ok_or(ApiError::Unauthorized)?;
+            if !grant.allows(needed) {
+                return Err(ApiError::Forbidden(match needed {
+                    Scope::Read => "read",
+                    Scope::Write => "write",
+                }));
+            }
+            grant.user
+        } else {
+            let auth_session = AuthSession::from_request_parts(parts, state)
+                .await
+                .map_err(|_| ApiError::Unauthorized)?;
+            auth_session.user.ok_or(ApiError::Unauthorized)?
+        };
+
+        if needed == Scope::Write && !has_role(user.role, UserRole::Organizer) {
+            return Err(ApiError::ReadOnly);
+        }
+        Ok(ApiUser(user))
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// Page to return, starting at 1.
+    pub page: Option<u64>,
+    /// Items per page, at most 100. Defaults to 25.
+    pub per_page: Option<u64>,
+}
+
+impl PageParams {
+    fn page(&self) -> u64 {
+        self.page.unwrap_or(1).max(1)
+    }
+
+    fn per_page(&self) -> u64 {
+        self.per_page
+            .unwrap_or(DEFAULT_PER_PAGE)
+            .clamp(1, MAX_PER_PAGE)
+    }
+}
+
+/// One page of a list endpoint.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+}
+
+impl<T> Page<T> {
+    async fn fetch<C, E>(db: &C, select: Select<E>, params: &PageParams) -> Result<Self, ApiError>
+    where
+        C: ConnectionTrait,
+        E: EntityTrait<Model = T>,
+        T: FromQueryResult + Send + Sync,
+    {
+        let (page, per_page) = (params.page(), params.per_page());
+        let paginator = select.paginate(db, per_page);
+        let total = paginator.num_items().await?;
+        let items = paginator.fetch_page(page - 1).await?;
+        Ok(Self {
+            items,
+            page,
+            per_page,
+            total,
+        })
+    }
+}
+
+/// Treats a JSON `true`/`false` as setting or clearing a timestamp column.
+fn timestamp_flag(flag: bool) -> Option<chrono::NaiveDateTime> {
+    flag.then(|| Utc::now().naive_utc())
+}
+
+/// Checks that whichever of a person or household id is given belongs to
+/// the organization, and that exactly one of them is given.
+async fn check_participant<C>(
+    db: &C,
+    organization_id: i32,
+    person_id: Option<i32>,
+    household_id: Option<i32>,
+) -> Result<(), ApiError>
+where
+    C: ConnectionTrait,
+{
+    match (person_id, household_id) {
+        (Some(id), None) => {
+            person::Entity::find_by_id(id)
+                .filter(person::Column::OrganizationId.eq(organization_id))
+                .one(db)
+                .await?
+                .ok_or(ApiError::NotFound("Person"))?;
+        }
+        (None, Some(id)) => {
+            household::Entity::find_by_id(id)
+                .filter(household::Column::OrganizationId.eq(organization_id))
+                .one(db)
+                .await?
+                .ok_or(ApiError::NotFound("Household"))?;
+        }
+        _ => {
+            return Err(ApiError::Validation(
+                "Exactly one of a person id and a household id is required".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
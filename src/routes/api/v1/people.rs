@@ -0,0 +1,160 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+};
+use axum_extra::extract::WithRejection;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    entities::{household, person},
+    router::AppState,
+};
+
+use super::{ApiError, ApiUser, ErrorBody, Page, PageParams};
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_people))
+        .routes(routes!(get_person))
+        .routes(routes!(list_households))
+        .routes(routes!(get_household))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PersonFilter {
+    /// Matches anywhere in the name.
+    pub name: Option<String>,
+    pub signed_up: Option<bool>,
+    pub can_host: Option<bool>,
+    pub household_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct HouseholdFilter {
+    /// Matches anywhere in the name.
+    pub name: Option<String>,
+    pub signed_up: Option<bool>,
+    pub can_host: Option<bool>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/people",
+    tag = "people",
+    params(PageParams, PersonFilter),
+    responses(
+        (status = 200, body = Page<person::Model>),
+        (status = 401, body = ErrorBody),
+    )
+)]
+pub async fn list_people(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Query(page), _): WithRejection<Query<PageParams>, ApiError>,
+    WithRejection(Query(filter), _): WithRejection<Query<PersonFilter>, ApiError>,
+) -> Result<Json<Page<person::Model>>, ApiError> {
+    let mut select = person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(user.organization_id()))
+        .order_by_asc(person::Column::Name)
+        .order_by_asc(person::Column::Id);
+    if let Some(name) = &filter.name {
+        select = select.filter(person::Column::Name.contains(name));
+    }
+    if let Some(signed_up) = filter.signed_up {
+        select = select.filter(person::Column::IsSignedUp.eq(signed_up));
+    }
+    if let Some(can_host) = filter.can_host {
+        select = select.filter(person::Column::CanHost.eq(can_host));
+    }
+    if let Some(household_id) = filter.household_id {
+        select = select.filter(person::Column::HouseholdId.eq(household_id));
+    }
+
+    Ok(Json(Page::fetch(&state.db, select, &page).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/people/{id}",
+    tag = "people",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = person::Model),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_person(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<Json<person::Model>, ApiError> {
+    let person = person::Entity::find_by_id(id)
+        .filter(person::Column::OrganizationId.eq(user.organization_id()))
+        .one(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound("Person"))?;
+    Ok(Json(person))
+}
+
+#[utoipa::path(
+    get,
+    path = "/households",
+    tag = "people",
+    params(PageParams, HouseholdFilter),
+    responses(
+        (status = 200, body = Page<household::Model>),
+        (status = 401, body = ErrorBody),
+    )
+)]
+pub async fn list_households(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Query(page), _): WithRejection<Query<PageParams>, ApiError>,
+    WithRejection(Query(filter), _): WithRejection<Query<HouseholdFilter>, ApiError>,
+) -> Result<Json<Page<household::Model>>, ApiError> {
+    let mut select = household::Entity::find()
+        .filter(household::Column::OrganizationId.eq(user.organization_id()))
+        .order_by_asc(household::Column::Name)
+        .order_by_asc(household::Column::Id);
+    if let Some(name) = &filter.name {
+        select = select.filter(household::Column::Name.contains(name));
+    }
+    if let Some(signed_up) = filter.signed_up {
+        select = select.filter(household::Column::IsSignedUp.eq(signed_up));
+    }
+    if let Some(can_host) = filter.can_host {
+        select = select.filter(household::Column::CanHost.eq(can_host));
+    }
+
+    Ok(Json(Page::fetch(&state.db, select, &page).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/households/{id}",
+    tag = "people",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = household::Model),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_household(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<Json<household::Model>, ApiError> {
+    let household = household::Entity::find_by_id(id)
+        .filter(household::Column::OrganizationId.eq(user.organization_id()))
+        .one(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound("Household"))?;
+    Ok(Json(household))
+}
@@ -0,0 +1,264 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use axum_extra::extract::WithRejection;
+use chrono::Utc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    audit::{self, AuditEntity, Change, potluck_name},
+    entities::{potluck, potluck_series},
+    router::AppState,
+};
+
+use super::{ApiError, ApiUser, ErrorBody, Page, PageParams, check_participant, timestamp_flag};
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_potlucks, create_potluck))
+        .routes(routes!(get_potluck, update_potluck, delete_potluck))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PotluckFilter {
+    pub series_id: Option<i32>,
+    pub round: Option<i32>,
+    pub cancelled: Option<bool>,
+}
+
+/// A potluck may start without a host; otherwise exactly one of the host
+/// ids is given.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreatePotluck {
+    pub series_id: i32,
+    /// Defaults to 1.
+    pub round: Option<i32>,
+    pub host_person_id: Option<i32>,
+    pub host_household_id: Option<i32>,
+}
+
+/// Fields left out are kept as they are. Giving either host id replaces the
+/// host.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePotluck {
+    pub round: Option<i32>,
+    pub host_person_id: Option<i32>,
+    pub host_household_id: Option<i32>,
+    pub cancelled: Option<bool>,
+    pub host_cancelled: Option<bool>,
+}
+
+pub(super) async fn find_potluck(
+    state: &AppState,
+    user: &ApiUser,
+    id: i32,
+) -> Result<potluck::Model, ApiError> {
+    potluck::Entity::find_by_id(id)
+        .filter(potluck::Column::OrganizationId.eq(user.organization_id()))
+        .one(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound("Potluck"))
+}
+
+fn check_round(round: i32) -> Result<i32, ApiError> {
+    if round < 1 {
+        return Err(ApiError::Validation("round must be at least 1".to_string()));
+    }
+    Ok(round)
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "potlucks",
+    params(PageParams, PotluckFilter),
+    responses(
+        (status = 200, body = Page<potluck::Model>),
+        (status = 401, body = ErrorBody),
+    )
+)]
+pub async fn list_potlucks(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Query(page), _): WithRejection<Query<PageParams>, ApiError>,
+    WithRejection(Query(filter), _): WithRejection<Query<PotluckFilter>, ApiError>,
+) -> Result<Json<Page<potluck::Model>>, ApiError> {
+    let mut select = potluck::Entity::find()
+        .filter(potluck::Column::OrganizationId.eq(user.organization_id()))
+        .order_by_asc(potluck::Column::PotluckSeriesId)
+        .order_by_asc(potluck::Column::Round)
+        .order_by_asc(potluck::Column::Id);
+    if let Some(series_id) = filter.series_id {
+        select = select.filter(potluck::Column::PotluckSeriesId.eq(series_id));
+    }
+    if let Some(round) = filter.round {
+        select = select.filter(potluck::Column::Round.eq(round));
+    }
+    match filter.cancelled {
+        Some(true) => select = select.filter(potluck::Column::CancelledAt.is_not_null()),
+        Some(false) => select = select.filter(potluck::Column::CancelledAt.is_null()),
+        None => {}
+    }
+
+    Ok(Json(Page::fetch(&state.db, select, &page).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "potlucks",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = potluck::Model),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_potluck(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<Json<potluck::Model>, ApiError> {
+    Ok(Json(find_potluck(&state, &user, id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "potlucks",
+    request_body = CreatePotluck,
+    responses(
+        (status = 201, body = potluck::Model),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+pub async fn create_potluck(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Json(body), _): WithRejection<Json<CreatePotluck>, ApiError>,
+) -> Result<(StatusCode, Json<potluck::Model>), ApiError> {
+    let series = potluck_series::Entity::find_by_id(body.series_id)
+        .filter(potluck_series::Column::OrganizationId.eq(user.organization_id()))
+        .one(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound("Series"))?;
+    if body.host_person_id.is_some() || body.host_household_id.is_some() {
+        check_participant(
+            &state.db,
+            user.organization_id(),
+            body.host_person_id,
+            body.host_household_id,
+        )
+        .await?;
+    }
+
+    let txn = state.db.begin().await?;
+    let potluck = potluck::ActiveModel {
+        organization_id: Set(user.organization_id()),
+        potluck_series_id: Set(series.id),
+        host_person_id: Set(body.host_person_id),
+        host_household_id: Set(body.host_household_id),
+        round: Set(check_round(body.round.unwrap_or(1))?),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    let name = potluck_name(&txn, &potluck).await?;
+    let change = Change::created(AuditEntity::Potluck, potluck.id, &name);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), [change]).await?;
+    txn.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(potluck)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "potlucks",
+    params(("id" = i32, Path)),
+    request_body = UpdatePotluck,
+    responses(
+        (status = 200, body = potluck::Model),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+pub async fn update_potluck(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+    WithRejection(Json(body), _): WithRejection<Json<UpdatePotluck>, ApiError>,
+) -> Result<Json<potluck::Model>, ApiError> {
+    let current = find_potluck(&state, &user, id).await?;
+    let mut potluck = current.clone().into_active_model();
+    if let Some(round) = body.round {
+        potluck.round = Set(check_round(round)?);
+    }
+    if body.host_person_id.is_some() || body.host_household_id.is_some() {
+        check_participant(
+            &state.db,
+            user.organization_id(),
+            body.host_person_id,
+            body.host_household_id,
+        )
+        .await?;
+        potluck.host_person_id = Set(body.host_person_id);
+        potluck.host_household_id = Set(body.host_household_id);
+    }
+    if let Some(cancelled) = body.cancelled {
+        potluck.cancelled_at = Set(timestamp_flag(cancelled));
+    }
+    if let Some(host_cancelled) = body.host_cancelled {
+        potluck.host_cancelled_at = Set(timestamp_flag(host_cancelled));
+    }
+
+    potluck.updated_at = Set(Utc::now().naive_utc());
+    let txn = state.db.begin().await?;
+    let name = potluck_name(&txn, &current).await?;
+    let potluck = potluck.update(&txn).await?;
+    let changes = Change::diff(AuditEntity::Potluck, potluck.id, &name, &current, &potluck);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), changes).await?;
+    txn.commit().await?;
+    Ok(Json(potluck))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "potlucks",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn delete_potluck(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<StatusCode, ApiError> {
+    let potluck = find_potluck(&state, &user, id).await?;
+    let txn = state.db.begin().await?;
+    let name = potluck_name(&txn, &potluck).await?;
+    potluck::Entity::delete_by_id(potluck.id).exec(&txn).await?;
+    let change = Change::deleted(AuditEntity::Potluck, potluck.id, &name);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), [change]).await?;
+    txn.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
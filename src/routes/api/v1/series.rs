@@ -0,0 +1,260 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use axum_extra::extract::WithRejection;
+use chrono::{NaiveDate, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::{
+    audit::{self, AuditEntity, Change},
+    entities::potluck_series,
+    router::AppState,
+};
+
+use super::{ApiError, ApiUser, ErrorBody, Page, PageParams};
+
+pub fn routes() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_series, create_series))
+        .routes(routes!(get_series, update_series, delete_series))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SeriesFilter {
+    /// Matches anywhere in the name.
+    pub name: Option<String>,
+    /// Only series running on this date.
+    pub active_on: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateSeries {
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub description: Option<String>,
+    pub group_size: Option<i32>,
+}
+
+/// Fields left out are kept as they are. An empty description clears it.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateSeries {
+    pub name: Option<String>,
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    pub description: Option<String>,
+    pub group_size: Option<i32>,
+}
+
+async fn find_series(
+    state: &AppState,
+    user: &ApiUser,
+    id: i32,
+) -> Result<potluck_series::Model, ApiError> {
+    potluck_series::Entity::find_by_id(id)
+        .filter(potluck_series::Column::OrganizationId.eq(user.organization_id()))
+        .one(&state.db)
+        .await?
+        .ok_or(ApiError::NotFound("Series"))
+}
+
+fn validate(model: &potluck_series::ActiveModel) -> Result<(), ApiError> {
+    let (name, start, end, group_size) = (
+        model.name.as_ref(),
+        model.start_date.as_ref(),
+        model.end_date.as_ref(),
+        model.group_size.as_ref(),
+    );
+    if name.trim().is_empty() {
+        return Err(ApiError::Validation("name must not be empty".to_string()));
+    }
+    if end <= start {
+        return Err(ApiError::Validation(
+            "end_date must be after start_date".to_string(),
+        ));
+    }
+    if *group_size < 1 {
+        return Err(ApiError::Validation(
+            "group_size must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "",
+    tag = "series",
+    params(PageParams, SeriesFilter),
+    responses(
+        (status = 200, body = Page<potluck_series::Model>),
+        (status = 401, body = ErrorBody),
+    )
+)]
+pub async fn list_series(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Query(page), _): WithRejection<Query<PageParams>, ApiError>,
+    WithRejection(Query(filter), _): WithRejection<Query<SeriesFilter>, ApiError>,
+) -> Result<Json<Page<potluck_series::Model>>, ApiError> {
+    let mut select = potluck_series::Entity::find()
+        .filter(potluck_series::Column::OrganizationId.eq(user.organization_id()))
+        .order_by_desc(potluck_series::Column::StartDate)
+        .order_by_asc(potluck_series::Column::Id);
+    if let Some(name) = &filter.name {
+        select = select.filter(potluck_series::Column::Name.contains(name));
+    }
+    if let Some(date) = filter.active_on {
+        select = select
+            .filter(potluck_series::Column::StartDate.lte(date))
+            .filter(potluck_series::Column::EndDate.gte(date));
+    }
+
+    Ok(Json(Page::fetch(&state.db, select, &page).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/{id}",
+    tag = "series",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = potluck_series::Model),
+        (status = 401, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn get_series(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<Json<potluck_series::Model>, ApiError> {
+    Ok(Json(find_series(&state, &user, id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "",
+    tag = "series",
+    request_body = CreateSeries,
+    responses(
+        (status = 201, body = potluck_series::Model),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+pub async fn create_series(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Json(body), _): WithRejection<Json<CreateSeries>, ApiError>,
+) -> Result<(StatusCode, Json<potluck_series::Model>), ApiError> {
+    let series = potluck_series::ActiveModel {
+        organization_id: Set(user.organization_id()),
+        name: Set(body.name.trim().to_string()),
+        start_date: Set(body.start_date),
+        end_date: Set(body.end_date),
+        description: Set(body.description.filter(|d| !d.trim().is_empty())),
+        group_size: Set(body.group_size.unwrap_or(8)),
+        ..Default::default()
+    };
+    validate(&series)?;
+
+    let txn = state.db.begin().await?;
+    let series = series.insert(&txn).await?;
+    let change = Change::created(AuditEntity::Series, series.id, &series.name);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), [change]).await?;
+    txn.commit().await?;
+    Ok((StatusCode::CREATED, Json(series)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/{id}",
+    tag = "series",
+    params(("id" = i32, Path)),
+    request_body = UpdateSeries,
+    responses(
+        (status = 200, body = potluck_series::Model),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+        (status = 422, body = ErrorBody),
+    )
+)]
+pub async fn update_series(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+    WithRejection(Json(body), _): WithRejection<Json<UpdateSeries>, ApiError>,
+) -> Result<Json<potluck_series::Model>, ApiError> {
+    let current = find_series(&state, &user, id).await?;
+    let mut series = current.clone().into_active_model();
+    if let Some(name) = body.name {
+        series.name = Set(name.trim().to_string());
+    }
+    if let Some(start_date) = body.start_date {
+        series.start_date = Set(start_date);
+    }
+    if let Some(end_date) = body.end_date {
+        series.end_date = Set(end_date);
+    }
+    if let Some(description) = body.description {
+        series.description = Set(Some(description).filter(|d| !d.trim().is_empty()));
+    }
+    if let Some(group_size) = body.group_size {
+        series.group_size = Set(group_size);
+    }
+    validate(&series)?;
+
+    series.updated_at = Set(Utc::now().naive_utc());
+    let txn = state.db.begin().await?;
+    let series = series.update(&txn).await?;
+    let changes = Change::diff(
+        AuditEntity::Series,
+        series.id,
+        &series.name,
+        &current,
+        &series,
+    );
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), changes).await?;
+    txn.commit().await?;
+    Ok(Json(series))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/{id}",
+    tag = "series",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 204),
+        (status = 401, body = ErrorBody),
+        (status = 403, body = ErrorBody),
+        (status = 404, body = ErrorBody),
+    )
+)]
+pub async fn delete_series(
+    State(state): State<AppState>,
+    user: ApiUser,
+    WithRejection(Path(id), _): WithRejection<Path<i32>, ApiError>,
+) -> Result<StatusCode, ApiError> {
+    let series = find_series(&state, &user, id).await?;
+    let txn = state.db.begin().await?;
+    potluck_series::Entity::delete_by_id(series.id)
+        .exec(&txn)
+        .await?;
+    let change = Change::deleted(AuditEntity::Series, series.id, &series.name);
+    audit::record(&txn, user.organization_id(), Some(user.user_id()), [change]).await?;
+    txn.commit().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
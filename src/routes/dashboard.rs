@@ -1,43 +1,42 @@
-use axum::{
-    extract::State,
-    response::{Html, IntoResponse},
-};
+use axum::{extract::State, response::Html};
 use chrono::Utc;
 use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
 
-use axum::response::Redirect;
-use crate::{auth::user::AuthSession, router::AppState, entities::potluck_series::{self, Entity as PotluckSeries}};
+use crate::{
+    auth::guard::CurrentUser,
+    entities::potluck_series::{self, Entity as PotluckSeries},
+    error::AppError,
+    router::AppState,
+};
 
-pub async fn dashboard(State(state): State<AppState>, auth_session: AuthSession) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return Redirect::to("/login").into_response();
-    }
+pub async fn dashboard(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, AppError> {
     let now = Utc::now().naive_utc();
 
     // Get active series (where end_date >= today)
     let active_series = PotluckSeries::find()
+        .filter(potluck_series::Column::OrganizationId.eq(user.organization_id))
         .filter(potluck_series::Column::EndDate.gte(now.date()))
         .order_by_asc(potluck_series::Column::StartDate)
         .one(&state.db)
-        .await
-        .unwrap_or(None);
+        .await?;
 
     // Get past series
     let past_series = PotluckSeries::find()
+        .filter(potluck_series::Column::OrganizationId.eq(user.organization_id))
         .filter(potluck_series::Column::EndDate.lt(now.date()))
         .order_by_desc(potluck_series::Column::EndDate)
         .limit(5)
         .all(&state.db)
-        .await
-        .unwrap_or_default();
+        .await?;
 
-    let tmpl = state.templates.get_template("dashboard.html").unwrap();
-    let html = tmpl
-        .render(minijinja::context! {
-            active_series => active_series,
-            past_series => past_series,
-        })
-        .unwrap();
+    let tmpl = state.templates.get_template("dashboard.html")?;
+    let html = tmpl.render(minijinja::context! {
+        active_series => active_series,
+        past_series => past_series,
+    })?;
 
-    Html(html).into_response()
+    Ok(Html(html))
 }
@@ -0,0 +1,169 @@
+use axum::{
+    Router,
+    extract::{Query, State},
+    response::Html,
+    routing::get,
+};
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use minijinja::context;
+use sea_orm::{
+    ActiveEnum, ColumnTrait, EntityTrait, Iterable, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{AuditAction, AuditEntity},
+    auth::guard::CurrentUser,
+    entities::{audit_log, person, user},
+    error::AppError,
+    router::AppState,
+};
+
+/// How many entries the history page shows at once.
+const PAGE_SIZE: u64 = 200;
+
+/// Filters from the history page form. Empty fields mean "any".
+#[derive(Deserialize, Serialize, Default)]
+pub struct HistoryParams {
+    #[serde(default)]
+    entity: String,
+    #[serde(default)]
+    entity_id: String,
+    #[serde(default)]
+    user: String,
+    #[serde(default)]
+    since: String,
+    #[serde(default)]
+    until: String,
+}
+
+#[derive(Serialize)]
+struct HistoryEntry {
+    changed_at: String,
+    changed_by: Option<String>,
+    kind: &'static str,
+    summary: String,
+    entry: audit_log::Model,
+}
+
+#[derive(Serialize)]
+struct Organizer {
+    id: i32,
+    name: String,
+}
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/", get(history))
+}
+
+fn entity_label(entity: AuditEntity) -> &'static str {
+    match entity {
+        AuditEntity::Person => "Person",
+        AuditEntity::Household => "Household",
+        AuditEntity::Series => "Series",
+        AuditEntity::Round => "Round",
+        AuditEntity::Potluck => "Potluck",
+        AuditEntity::Attendance => "Attendance",
+        AuditEntity::SeriesTemplate => "Series template",
+        AuditEntity::Unavailability => "Unavailability",
+        AuditEntity::ApiToken => "API token",
+    }
+}
+
+fn summarize(entry: &audit_log::Model) -> String {
+    let value = |v: &Option<String>| v.clone().unwrap_or_else(|| "nothing".to_string());
+    match entry.action {
+        AuditAction::Create => "Created".to_string(),
+        AuditAction::Delete => "Deleted".to_string(),
+        AuditAction::Merge => format!("Merged in {}", value(&entry.old_value)),
+        AuditAction::Update => format!(
+            "Changed {} from {} to {}",
+            entry
+                .field
+                .as_deref()
+                .unwrap_or("a field")
+                .replace('_', " "),
+            value(&entry.old_value),
+            value(&entry.new_value),
+        ),
+    }
+}
+
+pub async fn history(
+    State(state): State<AppState>,
+    CurrentUser(current_user): CurrentUser,
+    Query(params): Query<HistoryParams>,
+) -> Result<Html<String>, AppError> {
+    let mut select = audit_log::Entity::find()
+        .filter(audit_log::Column::OrganizationId.eq(current_user.organization_id));
+    if let Some(entity) = AuditEntity::iter().find(|e| e.to_value() == params.entity) {
+        select = select.filter(audit_log::Column::Entity.eq(entity));
+    }
+    if let Ok(entity_id) = params.entity_id.trim().parse::<i32>() {
+        select = select.filter(audit_log::Column::EntityId.eq(entity_id));
+    }
+    if let Ok(user_id) = params.user.parse::<i32>() {
+        select = select.filter(audit_log::Column::UserId.eq(user_id));
+    }
+    if let Ok(since) = NaiveDate::parse_from_str(&params.since, "%Y-%m-%d") {
+        select =
+            select.filter(audit_log::Column::CreatedAt.gte(since.and_time(Default::default())));
+    }
+    if let Some(until) = NaiveDate::parse_from_str(&params.until, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.succ_opt())
+    {
+        select = select.filter(audit_log::Column::CreatedAt.lt(until.and_time(Default::default())));
+    }
+    let entries = select
+        .order_by_desc(audit_log::Column::CreatedAt)
+        .order_by_desc(audit_log::Column::Id)
+        .limit(PAGE_SIZE)
+        .all(&state.db)
+        .await?;
+
+    let organizers: Vec<Organizer> = user::Entity::find()
+        .filter(user::Column::OrganizationId.eq(current_user.organization_id))
+        .find_also_related(person::Entity)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .filter_map(|(u, p)| {
+            Some(Organizer {
+                id: u.id,
+                name: p?.name,
+            })
+        })
+        .collect();
+    let names: HashMap<i32, &str> = organizers.iter().map(|o| (o.id, o.name.as_str())).collect();
+
+    let entities: Vec<(AuditEntity, &str)> =
+        AuditEntity::iter().map(|e| (e, entity_label(e))).collect();
+    let truncated = entries.len() as u64 == PAGE_SIZE;
+    let entries: Vec<HistoryEntry> = entries
+        .into_iter()
+        .map(|entry| HistoryEntry {
+            changed_at: entry.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            changed_by: entry
+                .user_id
+                .and_then(|id| names.get(&id).map(|n| n.to_string())),
+            kind: entity_label(entry.entity),
+            summary: summarize(&entry),
+            entry,
+        })
+        .collect();
+
+    let tmpl = state.templates.get_template("history.html")?;
+    let html = tmpl.render(context! {
+        entries => entries,
+        entities => entities,
+        organizers => organizers,
+        filters => params,
+        truncated => truncated,
+        page_size => PAGE_SIZE,
+        active => "history",
+    })?;
+    Ok(Html(html))
+}
@@ -1,49 +1,255 @@
-use crate::entities::{organization, person};
-use crate::{router::AppState, auth::user::AuthSession};
+use crate::audit::{self, AuditEntity, Change};
+use crate::auth::token::{self, Scope};
+use crate::entities::{api_token, notification, organization, person, user};
+use crate::{
+    auth::{
+        guard::CurrentUser,
+        router::{LINK_ACCOUNT_KEY, authorize},
+        user::AuthSession,
+    },
+    error::AppError,
+    router::AppState,
+};
 use axum::{
-    extract::State,
-    response::{Html, IntoResponse},
+    Form, Router,
+    extract::{Path, State},
+    response::{Html, Redirect},
+    routing::{get, post},
+};
+use axum_login::tower_sessions::Session;
+use chrono::{TimeDelta, Utc};
+use sea_orm::{
+    ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+    sea_query::Expr,
 };
-use reqwest::StatusCode;
-use sea_orm::EntityTrait;
-
-pub async fn me(State(state): State<AppState>, auth_session: AuthSession) -> impl IntoResponse {
-    if let Some(user) = auth_session.user {
-        // Fetch related person
-        let person = person::Entity::find_by_id(user.person_id)
-            .one(&state.db)
-            .await
-            .unwrap_or(None);
-
-        if person.is_none() {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-        let person = person.unwrap();
-
-        // Fetch related organization
-        let organization = organization::Entity::find_by_id(user.organization_id)
-            .one(&state.db)
-            .await
-            .unwrap_or(None);
-        if organization.is_none() {
-            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-        }
-        let organization = organization.unwrap();
-
-        let tmpl = state.templates.get_template("me.html").unwrap();
-        let html = tmpl
-            .render(minijinja::context! {
-                name => person.name,
-                email => person.email,
-                phone => person.phone,
-                address => person.address,
-                avatar_url => person.avatar_url,
-                organization_name => organization.name,
-                created_at => user.created_at.format("%Y-%m-%d").to_string(),
+use serde::{Deserialize, Serialize};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(me))
+        .route("/tokens", post(create_token))
+        .route("/tokens/{id}/revoke", post(revoke_token))
+        .route("/organizations/link", post(link_organization))
+        .route("/organizations/{id}/switch", post(switch_organization))
+}
+
+/// One of the organizations linked to the signed-in user's account.
+#[derive(Serialize)]
+struct LinkedOrganization {
+    /// The login to switch to for this organization.
+    user_id: i32,
+    name: String,
+    active: bool,
+}
+
+async fn linked_organizations(
+    state: &AppState,
+    user: &user::Model,
+) -> Result<Vec<LinkedOrganization>, AppError> {
+    let logins = user::Entity::find()
+        .filter(user::Column::AccountId.eq(user.account_id))
+        .find_also_related(organization::Entity)
+        .order_by_asc(user::Column::Id)
+        .all(&state.db)
+        .await?;
+    Ok(logins
+        .into_iter()
+        .filter_map(|(login, organization)| {
+            Some(LinkedOrganization {
+                user_id: login.id,
+                name: organization?.name,
+                active: login.id == user.id,
             })
-            .unwrap();
-        Html(html).into_response()
-    } else {
-        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })
+        .collect())
+}
+
+#[derive(Serialize)]
+struct TokenSummary {
+    token: api_token::Model,
+    scopes: Vec<Scope>,
+    expired: bool,
+}
+
+async fn token_summaries(
+    state: &AppState,
+    user: &user::Model,
+) -> Result<Vec<TokenSummary>, AppError> {
+    let now = Utc::now().naive_utc();
+    Ok(token::list_tokens(&state.db, user)
+        .await?
+        .into_iter()
+        .map(|token| TokenSummary {
+            scopes: token::scopes(&token),
+            expired: token.expires_at.is_some_and(|at| at <= now),
+            token,
+        })
+        .collect())
+}
+
+pub async fn me(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, AppError> {
+    // Fetch related person
+    let person = person::Entity::find_by_id(user.person_id)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    // Fetch related organization
+    let organization = organization::Entity::find_by_id(user.organization_id)
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let notifications = notification::Entity::find()
+        .filter(notification::Column::PersonId.eq(person.id))
+        .order_by_desc(notification::Column::CreatedAt)
+        .limit(20)
+        .all(&state.db)
+        .await?;
+
+    // Seeing them on this page counts as reading them.
+    notification::Entity::update_many()
+        .col_expr(
+            notification::Column::ReadAt,
+            Expr::value(Utc::now().naive_utc()),
+        )
+        .filter(notification::Column::PersonId.eq(person.id))
+        .filter(notification::Column::ReadAt.is_null())
+        .exec(&state.db)
+        .await?;
+
+    let tmpl = state.templates.get_template("me.html")?;
+    let html = tmpl.render(minijinja::context! {
+        name => person.name,
+        email => person.email,
+        phone => person.phone,
+        address => person.address,
+        avatar_url => person.avatar_url,
+        organization_name => organization.name,
+        role => user.role,
+        created_at => user.created_at.format("%Y-%m-%d").to_string(),
+        notifications => notifications,
+        tokens => token_summaries(&state, &user).await?,
+        organizations => linked_organizations(&state, &user).await?,
+    })?;
+    Ok(Html(html))
+}
+
+#[derive(Deserialize)]
+pub struct TokenForm {
+    name: String,
+    /// `read` or `write`; write access includes read.
+    access: String,
+    /// Leave empty for a token that never expires.
+    expires_in_days: Option<String>,
+}
+
+fn render_tokens(
+    state: &AppState,
+    tokens: Vec<TokenSummary>,
+    new_token: Option<String>,
+) -> Result<Html<String>, AppError> {
+    let tmpl = state.templates.get_template("_api_tokens.html")?;
+    let html = tmpl.render(minijinja::context! { tokens, new_token })?;
+    Ok(Html(html))
+}
+
+pub async fn create_token(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Form(form): Form<TokenForm>,
+) -> Result<Html<String>, AppError> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::BadRequest("Give the token a name".to_string()));
     }
+    let scopes = match form.access.as_str() {
+        "read" => vec![Scope::Read],
+        "write" => vec![Scope::Read, Scope::Write],
+        _ => return Err(AppError::BadRequest("Unknown access level".to_string())),
+    };
+    let expires_at = match form.expires_in_days.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(days) => match days.parse::<i64>() {
+            Ok(days) if days > 0 => Some(Utc::now().naive_utc() + TimeDelta::days(days)),
+            _ => {
+                return Err(AppError::BadRequest(
+                    "Expiry must be a whole number of days".to_string(),
+                ));
+            }
+        },
+    };
+
+    let txn = state.db.begin().await?;
+    let (created, secret) = token::create_token(&txn, &user, name, &scopes, expires_at).await?;
+    let change = Change::created(AuditEntity::ApiToken, created.id, &created.name);
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+    let tokens = token_summaries(&state, &user).await?;
+    render_tokens(&state, tokens, Some(secret))
+}
+
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Html<String>, AppError> {
+    let txn = state.db.begin().await?;
+    if !token::revoke_token(&txn, &user, id).await? {
+        return Err(AppError::NotFound);
+    }
+    let name = api_token::Entity::find_by_id(id)
+        .one(&txn)
+        .await?
+        .map(|t| t.name)
+        .unwrap_or_default();
+    let change = Change::updated(
+        AuditEntity::ApiToken,
+        id,
+        &name,
+        "revoked",
+        Some("false".to_string()),
+        Some("true".to_string()),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+    let tokens = token_summaries(&state, &user).await?;
+    render_tokens(&state, tokens, None)
+}
+
+/// Signs in to Planning Center again, as the user's login in another
+/// organization, and adds that organization to their account.
+pub async fn link_organization(
+    auth_session: AuthSession,
+    session: Session,
+    CurrentUser(user): CurrentUser,
+) -> Result<Redirect, AppError> {
+    session
+        .insert(LINK_ACCOUNT_KEY, user.account_id)
+        .await
+        .map_err(anyhow::Error::from)?;
+    Ok(authorize(&auth_session, &session, Some("/me")).await)
+}
+
+/// Makes another organization on the user's account the active one. Each
+/// organization has its own login, so this signs in as that one.
+pub async fn switch_organization(
+    State(state): State<AppState>,
+    mut auth_session: AuthSession,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Redirect, AppError> {
+    let login = user::Entity::find_by_id(id)
+        .filter(user::Column::AccountId.eq(user.account_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    auth_session
+        .login(&login)
+        .await
+        .map_err(|e| anyhow::anyhow!("Switching organization failed: {e}"))?;
+    Ok(Redirect::to("/dashboard"))
 }
@@ -1,5 +1,8 @@
 pub mod api;
 pub mod dashboard;
+pub mod history;
 pub mod me;
 pub mod search;
-pub mod people;
\ No newline at end of file
+pub mod people;
+pub mod potlucks;
+pub mod series;
\ No newline at end of file
@@ -1,16 +1,34 @@
 use axum::{
-    Router,
-    extract::{Path, Query, State},
-    response::{Html, IntoResponse, Redirect},
+    Form, Router,
+    extract::{Multipart, Path, Query, State},
+    response::{Html, Redirect},
     routing::{get, post},
 };
-use minijinja::context;
-use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
-use serde::Deserialize;
+use std::collections::HashMap;
+
+use chrono::Utc;
+use minijinja::{Value, context};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    auth::user::AuthSession,
-    entities::{household, person},
+    audit::{self, AuditEntity, Change},
+    auth::{guard::CurrentUser, user::ensure_valid_access_token},
+    entities::{household, merge_record, person, sea_orm_active_enums::RecordSource, user},
+    error::AppError,
+    import::{self, ImportReport, non_empty},
+    pco::{
+        filter::PeopleFilter,
+        link::{link_household, link_person},
+        person::{get_people, get_person},
+    },
+    planning::{
+        merge::{MergeCounts, merge_households, merge_people, preview_households, preview_people},
+        participant::{Headcount, signed_up_participants},
+    },
     router::AppState,
 };
 
@@ -19,9 +37,89 @@ pub struct PeopleParams {
     tab: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct DietaryNotesForm {
+    dietary_notes: String,
+}
+
+#[derive(Deserialize)]
+pub struct NewPersonForm {
+    name: String,
+    #[serde(default)]
+    email: String,
+    #[serde(default)]
+    phone: String,
+    /// Blank for someone on their own.
+    #[serde(default)]
+    household_id: String,
+    is_child: Option<String>,
+    sign_up: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NewHouseholdForm {
+    name: String,
+    can_host: Option<String>,
+    sign_up: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct LinkParams {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MergeParams {
+    /// The duplicate to preview merging in.
+    with: Option<i32>,
+}
+
+/// Another record that could be merged in.
+#[derive(Serialize)]
+struct MergeCandidate {
+    id: i32,
+    name: String,
+    detail: Option<String>,
+}
+
+/// One line of the before and after comparison in a merge preview.
+#[derive(Serialize)]
+struct MergeField {
+    label: &'static str,
+    keep: String,
+    duplicate: String,
+    merged: String,
+}
+
+#[derive(Serialize)]
+struct MergeEntry {
+    record: merge_record::Model,
+    merged_at: String,
+    merged_by: Option<String>,
+    moved: String,
+}
+
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/", get(all_people))
+        .route("/import", get(import_form).post(import_csv))
+        .route("/merges", get(merge_history))
+        .route("/person", post(create_person))
+        .route("/household", post(create_household))
+        .route("/person/{id}/link", get(link_person_form))
+        .route("/person/{id}/link/{pco_id}", post(link_person_to_pco))
+        .route("/household/{id}/link", get(link_household_form))
+        .route(
+            "/household/{id}/link/{pco_person_id}",
+            post(link_household_to_pco),
+        )
+        .route("/person/{id}/merge", get(merge_person_form))
+        .route("/person/{id}/merge/{duplicate_id}", post(merge_person))
+        .route("/household/{id}/merge", get(merge_household_form))
+        .route(
+            "/household/{id}/merge/{duplicate_id}",
+            post(merge_household),
+        )
         .route(
             "/household/{id}/toggle-active",
             post(toggle_household_active),
@@ -29,127 +127,793 @@ pub fn routes() -> Router<AppState> {
         .route("/person/{id}/toggle-active", post(toggle_person_active))
         .route("/household/{id}/toggle-host", post(toggle_household_host))
         .route("/person/{id}/toggle-host", post(toggle_person_host))
+        .route("/person/{id}/dietary-notes", post(update_dietary_notes))
 }
 
 pub async fn all_people(
     State(state): State<AppState>,
-    auth_session: AuthSession,
-) -> impl IntoResponse {
-    if auth_session.user.is_none() {
-        return Redirect::to("/login").into_response();
-    }
-
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, AppError> {
     let households = household::Entity::find()
+        .filter(household::Column::OrganizationId.eq(user.organization_id))
         .order_by_asc(household::Column::Name)
         .find_with_related(person::Entity)
         .all(&state.db)
-        .await
-        .unwrap();
+        .await?;
 
     let people = person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(user.organization_id))
         .filter(person::Column::HouseholdId.is_null())
         .order_by_asc(person::Column::Name)
         .all(&state.db)
-        .await
-        .unwrap();
+        .await?;
+
+    let participants = signed_up_participants(&state.db, user.organization_id).await?;
+    let headcount: Headcount = participants.iter().map(|p| p.headcount()).sum();
 
-    let tmpl = state.templates.get_template("people.html").unwrap();
-    let html = tmpl
-        .render(context! { households => households, people => people, active => "people", tab => "active" })
-        .unwrap();
-    Html(html).into_response()
+    let tmpl = state.templates.get_template("people.html")?;
+    let html = tmpl.render(context! {
+        households => households,
+        people => people,
+        participant_count => participants.len(),
+        headcount => headcount,
+        active => "people",
+        tab => "active",
+    })?;
+    Ok(Html(html))
 }
 
-async fn render_people_list(state: AppState, tab: String) -> impl IntoResponse {
+async fn render_people_list(
+    state: AppState,
+    organization_id: i32,
+    tab: String,
+) -> Result<Html<String>, AppError> {
     let households = household::Entity::find()
+        .filter(household::Column::OrganizationId.eq(organization_id))
         .order_by_asc(household::Column::Name)
         .find_with_related(person::Entity)
         .all(&state.db)
-        .await
-        .unwrap();
+        .await?;
 
     let people = person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(organization_id))
         .filter(person::Column::HouseholdId.is_null())
         .order_by_asc(person::Column::Name)
         .all(&state.db)
-        .await
-        .unwrap();
+        .await?;
 
-    let tmpl = state.templates.get_template("_people_list.html").unwrap();
-    let html = tmpl
-        .render(context! { households => households, people => people, tab => tab })
-        .unwrap();
-    Html(html).into_response()
+    let participants = signed_up_participants(&state.db, organization_id).await?;
+    let headcount: Headcount = participants.iter().map(|p| p.headcount()).sum();
+
+    let tmpl = state.templates.get_template("_people_list.html")?;
+    let html = tmpl.render(context! {
+        households => households,
+        people => people,
+        participant_count => participants.len(),
+        headcount => headcount,
+        tab => tab,
+    })?;
+    Ok(Html(html))
 }
 
 pub async fn toggle_household_active(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(id): Path<i32>,
     Query(params): Query<PeopleParams>,
-) -> impl IntoResponse {
-    let mut household: household::ActiveModel = household::Entity::find_by_id(id)
-        .one(&state.db)
-        .await
-        .unwrap()
-        .unwrap()
-        .into();
+) -> Result<Html<String>, AppError> {
+    let before = find_household(&state, user.organization_id, id).await?;
+    let mut household: household::ActiveModel = before.clone().into();
 
-    household.is_signed_up = Set(!household.is_signed_up.clone().unwrap());
-    household.update(&state.db).await.unwrap();
+    let signed_up = !before.is_signed_up;
+    household.is_signed_up = Set(signed_up);
+    household.signed_up_at = Set(signed_up.then(|| Utc::now().naive_utc()));
+    let txn = state.db.begin().await?;
+    let household = household.update(&txn).await?;
+    audit::record(
+        &txn,
+        household.organization_id,
+        Some(user.id),
+        Change::diff(
+            AuditEntity::Household,
+            household.id,
+            &household.name,
+            &before,
+            &household,
+        ),
+    )
+    .await?;
+    txn.commit().await?;
 
-    render_people_list(state, params.tab.unwrap_or_else(|| "active".to_string())).await
+    render_people_list(
+        state,
+        household.organization_id,
+        params.tab.unwrap_or_else(|| "active".to_string()),
+    )
+    .await
 }
 
 pub async fn toggle_person_active(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(id): Path<i32>,
     Query(params): Query<PeopleParams>,
-) -> impl IntoResponse {
-    let mut person: person::ActiveModel = person::Entity::find_by_id(id)
-        .one(&state.db)
-        .await
-        .unwrap()
-        .unwrap()
-        .into();
+) -> Result<Html<String>, AppError> {
+    let before = find_person(&state, user.organization_id, id).await?;
+    let mut person: person::ActiveModel = before.clone().into();
 
-    person.is_signed_up = Set(!person.is_signed_up.clone().unwrap());
-    person.update(&state.db).await.unwrap();
+    let signed_up = !before.is_signed_up;
+    person.is_signed_up = Set(signed_up);
+    person.signed_up_at = Set(signed_up.then(|| Utc::now().naive_utc()));
+    let txn = state.db.begin().await?;
+    let person = person.update(&txn).await?;
+    audit::record(
+        &txn,
+        person.organization_id,
+        Some(user.id),
+        Change::diff(
+            AuditEntity::Person,
+            person.id,
+            &person.name,
+            &before,
+            &person,
+        ),
+    )
+    .await?;
+    txn.commit().await?;
 
-    render_people_list(state, params.tab.unwrap_or_else(|| "active".to_string())).await
+    render_people_list(
+        state,
+        person.organization_id,
+        params.tab.unwrap_or_else(|| "active".to_string()),
+    )
+    .await
 }
 
 pub async fn toggle_household_host(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(id): Path<i32>,
     Query(params): Query<PeopleParams>,
-) -> impl IntoResponse {
-    let mut household: household::ActiveModel = household::Entity::find_by_id(id)
-        .one(&state.db)
-        .await
-        .unwrap()
-        .unwrap()
-        .into();
+) -> Result<Html<String>, AppError> {
+    let before = find_household(&state, user.organization_id, id).await?;
+    let mut household: household::ActiveModel = before.clone().into();
 
-    household.can_host = Set(!household.can_host.clone().unwrap());
-    household.update(&state.db).await.unwrap();
+    household.can_host = Set(!before.can_host);
+    let txn = state.db.begin().await?;
+    let household = household.update(&txn).await?;
+    audit::record(
+        &txn,
+        household.organization_id,
+        Some(user.id),
+        Change::diff(
+            AuditEntity::Household,
+            household.id,
+            &household.name,
+            &before,
+            &household,
+        ),
+    )
+    .await?;
+    txn.commit().await?;
 
-    render_people_list(state, params.tab.unwrap_or_else(|| "active".to_string())).await
+    render_people_list(
+        state,
+        household.organization_id,
+        params.tab.unwrap_or_else(|| "active".to_string()),
+    )
+    .await
 }
 
 pub async fn toggle_person_host(
     State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Query(params): Query<PeopleParams>,
+) -> Result<Html<String>, AppError> {
+    let before = find_person(&state, user.organization_id, id).await?;
+    let mut person: person::ActiveModel = before.clone().into();
+
+    person.can_host = Set(!before.can_host);
+    let txn = state.db.begin().await?;
+    let person = person.update(&txn).await?;
+    audit::record(
+        &txn,
+        person.organization_id,
+        Some(user.id),
+        Change::diff(
+            AuditEntity::Person,
+            person.id,
+            &person.name,
+            &before,
+            &person,
+        ),
+    )
+    .await?;
+    txn.commit().await?;
+
+    render_people_list(
+        state,
+        person.organization_id,
+        params.tab.unwrap_or_else(|| "active".to_string()),
+    )
+    .await
+}
+
+pub async fn update_dietary_notes(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
     Path(id): Path<i32>,
     Query(params): Query<PeopleParams>,
-) -> impl IntoResponse {
-    let mut person: person::ActiveModel = person::Entity::find_by_id(id)
+    Form(form): Form<DietaryNotesForm>,
+) -> Result<Html<String>, AppError> {
+    let before = find_person(&state, user.organization_id, id).await?;
+    let mut person: person::ActiveModel = before.clone().into();
+
+    person.dietary_notes = Set(non_empty(&form.dietary_notes));
+    person.updated_at = Set(Utc::now().naive_utc());
+    let txn = state.db.begin().await?;
+    let person = person.update(&txn).await?;
+    audit::record(
+        &txn,
+        person.organization_id,
+        Some(user.id),
+        Change::diff(
+            AuditEntity::Person,
+            person.id,
+            &person.name,
+            &before,
+            &person,
+        ),
+    )
+    .await?;
+    txn.commit().await?;
+
+    render_people_list(
+        state,
+        person.organization_id,
+        params.tab.unwrap_or_else(|| "active".to_string()),
+    )
+    .await
+}
+
+fn render_import(
+    state: &AppState,
+    report: Option<ImportReport>,
+    errors: Vec<String>,
+) -> Result<Html<String>, AppError> {
+    let tmpl = state.templates.get_template("people_import.html")?;
+    let html = tmpl.render(context! {
+        columns => import::COLUMNS,
+        report => report,
+        errors => errors,
+        active => "people",
+    })?;
+    Ok(Html(html))
+}
+
+pub async fn import_form(State(state): State<AppState>) -> Result<Html<String>, AppError> {
+    render_import(&state, None, Vec::new())
+}
+
+/// Takes a multipart upload with a `file` field and an optional `sign_up`
+/// checkbox.
+pub async fn import_csv(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    mut multipart: Multipart,
+) -> Result<Html<String>, AppError> {
+    let mut file = None;
+    let mut sign_up = false;
+    loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => return render_import(&state, None, vec![e.body_text()]),
+        };
+        match field.name() {
+            Some("file") => match field.bytes().await {
+                Ok(bytes) => file = Some(bytes),
+                Err(e) => return render_import(&state, None, vec![e.body_text()]),
+            },
+            Some("sign_up") => sign_up = true,
+            _ => {}
+        }
+    }
+    let Some(file) = file.filter(|f| !f.is_empty()) else {
+        return render_import(
+            &state,
+            None,
+            vec!["Choose a CSV file to import".to_string()],
+        );
+    };
+
+    let rows = match import::parse_rows(file.as_ref()) {
+        Ok(rows) => rows,
+        Err(errors) => return render_import(&state, None, errors),
+    };
+    let txn = state.db.begin().await?;
+    let report =
+        import::import_people(&txn, user.organization_id, rows, sign_up, Some(user.id)).await?;
+    txn.commit().await?;
+    render_import(&state, Some(report), Vec::new())
+}
+
+/// Link and merge refuse records they can't combine with a custom error,
+/// which is the person's mistake rather than ours.
+fn refused(err: DbErr) -> AppError {
+    match err {
+        DbErr::Custom(message) => AppError::Conflict(message),
+        err => err.into(),
+    }
+}
+
+/// Adds someone who isn't in Planning Center, on their own or as a member of
+/// an existing household.
+pub async fn create_person(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Form(form): Form<NewPersonForm>,
+) -> Result<Redirect, AppError> {
+    let name = non_empty(&form.name)
+        .ok_or_else(|| AppError::BadRequest("Name is required".to_string()))?;
+
+    let household_id = match form.household_id.trim() {
+        "" => None,
+        id => {
+            let unknown = || AppError::BadRequest("Unknown household".to_string());
+            let id = id.parse::<i32>().map_err(|_| unknown())?;
+            let household = household::Entity::find_by_id(id)
+                .filter(household::Column::OrganizationId.eq(user.organization_id))
+                .one(&state.db)
+                .await?
+                .ok_or_else(unknown)?;
+            Some(household.id)
+        }
+    };
+
+    // Household members sign up through their household.
+    let signed_up = form.sign_up.is_some() && household_id.is_none();
+    let now = Utc::now().naive_utc();
+    let model = person::ActiveModel {
+        organization_id: Set(user.organization_id),
+        pco_id: Set(None),
+        name: Set(name),
+        email: Set(non_empty(&form.email)),
+        phone: Set(non_empty(&form.phone)),
+        address: Set(serde_json::Value::Null),
+        avatar_url: Set(None),
+        is_signed_up: Set(signed_up),
+        can_host: Set(false),
+        is_child: Set(form.is_child.is_some()),
+        household_id: Set(household_id),
+        signed_up_at: Set(signed_up.then_some(now)),
+        dietary_notes: Set(None),
+        source: Set(RecordSource::Manual),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let txn = state.db.begin().await?;
+    let person = model.insert(&txn).await?;
+    let change = Change::created(AuditEntity::Person, person.id, &person.name);
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    Ok(Redirect::to("/people"))
+}
+
+pub async fn create_household(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Form(form): Form<NewHouseholdForm>,
+) -> Result<Redirect, AppError> {
+    let name = non_empty(&form.name)
+        .ok_or_else(|| AppError::BadRequest("Name is required".to_string()))?;
+
+    let signed_up = form.sign_up.is_some();
+    let now = Utc::now().naive_utc();
+    let model = household::ActiveModel {
+        organization_id: Set(user.organization_id),
+        pco_id: Set(None),
+        name: Set(name),
+        is_signed_up: Set(signed_up),
+        can_host: Set(form.can_host.is_some()),
+        avatar_url: Set(None),
+        signed_up_at: Set(signed_up.then_some(now)),
+        source: Set(RecordSource::Manual),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    let txn = state.db.begin().await?;
+    let household = model.insert(&txn).await?;
+    let change = Change::created(AuditEntity::Household, household.id, &household.name);
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    Ok(Redirect::to("/people"))
+}
+
+/// A fresh Planning Center token for the signed-in user.
+async fn pco_token(state: &AppState, user: &mut user::Model) -> Result<String, AppError> {
+    Ok(ensure_valid_access_token(user, &state.db, &state.client, &state.token_keys).await?)
+}
+
+async fn find_person(
+    state: &AppState,
+    organization_id: i32,
+    id: i32,
+) -> Result<person::Model, AppError> {
+    person::Entity::find_by_id(id)
+        .filter(person::Column::OrganizationId.eq(organization_id))
         .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+async fn find_household(
+    state: &AppState,
+    organization_id: i32,
+    id: i32,
+) -> Result<household::Model, AppError> {
+    household::Entity::find_by_id(id)
+        .filter(household::Column::OrganizationId.eq(organization_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+/// Searches Planning Center for the record a local person or household
+/// should be linked to.
+async fn render_link(
+    state: &AppState,
+    user: &mut user::Model,
+    kind: &str,
+    id: i32,
+    record_name: &str,
+    name: Option<String>,
+) -> Result<Html<String>, AppError> {
+    let name = name
+        .and_then(|n| non_empty(&n))
+        .unwrap_or_else(|| record_name.to_string());
+    let token = pco_token(state, user).await?;
+    let results = get_people(&token, 1, 25, &PeopleFilter::by_name(Some(name.clone()))).await?;
+
+    let tmpl = state.templates.get_template("people_link.html")?;
+    let html = tmpl.render(context! {
+        kind => kind,
+        id => id,
+        record_name => record_name,
+        name => name,
+        people => results.people,
+        active => "people",
+    })?;
+    Ok(Html(html))
+}
+
+pub async fn link_person_form(
+    State(state): State<AppState>,
+    CurrentUser(mut user): CurrentUser,
+    Path(id): Path<i32>,
+    Query(params): Query<LinkParams>,
+) -> Result<Html<String>, AppError> {
+    let person = find_person(&state, user.organization_id, id).await?;
+    render_link(&state, &mut user, "person", id, &person.name, params.name).await
+}
+
+pub async fn link_household_form(
+    State(state): State<AppState>,
+    CurrentUser(mut user): CurrentUser,
+    Path(id): Path<i32>,
+    Query(params): Query<LinkParams>,
+) -> Result<Html<String>, AppError> {
+    let household = find_household(&state, user.organization_id, id).await?;
+    render_link(
+        &state,
+        &mut user,
+        "household",
+        id,
+        &household.name,
+        params.name,
+    )
+    .await
+}
+
+pub async fn link_person_to_pco(
+    State(state): State<AppState>,
+    CurrentUser(mut user): CurrentUser,
+    Path((id, pco_id)): Path<(i32, String)>,
+) -> Result<Redirect, AppError> {
+    let local = find_person(&state, user.organization_id, id).await?;
+    let token = pco_token(&state, &mut user).await?;
+    let remote = get_person(&token, &pco_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let txn = state.db.begin().await?;
+    let linked = link_person(&txn, local.clone(), remote, Some(user.id))
+        .await
+        .map_err(refused)?;
+    let changes = Change::diff(
+        AuditEntity::Person,
+        linked.id,
+        &linked.name,
+        &local,
+        &linked,
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), changes).await?;
+    txn.commit().await?;
+
+    Ok(Redirect::to("/people"))
+}
+
+/// Links a local household to the Planning Center household of the chosen
+/// person.
+pub async fn link_household_to_pco(
+    State(state): State<AppState>,
+    CurrentUser(mut user): CurrentUser,
+    Path((id, pco_person_id)): Path<(i32, String)>,
+) -> Result<Redirect, AppError> {
+    let local = find_household(&state, user.organization_id, id).await?;
+    let token = pco_token(&state, &mut user).await?;
+    let remote = get_person(&token, &pco_person_id)
+        .await?
+        .and_then(|person| person.household)
+        .ok_or(AppError::NotFound)?;
+
+    let txn = state.db.begin().await?;
+    let linked = link_household(&txn, local.clone(), remote, Some(user.id))
+        .await
+        .map_err(refused)?;
+    let changes = Change::diff(
+        AuditEntity::Household,
+        linked.id,
+        &linked.name,
+        &local,
+        &linked,
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), changes).await?;
+    txn.commit().await?;
+
+    Ok(Redirect::to("/people"))
+}
+
+fn field<T>(
+    label: &'static str,
+    [keep, duplicate, merged]: [&T; 3],
+    value: impl Fn(&T) -> String,
+) -> MergeField {
+    MergeField {
+        label,
+        keep: value(keep),
+        duplicate: value(duplicate),
+        merged: value(merged),
+    }
+}
+
+fn yes_no(value: bool) -> String {
+    if value { "Yes" } else { "No" }.to_string()
+}
+
+fn person_fields(
+    records: [&person::Model; 3],
+    households: &HashMap<i32, String>,
+) -> Vec<MergeField> {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    vec![
+        field("Name", records, |p| p.name.clone()),
+        field("Email", records, |p| text(&p.email)),
+        field("Phone", records, |p| text(&p.phone)),
+        field("Household", records, |p| {
+            p.household_id
+                .and_then(|id| households.get(&id).cloned())
+                .unwrap_or_default()
+        }),
+        field("Planning Center", records, |p| text(&p.pco_id)),
+        field("Signed up", records, |p| yes_no(p.is_signed_up)),
+        field("Can host", records, |p| yes_no(p.can_host)),
+        field("Dietary notes", records, |p| text(&p.dietary_notes)),
+    ]
+}
+
+fn household_fields(records: [&household::Model; 3]) -> Vec<MergeField> {
+    vec![
+        field("Name", records, |h| h.name.clone()),
+        field("Planning Center", records, |h| {
+            h.pco_id.clone().unwrap_or_default()
+        }),
+        field("Signed up", records, |h| yes_no(h.is_signed_up)),
+        field("Can host", records, |h| yes_no(h.can_host)),
+    ]
+}
+
+fn render_merge(
+    state: &AppState,
+    kind: &str,
+    keep: (i32, &str),
+    candidates: Vec<MergeCandidate>,
+    preview: Option<Value>,
+) -> Result<Html<String>, AppError> {
+    let tmpl = state.templates.get_template("people_merge.html")?;
+    let html = tmpl.render(context! {
+        kind => kind,
+        id => keep.0,
+        record_name => keep.1,
+        candidates => candidates,
+        preview => preview,
+        active => "people",
+    })?;
+    Ok(Html(html))
+}
+
+/// Picks a duplicate to merge into a person and previews the result.
+pub async fn merge_person_form(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Query(params): Query<MergeParams>,
+) -> Result<Html<String>, AppError> {
+    let keep = find_person(&state, user.organization_id, id).await?;
+    let candidates = person::Entity::find()
+        .filter(person::Column::OrganizationId.eq(user.organization_id))
+        .filter(person::Column::Id.ne(id))
+        .order_by_asc(person::Column::Name)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|p| MergeCandidate {
+            id: p.id,
+            name: p.name,
+            detail: p.email.or(p.phone),
+        })
+        .collect();
+
+    let preview = match params.with {
+        Some(duplicate_id) => {
+            let duplicate = find_person(&state, user.organization_id, duplicate_id).await?;
+            let (merged, counts) = preview_people(&state.db, keep.clone(), duplicate.clone())
+                .await
+                .map_err(refused)?;
+            let households: HashMap<i32, String> = household::Entity::find()
+                .filter(
+                    household::Column::Id.is_in(
+                        [keep.household_id, duplicate.household_id]
+                            .into_iter()
+                            .flatten(),
+                    ),
+                )
+                .all(&state.db)
+                .await?
+                .into_iter()
+                .map(|h| (h.id, h.name))
+                .collect();
+            Some(context! {
+                id => duplicate.id,
+                name => duplicate.name,
+                fields => person_fields([&keep, &duplicate, &merged], &households),
+                moved => counts.describe(),
+            })
+        }
+        None => None,
+    };
+
+    render_merge(&state, "person", (keep.id, &keep.name), candidates, preview)
+}
+
+/// Picks a duplicate to merge into a household and previews the result.
+pub async fn merge_household_form(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Query(params): Query<MergeParams>,
+) -> Result<Html<String>, AppError> {
+    let keep = find_household(&state, user.organization_id, id).await?;
+    let candidates = household::Entity::find()
+        .filter(household::Column::OrganizationId.eq(user.organization_id))
+        .filter(household::Column::Id.ne(id))
+        .order_by_asc(household::Column::Name)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|h| MergeCandidate {
+            id: h.id,
+            name: h.name,
+            detail: h.pco_id.is_none().then(|| "Not in PCO".to_string()),
+        })
+        .collect();
+
+    let preview = match params.with {
+        Some(duplicate_id) => {
+            let duplicate = find_household(&state, user.organization_id, duplicate_id).await?;
+            let (merged, counts) = preview_households(&state.db, keep.clone(), duplicate.clone())
+                .await
+                .map_err(refused)?;
+            Some(context! {
+                id => duplicate.id,
+                name => duplicate.name,
+                fields => household_fields([&keep, &duplicate, &merged]),
+                moved => counts.describe(),
+            })
+        }
+        None => None,
+    };
+
+    render_merge(
+        &state,
+        "household",
+        (keep.id, &keep.name),
+        candidates,
+        preview,
+    )
+}
+
+pub async fn merge_person(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, duplicate_id)): Path<(i32, i32)>,
+) -> Result<Redirect, AppError> {
+    let keep = find_person(&state, user.organization_id, id).await?;
+    let duplicate = find_person(&state, user.organization_id, duplicate_id).await?;
+    let txn = state.db.begin().await?;
+    merge_people(&txn, keep, duplicate, Some(user.id))
+        .await
+        .map_err(refused)?;
+    txn.commit().await?;
+    Ok(Redirect::to("/people/merges"))
+}
+
+pub async fn merge_household(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, duplicate_id)): Path<(i32, i32)>,
+) -> Result<Redirect, AppError> {
+    let keep = find_household(&state, user.organization_id, id).await?;
+    let duplicate = find_household(&state, user.organization_id, duplicate_id).await?;
+    let txn = state.db.begin().await?;
+    merge_households(&txn, keep, duplicate, Some(user.id))
         .await
-        .unwrap()
-        .unwrap()
-        .into();
+        .map_err(refused)?;
+    txn.commit().await?;
+    Ok(Redirect::to("/people/merges"))
+}
 
-    person.can_host = Set(!person.can_host.clone().unwrap());
-    person.update(&state.db).await.unwrap();
+/// Every merge in the organization, newest first.
+pub async fn merge_history(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Html<String>, AppError> {
+    let records = merge_record::Entity::find()
+        .filter(merge_record::Column::OrganizationId.eq(user.organization_id))
+        .order_by_desc(merge_record::Column::CreatedAt)
+        .all(&state.db)
+        .await?;
 
-    render_people_list(state, params.tab.unwrap_or_else(|| "active".to_string())).await
-}
\ No newline at end of file
+    let names: HashMap<i32, String> = user::Entity::find()
+        .filter(user::Column::Id.is_in(records.iter().filter_map(|r| r.user_id)))
+        .find_also_related(person::Entity)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .filter_map(|(u, p)| Some((u.id, p?.name)))
+        .collect();
+
+    let entries: Vec<MergeEntry> = records
+        .into_iter()
+        .map(|record| MergeEntry {
+            merged_at: record.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            merged_by: record.user_id.and_then(|id| names.get(&id).cloned()),
+            moved: serde_json::from_value::<MergeCounts>(record.counts.clone())
+                .unwrap_or_default()
+                .describe(),
+            record,
+        })
+        .collect();
+
+    let tmpl = state.templates.get_template("people_merges.html")?;
+    let html = tmpl.render(context! {
+        entries => entries,
+        active => "people",
+    })?;
+    Ok(Html(html))
+}
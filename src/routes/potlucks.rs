@@ -0,0 +1,371 @@
+use axum::{
+    Form, Router,
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{Html, IntoResponse, Response},
+    routing::{get, post},
+};
+use chrono::Utc;
+use minijinja::context;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DbErr, EntityTrait, IntoActiveModel, QueryFilter, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{self, AuditEntity, Change, potluck_name},
+    auth::guard::CurrentUser,
+    entities::{potluck, potluck_series},
+    error::AppError,
+    packet::{self, HostPacket},
+    planning::{
+        cancellation,
+        groups::{Group, load_group, load_round},
+        pairing::load_pairing_counts,
+        rebalance::Placement,
+    },
+    router::AppState,
+};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/{id}", get(show_potluck))
+        .route("/{id}/packet", get(show_packet))
+        .route("/{id}/attendance/{attendance_id}/dish", post(assign_dish))
+        .route(
+            "/{id}/attendance/{attendance_id}/cancel",
+            post(cancel_attendance),
+        )
+        .route("/{id}/host/cancel", post(cancel_host))
+        .route("/{id}/host/{attendance_id}", post(replace_host))
+        .route("/{id}/cancel", post(cancel_potluck))
+}
+
+#[derive(Deserialize)]
+pub struct DishForm {
+    dish: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PacketFormat {
+    #[default]
+    Html,
+    Pdf,
+}
+
+#[derive(Deserialize)]
+pub struct PacketParams {
+    #[serde(default)]
+    pub format: PacketFormat,
+}
+
+/// A rebalancing outcome resolved to names for display.
+#[derive(Serialize)]
+struct MoveSummary {
+    name: String,
+    host: Option<String>,
+}
+
+async fn find_potluck(
+    state: &AppState,
+    organization_id: i32,
+    id: i32,
+) -> Result<Option<(potluck::Model, potluck_series::Model)>, DbErr> {
+    let found = potluck::Entity::find_by_id(id)
+        .filter(potluck::Column::OrganizationId.eq(organization_id))
+        .find_also_related(potluck_series::Entity)
+        .one(&state.db)
+        .await?;
+
+    Ok(found.and_then(|(potluck, series)| Some((potluck, series?))))
+}
+
+fn render_group(
+    state: &AppState,
+    template: &str,
+    group: &Group,
+    series: &potluck_series::Model,
+    moves: Vec<MoveSummary>,
+) -> Result<Html<String>, AppError> {
+    let tmpl = state.templates.get_template(template)?;
+
+    let html = tmpl.render(context! {
+        group => group,
+        series => series,
+        headcount => group.headcount(),
+        capacity => series.group_size,
+        host_candidates => group.host_candidates(),
+        moves => moves,
+    })?;
+
+    Ok(Html(html))
+}
+
+async fn reload_and_render(
+    state: &AppState,
+    organization_id: i32,
+    id: i32,
+    moves: Vec<MoveSummary>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, series) = find_potluck(state, organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let group = load_group(&state.db, potluck).await?;
+
+    render_group(state, "_potluck_group.html", &group, &series, moves)
+}
+
+pub async fn show_potluck(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, series) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let group = load_group(&state.db, potluck).await?;
+
+    render_group(&state, "potluck.html", &group, &series, Vec::new())
+}
+
+pub async fn cancel_attendance(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, attendance_id)): Path<(i32, i32)>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, _) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let txn = state.db.begin().await?;
+    let group = load_group(&txn, potluck).await?;
+    cancellation::cancel_attendance(&txn, &group, attendance_id).await?;
+    if let Some(attendee) = group.find_attendee(attendance_id) {
+        let change = Change::updated(
+            AuditEntity::Attendance,
+            attendance_id,
+            &attendee.participant.name,
+            "cancelled",
+            Some("false".to_string()),
+            Some("true".to_string()),
+        );
+        audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    }
+    txn.commit().await?;
+    reload_and_render(&state, user.organization_id, id, Vec::new()).await
+}
+
+pub async fn cancel_host(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, _) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let txn = state.db.begin().await?;
+    let group = load_group(&txn, potluck).await?;
+    cancellation::cancel_host(&txn, &group).await?;
+    let name = potluck_name(&txn, &group.potluck).await?;
+    let change = Change::updated(
+        AuditEntity::Potluck,
+        id,
+        &name,
+        "host_cancelled",
+        Some("false".to_string()),
+        Some("true".to_string()),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+    reload_and_render(&state, user.organization_id, id, Vec::new()).await
+}
+
+pub async fn replace_host(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, attendance_id)): Path<(i32, i32)>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, _) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let txn = state.db.begin().await?;
+    let group = load_group(&txn, potluck).await?;
+    if cancellation::host_is_seated(&group) {
+        return Err(AppError::Conflict(
+            "Only a host who has cancelled can be replaced".to_string(),
+        ));
+    }
+    cancellation::replace_host(&txn, &group, attendance_id).await?;
+    let name = potluck_name(&txn, &group.potluck).await?;
+    let change = Change::updated(
+        AuditEntity::Potluck,
+        id,
+        &name,
+        "host",
+        group.host.as_ref().map(|h| h.name.clone()),
+        group
+            .find_attendee(attendance_id)
+            .map(|a| a.participant.name.clone()),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+    reload_and_render(&state, user.organization_id, id, Vec::new()).await
+}
+
+pub async fn cancel_potluck(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, series) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    if potluck.cancelled_at.is_some() {
+        return Err(AppError::Conflict(
+            "This potluck is already cancelled".to_string(),
+        ));
+    }
+
+    let txn = state.db.begin().await?;
+    let round = load_round(&txn, series.id, potluck.round).await?;
+    let group = round
+        .iter()
+        .find(|g| g.potluck.id == id)
+        .ok_or(AppError::NotFound)?;
+    let pairings = load_pairing_counts(&txn, user.organization_id).await?;
+    let capacity = series.group_size.max(0) as usize;
+    let placements = cancellation::cancel_potluck(&txn, group, &round, capacity, &pairings).await?;
+    let name = potluck_name(&txn, &group.potluck).await?;
+    let change = Change::updated(
+        AuditEntity::Potluck,
+        id,
+        &name,
+        "cancelled",
+        Some("false".to_string()),
+        Some("true".to_string()),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    let moves = summarize_moves(group, &round, &placements);
+    reload_and_render(&state, user.organization_id, id, moves).await
+}
+
+fn summarize_moves(group: &Group, round: &[Group], placements: &[Placement]) -> Vec<MoveSummary> {
+    placements
+        .iter()
+        .filter_map(|placement| {
+            let participant = group
+                .active_attendees()
+                .map(|a| &a.participant)
+                .chain(&group.host)
+                .find(|p| p.id == placement.participant)?;
+            let host = placement
+                .potluck_id
+                .and_then(|id| round.iter().find(|g| g.potluck.id == id))
+                .map(|g| {
+                    g.host
+                        .as_ref()
+                        .map_or_else(|| format!("Potluck #{}", g.potluck.id), |h| h.name.clone())
+                });
+            Some(MoveSummary {
+                name: participant.name.clone(),
+                host,
+            })
+        })
+        .collect()
+}
+
+pub async fn assign_dish(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, attendance_id)): Path<(i32, i32)>,
+    Form(form): Form<DishForm>,
+) -> Result<Html<String>, AppError> {
+    let (potluck, _) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let group = load_group(&state.db, potluck).await?;
+    let attendee = group
+        .find_attendee(attendance_id)
+        .ok_or(AppError::NotFound)?;
+
+    let dish = form.dish.trim();
+    let dish = (!dish.is_empty()).then(|| dish.to_string());
+    let txn = state.db.begin().await?;
+    let mut row = attendee.attendance.clone().into_active_model();
+    row.dish = Set(dish.clone());
+    row.updated_at = Set(Utc::now().naive_utc());
+    row.update(&txn).await?;
+    let change = Change::updated(
+        AuditEntity::Attendance,
+        attendance_id,
+        &attendee.participant.name,
+        "dish",
+        attendee.attendance.dish.clone(),
+        dish,
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+    reload_and_render(&state, user.organization_id, id, Vec::new()).await
+}
+
+/// Renders host packets as a print-ready page, or as a PDF download.
+pub(crate) fn packet_response(
+    state: &AppState,
+    title: &str,
+    back_url: &str,
+    filename: &str,
+    format: PacketFormat,
+    packets: &[HostPacket],
+) -> Result<Response, AppError> {
+    match format {
+        PacketFormat::Html => {
+            let tmpl = state.templates.get_template("host_packets.html")?;
+            let html = tmpl.render(context! {
+                title => title,
+                back_url => back_url,
+                packets => packets,
+            })?;
+            Ok(Html(html).into_response())
+        }
+        PacketFormat::Pdf => {
+            let body = packet::render_pdf(title, packets)
+                .map_err(|e| anyhow::anyhow!("Host packet PDF failed: {e:?}"))?;
+            Ok((
+                [
+                    (CONTENT_TYPE, "application/pdf".to_string()),
+                    (
+                        CONTENT_DISPOSITION,
+                        format!("inline; filename=\"{filename}.pdf\""),
+                    ),
+                ],
+                body,
+            )
+                .into_response())
+        }
+    }
+}
+
+pub async fn show_packet(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Query(params): Query<PacketParams>,
+) -> Result<Response, AppError> {
+    let (potluck, series) = find_potluck(&state, user.organization_id, id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+    let packet = packet::load_packet(&state.db, &series, potluck).await?;
+
+    let title = format!("{} - Round {} host packet", series.name, packet.round);
+    packet_response(
+        &state,
+        &title,
+        &format!("/potlucks/{id}"),
+        &format!("potluck-{id}-host-packet"),
+        params.format,
+        &[packet],
+    )
+}
@@ -1,57 +1,55 @@
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse},
+    response::{Html, IntoResponse, Response},
 };
+use axum_extra::extract::Form;
 use minijinja::context;
-use sea_orm::{
-    ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait,
-};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
 use serde::Deserialize;
-
-use crate::{auth::user::AuthSession, router::AppState};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::{
+    audit,
+    auth::guard::CurrentUser,
+    enroll::{self, Target},
+    error::AppError,
+    router::AppState,
+};
 
 use crate::auth::user::ensure_valid_access_token;
-use crate::entities::user::Entity as UserEntity;
-use crate::entities::{household, organization, person};
+use crate::entities::{household, person};
+use crate::pco::filter::{PeopleFilter, get_campuses, get_list_categories, get_lists};
 use crate::pco::household::get_household_people;
-use crate::pco::person::{get_people, get_person, PeoplePage};
+use crate::pco::person::{PeoplePage, get_all_people, get_people, get_person};
 
+/// Where the next page of results starts. The search itself comes from
+/// [`PeopleFilter`].
 #[derive(Deserialize)]
 pub struct PeopleQuery {
     pub offset: Option<usize>,
-    pub name: Option<String>,
 }
 
+const PER_PAGE: usize = 25;
+
 async fn get_people_with_signup_status(
     state: &AppState,
     user: &mut crate::entities::user::Model,
     page: usize,
     per_page: usize,
-    name: Option<String>,
-) -> Result<PeoplePage, String> {
-    if ensure_valid_access_token(user, &state.db, &state.client)
-        .await
-        .is_err()
-    {
-        return Err("Invalid token".to_string());
-    }
+    filter: &PeopleFilter,
+) -> Result<PeoplePage, AppError> {
+    let access_token =
+        ensure_valid_access_token(user, &state.db, &state.client, &state.token_keys).await?;
 
-    let mut people_page = get_people(&user.access_token, page, per_page, name.clone())
-        .await
-        .map_err(|e| e.to_string())?;
+    let mut people_page = get_people(&access_token, page, per_page, filter).await?;
 
-    let pco_ids: Vec<String> = people_page
-        .people
-        .iter()
-        .map(|p| p.id.clone())
-        .collect();
+    let pco_ids: Vec<String> = people_page.people.iter().map(|p| p.id.clone()).collect();
 
     let persons_in_db = person::Entity::find()
         .filter(person::Column::PcoId.is_in(pco_ids))
         .all(&state.db)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     let household_ids: Vec<String> = people_page
         .people
@@ -62,503 +60,237 @@ async fn get_people_with_signup_status(
     let households_in_db = household::Entity::find()
         .filter(household::Column::PcoId.is_in(household_ids))
         .all(&state.db)
-        .await
-        .map_err(|e| e.to_string())?;
+        .await?;
 
     for person_data in &mut people_page.people {
-        if let Some(person_in_db) = persons_in_db.iter().find(|p| p.pco_id == person_data.id) {
+        if let Some(person_in_db) = persons_in_db
+            .iter()
+            .find(|p| p.pco_id.as_ref() == Some(&person_data.id))
+        {
             person_data.is_signed_up = person_in_db.is_signed_up;
         }
-        if let Some(household_info) = &mut person_data.household {
-            if let Some(household_in_db) = households_in_db
+        if let Some(household_info) = &mut person_data.household
+            && let Some(household_in_db) = households_in_db
                 .iter()
-                .find(|h| h.pco_id == household_info.id)
-            {
-                household_info.is_signed_up = Some(household_in_db.is_signed_up);
-            }
+                .find(|h| h.pco_id.as_ref() == Some(&household_info.id))
+        {
+            household_info.is_signed_up = Some(household_in_db.is_signed_up);
         }
     }
 
     Ok(people_page)
 }
 
-pub async fn search(State(state): State<AppState>, auth_session: AuthSession) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(u) => u.id,
-        None => return (StatusCode::UNAUTHORIZED, "No user session").into_response(),
-    };
-    // Fetch user from DB
-    let mut user = match UserEntity::find_by_id(user_id).one(&state.db).await {
-        Ok(Some(u)) => u,
-        _ => return (StatusCode::UNAUTHORIZED, "User not found").into_response(),
-    };
+/// Renders the page of search results `query` asks for.
+async fn render_rows(
+    state: &AppState,
+    user: &mut crate::entities::user::Model,
+    query: &PeopleQuery,
+    filter: &PeopleFilter,
+) -> Result<Html<String>, AppError> {
+    let offset = query.offset.unwrap_or(0);
+    let page = offset / PER_PAGE + 1;
+    let people_page = get_people_with_signup_status(state, user, page, PER_PAGE, filter).await?;
+
+    let has_more = offset + people_page.count < people_page.total_count;
+    let next_offset = offset + PER_PAGE;
+    let tmpl = state.templates.get_template("search_table_rows.html")?;
+    let html = tmpl.render(context! {
+        people => people_page.people,
+        has_more => has_more,
+        next_offset => next_offset,
+        first_page => offset == 0,
+        total_count => people_page.total_count,
+        filter => filter,
+    })?;
+    Ok(Html(html))
+}
 
-    let per_page = 25;
+pub async fn search(
+    State(state): State<AppState>,
+    CurrentUser(mut user): CurrentUser,
+    Query(filter): Query<PeopleFilter>,
+) -> Result<Html<String>, AppError> {
+    let access_token =
+        ensure_valid_access_token(&mut user, &state.db, &state.client, &state.token_keys).await?;
+    let campuses = get_campuses(&access_token).await?;
+    let lists = get_lists(&access_token).await?;
+    let categories = get_list_categories(&access_token).await?;
     let people_page =
-        match get_people_with_signup_status(&state, &mut user, 1, per_page, None).await {
-            Ok(page) => page,
-            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
-        };
+        get_people_with_signup_status(&state, &mut user, 1, PER_PAGE, &filter).await?;
 
     let has_more = people_page.count < people_page.total_count;
-    let next_offset = per_page;
-    let tmpl = state.templates.get_template("search.html").unwrap();
-    let html = tmpl
-        .render(context! {
-            people => people_page.people,
-            has_more => has_more,
-            next_offset => next_offset,
-            name => "",
-        })
-        .unwrap();
-    Html(html).into_response()
+    let next_offset = PER_PAGE;
+    let tmpl = state.templates.get_template("search.html")?;
+    let html = tmpl.render(context! {
+        people => people_page.people,
+        has_more => has_more,
+        next_offset => next_offset,
+        first_page => true,
+        total_count => people_page.total_count,
+        filter => filter,
+        campuses => campuses,
+        lists => lists,
+        categories => categories,
+    })?;
+    Ok(Html(html))
 }
 
 pub async fn search_partial(
     State(state): State<AppState>,
-    auth_session: AuthSession,
+    CurrentUser(mut user): CurrentUser,
     Query(query): Query<PeopleQuery>,
-) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(u) => u.id,
-        None => return (StatusCode::UNAUTHORIZED, "No user session").into_response(),
-    };
-    let mut user = match UserEntity::find_by_id(user_id).one(&state.db).await {
-        Ok(Some(u)) => u,
-        _ => return (StatusCode::UNAUTHORIZED, "User not found").into_response(),
-    };
-
-    let offset = query.offset.unwrap_or(0);
-    let per_page = 25;
-    let page = offset / per_page + 1;
-
-    let people_page =
-        match get_people_with_signup_status(&state, &mut user, page, per_page, query.name.clone())
-            .await
-        {
-            Ok(page) => page,
-            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
-        };
-
-    let has_more = offset + people_page.count < people_page.total_count;
-    let next_offset = offset + per_page;
-    let tmpl = state
-        .templates
-        .get_template("search_table_rows.html")
-        .unwrap();
-    let html = tmpl
-        .render(context! {
-            people => people_page.people,
-            has_more => has_more,
-            next_offset => next_offset,
-            name => query.name.clone().unwrap_or_default(),
-        })
-        .unwrap();
-    Html(html).into_response()
+    Query(filter): Query<PeopleFilter>,
+) -> Result<Html<String>, AppError> {
+    render_rows(&state, &mut user, &query, &filter).await
 }
 
 pub async fn sign_up_household(
     State(state): State<AppState>,
-    auth_session: AuthSession,
+    CurrentUser(mut user): CurrentUser,
     Path(household_id): Path<String>,
     Query(query): Query<PeopleQuery>,
-) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(u) => u.id,
-        None => return (StatusCode::UNAUTHORIZED, "No user session").into_response(),
-    };
-    let mut user = match UserEntity::find_by_id(user_id).one(&state.db).await {
-        Ok(Some(u)) => u,
-        _ => return (StatusCode::UNAUTHORIZED, "User not found").into_response(),
-    };
-    if ensure_valid_access_token(&mut user, &state.db, &state.client)
-        .await
-        .is_err()
-    {
-        return (StatusCode::UNAUTHORIZED, "Invalid token").into_response();
-    }
+    Query(filter): Query<PeopleFilter>,
+) -> Result<Html<String>, AppError> {
+    let access_token =
+        ensure_valid_access_token(&mut user, &state.db, &state.client, &state.token_keys).await?;
 
     let organization_id = user.organization_id;
 
-    let household_info = match get_household_people(&user.access_token, &household_id).await {
-        Ok(Some(household_info)) => household_info,
-        _ => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to get household data from PCO",
-            )
-                .into_response();
-        }
-    };
-
-    let txn = match state.db.begin().await {
-        Ok(txn) => txn,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to begin transaction",
-            )
-                .into_response();
-        }
-    };
-
-    let household_model = match household::Entity::find()
-        .filter(household::Column::PcoId.eq(&household_id))
-        .one(&txn)
-        .await
-    {
-        Ok(Some(existing)) => {
-            let mut active_model: household::ActiveModel = existing.into();
-            active_model.name = Set(household_info.name);
-            active_model.avatar_url = Set(household_info.avatar);
-            active_model.is_signed_up = Set(true);
-            match active_model.update(&txn).await {
-                Ok(model) => model,
-                Err(_) => {
-                    let _ = txn.rollback().await;
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to update household",
-                    )
-                        .into_response();
-                }
-            }
-        }
-        Ok(None) => {
-            let new_household = household::ActiveModel {
-                pco_id: Set(household_id),
-                organization_id: Set(organization_id),
-                name: Set(household_info.name),
-                avatar_url: Set(household_info.avatar),
-                is_signed_up: Set(true),
-                can_host: Set(false),
-                ..Default::default()
-            };
-            match new_household.insert(&txn).await {
-                Ok(model) => model,
-                Err(_) => {
-                    let _ = txn.rollback().await;
-                    return (
-                        StatusCode::INTERNAL_SERVER_ERROR,
-                        "Failed to insert household",
-                    )
-                        .into_response();
-                }
-            }
-        }
-        Err(_) => {
-            let _ = txn.rollback().await;
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to query household",
-            )
-                .into_response();
-        }
-    };
+    let household_info = get_household_people(&access_token, &household_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
 
-    if let Some(people) = household_info.people {
-        for pco_person in people {
-            let existing_person = match person::Entity::find()
-                .filter(person::Column::PcoId.eq(&pco_person.id))
-                .one(&txn)
-                .await
-            {
-                Ok(person) => person,
-                Err(_) => {
-                    let _ = txn.rollback().await;
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query person")
-                        .into_response();
-                }
-            };
-
-            if let Some(existing) = existing_person {
-                let mut active_model: person::ActiveModel = existing.into();
-                active_model.name = Set(pco_person.name);
-                active_model.email = Set(pco_person.email);
-                active_model.phone = Set(pco_person.phone);
-                active_model.address = Set(pco_person.address.unwrap_or_default());
-                active_model.avatar_url = Set(pco_person.avatar);
-                active_model.is_child = Set(pco_person.is_child);
-                active_model.household_id = Set(Some(household_model.id));
-                if active_model.update(&txn).await.is_err() {
-                    let _ = txn.rollback().await;
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update person")
-                        .into_response();
-                }
-            } else {
-                let new_person = person::ActiveModel {
-                    pco_id: Set(pco_person.id),
-                    organization_id: Set(organization_id),
-                    name: Set(pco_person.name),
-                    email: Set(pco_person.email),
-                    phone: Set(pco_person.phone),
-                    address: Set(pco_person.address.unwrap_or_default()),
-                    avatar_url: Set(pco_person.avatar),
-                    is_signed_up: Set(false),
-                    can_host: Set(false),
-                    is_child: Set(pco_person.is_child),
-                    household_id: Set(Some(household_model.id)),
-                    ..Default::default()
-                };
-                if new_person.insert(&txn).await.is_err() {
-                    let _ = txn.rollback().await;
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to insert person")
-                        .into_response();
-                }
-            }
-        }
-    }
+    let txn = state.db.begin().await?;
+    let changes =
+        enroll::sign_up_household(&txn, organization_id, household_id, household_info).await?;
+    audit::record(&txn, organization_id, Some(user.id), changes).await?;
+    txn.commit().await?;
 
-    match txn.commit().await {
-        Ok(_) => {
-            let offset = query.offset.unwrap_or(0);
-            let per_page = 25;
-            let page = offset / per_page + 1;
-            let mut user = match UserEntity::find_by_id(user.id).one(&state.db).await {
-                Ok(Some(u)) => u,
-                _ => return (StatusCode::UNAUTHORIZED, "User not found").into_response(),
-            };
-            let people_page = match get_people_with_signup_status(
-                &state,
-                &mut user,
-                page,
-                per_page,
-                query.name.clone(),
-            )
-            .await
-            {
-                Ok(page) => page,
-                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
-            };
-
-            let has_more = offset + people_page.count < people_page.total_count;
-            let next_offset = offset + per_page;
-            let tmpl = state
-                .templates
-                .get_template("search_table_rows.html")
-                .unwrap();
-            let html = tmpl
-                .render(context! {
-                    people => people_page.people,
-                    has_more => has_more,
-                    next_offset => next_offset,
-                    name => query.name.clone().unwrap_or_default(),
-                })
-                .unwrap();
-            Html(html).into_response()
-        }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to commit transaction",
-        )
-            .into_response(),
-    }
+    render_rows(&state, &mut user, &query, &filter).await
 }
 
 pub async fn sign_up_person(
     State(state): State<AppState>,
-    auth_session: AuthSession,
+    CurrentUser(mut user): CurrentUser,
     Path(person_id): Path<String>,
     Query(query): Query<PeopleQuery>,
-) -> impl IntoResponse {
-    let user_id = match auth_session.user {
-        Some(u) => u.id,
-        None => return (StatusCode::UNAUTHORIZED, "No user session").into_response(),
-    };
-    let mut user = match UserEntity::find_by_id(user_id).one(&state.db).await {
-        Ok(Some(u)) => u,
-        _ => return (StatusCode::UNAUTHORIZED, "User not found").into_response(),
-    };
-    if ensure_valid_access_token(&mut user, &state.db, &state.client)
-        .await
-        .is_err()
-    {
-        return (StatusCode::UNAUTHORIZED, "Invalid token").into_response();
-    }
+    Query(filter): Query<PeopleFilter>,
+) -> Result<Html<String>, AppError> {
+    let access_token =
+        ensure_valid_access_token(&mut user, &state.db, &state.client, &state.token_keys).await?;
+
+    let person_data = get_person(&access_token, &person_id)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let txn = state.db.begin().await?;
+    let (organization_id, changes) =
+        enroll::sign_up_person(&txn, user.organization_id, person_data).await?;
+    audit::record(&txn, organization_id, Some(user.id), changes).await?;
+    txn.commit().await?;
+
+    render_rows(&state, &mut user, &query, &filter).await
+}
 
-    let organization_id = user.organization_id;
+/// Who to sign up in bulk, by Planning Center id.
+#[derive(Deserialize)]
+pub struct SignUpForm {
+    #[serde(default)]
+    person: Vec<String>,
+    #[serde(default)]
+    household: Vec<String>,
+}
 
-    let person_data = match get_person(&user.access_token, &person_id).await {
-        Ok(Some(person_data)) => person_data,
-        _ => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to get person data from PCO",
-            )
-                .into_response();
-        }
-    };
+/// Renders how far a bulk sign-up has got. Once it has finished, the
+/// `sign-up-finished` event tells the page to refresh its results.
+fn render_progress(
+    state: &AppState,
+    job_id: Uuid,
+    user: &crate::entities::user::Model,
+) -> Result<Response, AppError> {
+    let job = state
+        .jobs
+        .progress(job_id, user.organization_id)
+        .ok_or(AppError::NotFound)?;
+    let tmpl = state.templates.get_template("_sign_up_progress.html")?;
+    let html = tmpl.render(context! { job_id, job => &job })?;
+    if job.finished {
+        Ok(([("HX-Trigger", "sign-up-finished")], Html(html)).into_response())
+    } else {
+        Ok(Html(html).into_response())
+    }
+}
 
-    let txn = match state.db.begin().await {
-        Ok(txn) => txn,
-        Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to begin transaction",
-            )
-                .into_response();
-        }
-    };
+fn start_job(
+    state: &AppState,
+    user: crate::entities::user::Model,
+    targets: Vec<Target>,
+) -> Result<Response, AppError> {
+    if targets.is_empty() {
+        return Err(AppError::BadRequest(
+            "Choose someone to sign up".to_string(),
+        ));
+    }
+    let job_id = state.jobs.start(
+        state.db.clone(),
+        state.client.clone(),
+        state.token_keys.clone(),
+        user.clone(),
+        targets,
+    );
+    render_progress(state, job_id, &user)
+}
 
-    // Handle Organization
-    let organization_model = if let Some(org_info) = person_data.organization.clone() {
-        match organization::Entity::find()
-            .filter(organization::Column::PcoId.eq(&org_info.id))
-            .one(&txn)
-            .await
-        {
-            Ok(Some(existing)) => {
-                let mut active_model: organization::ActiveModel = existing.into();
-                active_model.name = Set(org_info.name);
-                active_model.avatar_url = Set(org_info.avatar_url);
-                match active_model.update(&txn).await {
-                    Ok(model) => model,
-                    Err(_) => {
-                        let _ = txn.rollback().await;
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            "Failed to update organization",
-                        )
-                            .into_response();
-                    }
-                }
-            }
-            Ok(None) => {
-                let new_org = organization::ActiveModel {
-                    pco_id: Set(org_info.id),
-                    name: Set(org_info.name),
-                    avatar_url: Set(org_info.avatar_url),
-                    ..Default::default()
-                };
-                match new_org.insert(&txn).await {
-                    Ok(model) => model,
-                    Err(_) => {
-                        let _ = txn.rollback().await;
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            "Failed to insert organization",
-                        )
-                            .into_response();
-                    }
-                }
-            }
-            Err(_) => {
-                let _ = txn.rollback().await;
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to query organization",
-                )
-                    .into_response();
-            }
-        }
-    } else {
-        // If no organization data from PCO, use the user's organization
-        match organization::Entity::find_by_id(organization_id)
-            .one(&txn)
-            .await
-        {
-            Ok(Some(org)) => org,
-            _ => {
-                let _ = txn.rollback().await;
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Organization not found for user",
-                )
-                    .into_response();
-            }
-        }
-    };
+/// Starts signing up the chosen people and households in the background.
+pub async fn sign_up_many(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Form(form): Form<SignUpForm>,
+) -> Result<Response, AppError> {
+    let mut targets: Vec<Target> = form
+        .household
+        .into_iter()
+        .map(Target::Household)
+        .chain(form.person.into_iter().map(Target::Person))
+        .collect();
+    // Relatives share a household, so it can be chosen more than once.
+    let mut seen = HashSet::new();
+    targets.retain(|target| seen.insert(target.clone()));
+    start_job(&state, user, targets)
+}
 
-    // Handle Person
-    let existing_person = match person::Entity::find()
-        .filter(person::Column::PcoId.eq(&person_data.id))
-        .one(&txn)
-        .await
-    {
-        Ok(person) => person,
-        Err(_) => {
-            let _ = txn.rollback().await;
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query person").into_response();
-        }
+/// Starts signing up everyone on a Planning Center List, so a whole small
+/// group can be enrolled at once.
+pub async fn sign_up_list(
+    State(state): State<AppState>,
+    CurrentUser(mut user): CurrentUser,
+    Path(list_id): Path<String>,
+) -> Result<Response, AppError> {
+    let access_token =
+        ensure_valid_access_token(&mut user, &state.db, &state.client, &state.token_keys).await?;
+
+    let list = PeopleFilter {
+        list: Some(list_id),
+        ..Default::default()
     };
-
-    if let Some(existing) = existing_person {
-        let mut active_model: person::ActiveModel = existing.into();
-        active_model.name = Set(person_data.name);
-        active_model.email = Set(person_data.email);
-        active_model.phone = Set(person_data.phone);
-        active_model.address = Set(person_data.address.unwrap_or_default());
-        active_model.avatar_url = Set(person_data.avatar);
-        active_model.is_child = Set(person_data.is_child);
-        active_model.is_signed_up = Set(true);
-        active_model.organization_id = Set(organization_model.id);
-        if active_model.update(&txn).await.is_err() {
-            let _ = txn.rollback().await;
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update person").into_response();
-        }
-    } else {
-        let new_person = person::ActiveModel {
-            pco_id: Set(person_data.id),
-            organization_id: Set(organization_model.id),
-            name: Set(person_data.name),
-            email: Set(person_data.email),
-            phone: Set(person_data.phone),
-            address: Set(person_data.address.unwrap_or_default()),
-            avatar_url: Set(person_data.avatar),
-            is_signed_up: Set(true),
-            can_host: Set(false),
-            is_child: Set(person_data.is_child),
-            household_id: Set(None),
-            ..Default::default()
-        };
-        if new_person.insert(&txn).await.is_err() {
-            let _ = txn.rollback().await;
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to insert person").into_response();
-        }
+    let targets: Vec<Target> = get_all_people(&access_token, &list)
+        .await?
+        .into_iter()
+        .map(|person| Target::Person(person.id))
+        .collect();
+    if targets.is_empty() {
+        return Err(AppError::BadRequest(
+            "There's nobody active on this List to sign up".to_string(),
+        ));
     }
+    start_job(&state, user, targets)
+}
 
-    match txn.commit().await {
-        Ok(_) => {
-            let offset = query.offset.unwrap_or(0);
-            let per_page = 25;
-            let page = offset / per_page + 1;
-            let mut user = match UserEntity::find_by_id(user.id).one(&state.db).await {
-                Ok(Some(u)) => u,
-                _ => return (StatusCode::UNAUTHORIZED, "User not found").into_response(),
-            };
-            let people_page = match get_people_with_signup_status(
-                &state,
-                &mut user,
-                page,
-                per_page,
-                query.name.clone(),
-            )
-            .await
-            {
-                Ok(page) => page,
-                Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
-            };
-
-            let has_more = offset + people_page.count < people_page.total_count;
-            let next_offset = offset + per_page;
-            let tmpl = state
-                .templates
-                .get_template("search_table_rows.html")
-                .unwrap();
-            let html = tmpl
-                .render(context! {
-                    people => people_page.people,
-                    has_more => has_more,
-                    next_offset => next_offset,
-                    name => query.name.clone().unwrap_or_default(),
-                })
-                .unwrap();
-            Html(html).into_response()
-        }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to commit transaction",
-        )
-            .into_response(),
-    }
-}
\ No newline at end of file
+pub async fn sign_up_progress(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(job_id): Path<Uuid>,
+) -> Result<Response, AppError> {
+    render_progress(&state, job_id, &user)
+}
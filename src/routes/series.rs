@@ -0,0 +1,678 @@
+use axum::{
+    Form, Router,
+    extract::{Path, Query, State},
+    http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+};
+use axum_extra::extract::Query as MultiQuery;
+use chrono::{NaiveDate, Utc};
+use minijinja::context;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    audit::{self, AuditEntity, Change, find_participant_name, participant_name},
+    auth::guard::CurrentUser,
+    entities::{potluck_round, potluck_series, series_template, unavailability, waitlist},
+    error::AppError,
+    export::{self, GroupColumn, RosterColumn},
+    packet::load_round_packets,
+    planning::{
+        availability::{Availability, load_availability},
+        generate::{JoinOutcome, RoundOutcome, generate_round, place_late_joiners},
+        groups::{Group, load_round, load_series},
+        participant::{Headcount, Participant, ParticipantId, signed_up_participants},
+        template::{self, SeriesShape, load_shape, sign_up_roster},
+        waitlist::{load_waitlist, waiting_in_round},
+    },
+    router::AppState,
+};
+
+use super::potlucks::{PacketParams, packet_response};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create_series))
+        .route("/new", get(new_series))
+        .route("/templates/{template_id}/delete", post(delete_template))
+        .route("/{id}", get(show_series))
+        .route("/{id}/template", post(save_as_template))
+        .route("/{id}/rounds", post(add_round))
+        .route("/{id}/rounds/{round}/generate", post(generate))
+        .route("/{id}/rounds/{round}/packets", get(round_packets))
+        .route("/{id}/late-joiners", post(add_late_joiners))
+        .route("/{id}/export/roster.csv", get(export_roster))
+        .route("/{id}/export/groups.csv", get(export_groups))
+        .route("/{id}/unavailability", post(add_unavailability))
+        .route(
+            "/{id}/unavailability/{unavailability_id}/delete",
+            post(delete_unavailability),
+        )
+}
+
+#[derive(Serialize)]
+struct GroupSummary<'a> {
+    group: &'a Group,
+    headcount: Headcount,
+}
+
+#[derive(Serialize)]
+struct RoundSummary<'a> {
+    round: i32,
+    scheduled_on: Option<NaiveDate>,
+    groups: Vec<GroupSummary<'a>>,
+    /// Signed-up participants whose blackout dates cover this round.
+    unavailable: Vec<Participant>,
+    waitlist: Vec<Participant>,
+}
+
+/// What the last action on the page did, shown above the rounds.
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum SeriesOutcome {
+    Generated { round: i32, outcome: RoundOutcome },
+    LateJoiners { rounds: Vec<JoinOutcome> },
+}
+
+#[derive(Deserialize)]
+pub struct NewSeriesParams {
+    from: Option<i32>,
+}
+
+/// Creates a series from scratch, from a saved template or as a copy of an
+/// earlier series, depending on which of `template_id` and `series_id` is set.
+#[derive(Deserialize)]
+pub struct NewSeriesForm {
+    name: String,
+    start_date: NaiveDate,
+    end_date: Option<NaiveDate>,
+    group_size: Option<i32>,
+    description: Option<String>,
+    template_id: Option<i32>,
+    series_id: Option<i32>,
+    include_roster: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct TemplateForm {
+    name: String,
+}
+
+#[derive(Deserialize)]
+pub struct RoundForm {
+    scheduled_on: NaiveDate,
+}
+
+#[derive(Deserialize)]
+pub struct UnavailabilityForm {
+    participant: String,
+    starts_on: NaiveDate,
+    ends_on: NaiveDate,
+    note: Option<String>,
+}
+
+fn summarize_rounds<'a>(
+    rounds: &[potluck_round::Model],
+    groups: &'a [Group],
+    participants: &[Participant],
+    availability: &Availability,
+    waitlist: &[waitlist::Model],
+) -> Vec<RoundSummary<'a>> {
+    let mut numbers: Vec<i32> = rounds
+        .iter()
+        .map(|r| r.round)
+        .chain(groups.iter().map(|g| g.potluck.round))
+        .collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    numbers
+        .into_iter()
+        .map(|number| {
+            let scheduled_on = rounds
+                .iter()
+                .find(|r| r.round == number)
+                .map(|r| r.scheduled_on);
+            let unavailable = scheduled_on
+                .map(|date| availability.split(participants.to_vec(), date).1)
+                .unwrap_or_default();
+            RoundSummary {
+                round: number,
+                scheduled_on,
+                groups: groups
+                    .iter()
+                    .filter(|g| g.potluck.round == number)
+                    .map(|group| GroupSummary {
+                        group,
+                        headcount: group.headcount(),
+                    })
+                    .collect(),
+                unavailable,
+                waitlist: waiting_in_round(waitlist, number, participants),
+            }
+        })
+        .collect()
+}
+
+/// How a round is named in the audit log.
+fn round_label(series: &potluck_series::Model, round: i32) -> String {
+    format!("{} round {round}", series.name)
+}
+
+async fn find_series(
+    state: &AppState,
+    organization_id: i32,
+    id: i32,
+) -> Result<potluck_series::Model, AppError> {
+    potluck_series::Entity::find_by_id(id)
+        .filter(potluck_series::Column::OrganizationId.eq(organization_id))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)
+}
+
+async fn render_series(
+    state: &AppState,
+    template: &str,
+    series: &potluck_series::Model,
+    outcome: Option<SeriesOutcome>,
+) -> Result<Html<String>, AppError> {
+    let rounds = potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+        .order_by_asc(potluck_round::Column::Round)
+        .all(&state.db)
+        .await?;
+    let groups = load_series(&state.db, series.id).await?;
+    let participants = signed_up_participants(&state.db, series.organization_id).await?;
+    let availability = load_availability(&state.db, series.id).await?;
+    let unavailability = availability.entries_with_participants(&participants);
+    let waitlist = load_waitlist(&state.db, series.id).await?;
+    let rounds = summarize_rounds(&rounds, &groups, &participants, &availability, &waitlist);
+
+    let roster_columns: Vec<ExportColumn> = RosterColumn::ALL
+        .iter()
+        .map(|c| ExportColumn {
+            key: c.key(),
+            label: c.label(),
+        })
+        .collect();
+    let group_columns: Vec<ExportColumn> = GroupColumn::ALL
+        .iter()
+        .map(|c| ExportColumn {
+            key: c.key(),
+            label: c.label(),
+        })
+        .collect();
+
+    let tmpl = state.templates.get_template(template)?;
+    let html = tmpl.render(context! {
+        series => series,
+        rounds => rounds,
+        participants => participants,
+        unavailability => unavailability,
+        outcome => outcome,
+        roster_columns => roster_columns,
+        group_columns => group_columns,
+    })?;
+    Ok(Html(html))
+}
+
+pub async fn new_series(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Query(params): Query<NewSeriesParams>,
+) -> Result<Html<String>, AppError> {
+    let templates = series_template::Entity::find()
+        .filter(series_template::Column::OrganizationId.eq(user.organization_id))
+        .order_by_asc(series_template::Column::Name)
+        .all(&state.db)
+        .await?;
+    let series = potluck_series::Entity::find()
+        .filter(potluck_series::Column::OrganizationId.eq(user.organization_id))
+        .order_by_desc(potluck_series::Column::StartDate)
+        .all(&state.db)
+        .await?;
+
+    let tmpl = state.templates.get_template("series_new.html")?;
+    let html = tmpl.render(context! {
+        templates => templates,
+        series => series,
+        from => params.from,
+    })?;
+    Ok(Html(html))
+}
+
+pub async fn create_series(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Form(form): Form<NewSeriesForm>,
+) -> Result<Redirect, AppError> {
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Unprocessable("A series needs a name".to_string()));
+    }
+
+    // The series copied from, if any: only its roster can be carried over,
+    // and only once it is known to belong to the organization.
+    let mut copied_from = None;
+    let shape = if let Some(template_id) = form.template_id {
+        let template = series_template::Entity::find_by_id(template_id)
+            .filter(series_template::Column::OrganizationId.eq(user.organization_id))
+            .one(&state.db)
+            .await?
+            .ok_or(AppError::NotFound)?;
+        SeriesShape::from_template(&template)
+    } else if let Some(series_id) = form.series_id {
+        let series = find_series(&state, user.organization_id, series_id).await?;
+        let shape = load_shape(&state.db, &series).await?;
+        copied_from = Some(series);
+        shape
+    } else {
+        let end_date = form
+            .end_date
+            .filter(|end| *end > form.start_date)
+            .ok_or_else(|| {
+                AppError::Unprocessable("The series must end after its first day".to_string())
+            })?;
+        SeriesShape {
+            description: form.description.filter(|d| !d.trim().is_empty()),
+            group_size: form.group_size.unwrap_or(8).max(1),
+            length_days: (end_date - form.start_date).num_days() as i32,
+            round_offsets: Vec::new(),
+        }
+    };
+
+    let txn = state.db.begin().await?;
+    let series =
+        template::create_series(&txn, user.organization_id, name, form.start_date, &shape).await?;
+    if let Some(source) = &copied_from
+        && form.include_roster.is_some()
+    {
+        sign_up_roster(&txn, source.id).await?;
+    }
+    let change = Change::created(AuditEntity::Series, series.id, &series.name);
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    Ok(Redirect::to(&format!("/series/{}", series.id)))
+}
+
+pub async fn save_as_template(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Form(form): Form<TemplateForm>,
+) -> Result<Redirect, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    let name = form.name.trim();
+    if name.is_empty() {
+        return Err(AppError::Unprocessable(
+            "A template needs a name".to_string(),
+        ));
+    }
+
+    let txn = state.db.begin().await?;
+    let template = template::save_template(&txn, &series, name).await?;
+    let change = Change::created(AuditEntity::SeriesTemplate, template.id, &template.name);
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    Ok(Redirect::to("/series/new"))
+}
+
+pub async fn delete_template(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(template_id): Path<i32>,
+) -> Result<Redirect, AppError> {
+    let txn = state.db.begin().await?;
+    let template = series_template::Entity::find_by_id(template_id)
+        .filter(series_template::Column::OrganizationId.eq(user.organization_id))
+        .one(&txn)
+        .await?;
+    if let Some(template) = template {
+        series_template::Entity::delete_by_id(template.id)
+            .exec(&txn)
+            .await?;
+        let change = Change::deleted(AuditEntity::SeriesTemplate, template.id, &template.name);
+        audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    }
+    txn.commit().await?;
+
+    Ok(Redirect::to("/series/new"))
+}
+
+pub async fn show_series(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Html<String>, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    render_series(&state, "series.html", &series, None).await
+}
+
+pub async fn add_round(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Form(form): Form<RoundForm>,
+) -> Result<Html<String>, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    if form.scheduled_on < series.start_date || form.scheduled_on > series.end_date {
+        return Err(AppError::Unprocessable(
+            "Round date must fall within the series".to_string(),
+        ));
+    }
+
+    let next = potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+        .order_by_desc(potluck_round::Column::Round)
+        .one(&state.db)
+        .await?
+        .map_or(1, |r| r.round + 1);
+
+    let round = potluck_round::ActiveModel {
+        organization_id: Set(series.organization_id),
+        potluck_series_id: Set(series.id),
+        round: Set(next),
+        scheduled_on: Set(form.scheduled_on),
+        ..Default::default()
+    };
+    let txn = state.db.begin().await?;
+    let round = round.insert(&txn).await?;
+    let change = Change::created(
+        AuditEntity::Round,
+        round.id,
+        &round_label(&series, round.round),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    render_series(&state, "_series_rounds.html", &series, None).await
+}
+
+pub async fn generate(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, round)): Path<(i32, i32)>,
+) -> Result<Html<String>, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    let round = potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+        .filter(potluck_round::Column::Round.eq(round))
+        .one(&state.db)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+    let txn = state.db.begin().await?;
+    let outcome = generate_round(&txn, &series, &round).await?;
+    let change = Change::updated(
+        AuditEntity::Round,
+        round.id,
+        &round_label(&series, round.round),
+        "groups",
+        None,
+        Some(format!(
+            "{} groups, {} waitlisted",
+            outcome.plan.groups.len(),
+            outcome.plan.waitlist.len()
+        )),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    render_series(
+        &state,
+        "_series_rounds.html",
+        &series,
+        Some(SeriesOutcome::Generated {
+            round: round.round,
+            outcome,
+        }),
+    )
+    .await
+}
+
+pub async fn add_late_joiners(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Html<String>, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    let txn = state.db.begin().await?;
+    let upcoming = potluck_round::Entity::find()
+        .filter(potluck_round::Column::PotluckSeriesId.eq(series.id))
+        .filter(potluck_round::Column::ScheduledOn.gte(Utc::now().date_naive()))
+        .order_by_asc(potluck_round::Column::Round)
+        .all(&txn)
+        .await?;
+
+    let mut rounds = Vec::new();
+    let mut changes = Vec::new();
+    for round in &upcoming {
+        if let Some(outcome) = place_late_joiners(&txn, &series, round).await? {
+            if !outcome.seated.is_empty() {
+                let seated: Vec<&str> = outcome
+                    .seated
+                    .iter()
+                    .map(|s| s.participant.name.as_str())
+                    .collect();
+                changes.push(Change::updated(
+                    AuditEntity::Round,
+                    round.id,
+                    &round_label(&series, round.round),
+                    "late_joiners",
+                    None,
+                    Some(seated.join(", ")),
+                ));
+            }
+            rounds.push(outcome);
+        }
+    }
+    audit::record(&txn, user.organization_id, Some(user.id), changes).await?;
+    txn.commit().await?;
+
+    render_series(
+        &state,
+        "_series_rounds.html",
+        &series,
+        Some(SeriesOutcome::LateJoiners { rounds }),
+    )
+    .await
+}
+
+pub async fn add_unavailability(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    Form(form): Form<UnavailabilityForm>,
+) -> Result<Html<String>, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    let participant = form
+        .participant
+        .parse::<ParticipantId>()
+        .map_err(|_| AppError::Unprocessable("Unknown participant".to_string()))?;
+    if form.ends_on < form.starts_on {
+        return Err(AppError::Unprocessable(
+            "The last day away must not be before the first".to_string(),
+        ));
+    }
+
+    let entry = unavailability::ActiveModel {
+        organization_id: Set(series.organization_id),
+        potluck_series_id: Set(series.id),
+        person_id: Set(participant.person_id()),
+        household_id: Set(participant.household_id()),
+        starts_on: Set(form.starts_on),
+        ends_on: Set(form.ends_on),
+        note: Set(form.note.filter(|n| !n.trim().is_empty())),
+        ..Default::default()
+    };
+    let txn = state.db.begin().await?;
+    let name = find_participant_name(&txn, series.organization_id, participant)
+        .await?
+        .ok_or_else(|| AppError::Unprocessable("Unknown participant".to_string()))?;
+    let entry = entry.insert(&txn).await?;
+    let change = Change::created(
+        AuditEntity::Unavailability,
+        entry.id,
+        &format!("{name}, {} to {}", entry.starts_on, entry.ends_on),
+    );
+    audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    txn.commit().await?;
+
+    render_series(&state, "_series_rounds.html", &series, None).await
+}
+
+pub async fn delete_unavailability(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, unavailability_id)): Path<(i32, i32)>,
+) -> Result<Html<String>, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+
+    let txn = state.db.begin().await?;
+    let entry = unavailability::Entity::find_by_id(unavailability_id)
+        .filter(unavailability::Column::PotluckSeriesId.eq(series.id))
+        .one(&txn)
+        .await?;
+    if let Some(entry) = entry {
+        unavailability::Entity::delete_by_id(entry.id)
+            .exec(&txn)
+            .await?;
+        let participant = ParticipantId::from_columns(entry.person_id, entry.household_id);
+        let name = match participant {
+            Some(participant) => {
+                participant_name(&txn, series.organization_id, participant).await?
+            }
+            None => String::new(),
+        };
+        let change = Change::deleted(
+            AuditEntity::Unavailability,
+            entry.id,
+            &format!("{name}, {} to {}", entry.starts_on, entry.ends_on),
+        );
+        audit::record(&txn, user.organization_id, Some(user.id), [change]).await?;
+    }
+    txn.commit().await?;
+
+    render_series(&state, "_series_rounds.html", &series, None).await
+}
+
+/// A column organizers can pick for a CSV export.
+#[derive(Serialize)]
+struct ExportColumn {
+    key: &'static str,
+    label: &'static str,
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    /// Column keys in the order wanted; repeated or comma separated.
+    #[serde(default)]
+    columns: Vec<String>,
+    /// Only this round of the groups export; empty for every round.
+    round: Option<String>,
+}
+
+fn csv_download(series: &potluck_series::Model, suffix: &str, body: Vec<u8>) -> Response {
+    let slug: String = series
+        .name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let filename = format!("{}-{suffix}.csv", slug.trim_matches('-'));
+    (
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+pub async fn export_roster(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    MultiQuery(params): MultiQuery<ExportParams>,
+) -> Result<Response, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+    let columns = export::select_columns(&RosterColumn::ALL, RosterColumn::key, &params.columns)
+        .map_err(AppError::BadRequest)?;
+
+    let participants = signed_up_participants(&state.db, series.organization_id).await?;
+    let mut body = Vec::new();
+    export::write_roster(&mut body, &participants, &columns).map_err(anyhow::Error::from)?;
+    Ok(csv_download(&series, "roster", body))
+}
+
+pub async fn export_groups(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+    MultiQuery(params): MultiQuery<ExportParams>,
+) -> Result<Response, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+    let columns = export::select_columns(&GroupColumn::ALL, GroupColumn::key, &params.columns)
+        .map_err(AppError::BadRequest)?;
+    let round = match params.round.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(round) => Some(
+            round
+                .parse::<i32>()
+                .map_err(|_| AppError::BadRequest("Unknown round".to_string()))?,
+        ),
+    };
+
+    let groups = match round {
+        Some(round) => load_round(&state.db, series.id, round).await?,
+        None => load_series(&state.db, series.id).await?,
+    };
+    let mut body = Vec::new();
+    export::write_groups(&mut body, &groups, &columns).map_err(anyhow::Error::from)?;
+    let suffix = match round {
+        Some(round) => format!("round-{round}"),
+        None => "groups".to_string(),
+    };
+    Ok(csv_download(&series, &suffix, body))
+}
+
+pub async fn round_packets(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path((id, round)): Path<(i32, i32)>,
+    Query(params): Query<PacketParams>,
+) -> Result<Response, AppError> {
+    let series = find_series(&state, user.organization_id, id).await?;
+    let packets = load_round_packets(&state.db, &series, round).await?;
+
+    let title = format!("{} - Round {round} host packets", series.name);
+    packet_response(
+        &state,
+        &title,
+        &format!("/series/{id}"),
+        &format!("round-{round}-host-packets"),
+        params.format,
+        &packets,
+    )
+}
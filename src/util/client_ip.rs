@@ -0,0 +1,33 @@
+//! Working out where a request came from, which depends on whether a
+//! reverse proxy sits in front of the app.
+
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderMap,
+};
+use std::net::{IpAddr, SocketAddr};
+
+/// The client's address. With `trust_proxy_headers`, the address the proxy
+/// reports in `X-Forwarded-For` or `X-Real-IP`; otherwise the peer of the
+/// connection, which behind a proxy is the proxy itself.
+pub fn client_ip(request: &Request, trust_proxy_headers: bool) -> Option<IpAddr> {
+    if trust_proxy_headers && let Some(ip) = forwarded_ip(request.headers()) {
+        return Some(ip);
+    }
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+}
+
+fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    // The first entry is the original client; proxies append themselves.
+    let forwarded_for = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next());
+    let real_ip = headers.get("x-real-ip").and_then(|v| v.to_str().ok());
+    forwarded_for
+        .or(real_ip)
+        .and_then(|ip| ip.trim().parse().ok())
+}
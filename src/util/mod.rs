@@ -1 +1,2 @@
-pub mod asset_loader;
\ No newline at end of file
+pub mod asset_loader;
+pub mod client_ip;